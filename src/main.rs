@@ -2,20 +2,206 @@
 extern crate lazy_static;
 extern crate clap;
 extern crate reqwest;
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
 
 mod poet;
 
 use crate::poet::*;
 
-fn handle_term_query(query: &str, dict: &dyn dictionary::Dictionary) {
-    if let Some(entry) = dict.lookup(query) {
+/// Looks up `query` and prints the words similar to each of its pronunciations, in `notation`.
+///
+/// If `query` isn't a word, it can be a pronunciation instead, in IPA or ARPABET, e.g.
+/// "/ˈblɪŋk/" or "B L IH1 NG K".
+fn handle_term_query(query: &str, dict: &dyn dictionary::Dictionary, notation: ipa::Notation) {
+    let similar = if let Some(entry) = dict.lookup(query) {
         println!("Found {:?}", entry);
-        for word in dict.similar(query).words {
+        dict.similar(query)
+    } else if let Ok(phonemes) = ipa::parse_pronunciation(query) {
+        println!("Read {} as {}", query, notation.format_syllables(&phonemes));
+        dict.similar_to_phonemes(&phonemes, None)
+    } else {
+        println!("Not found: {}", query);
+        return;
+    };
+    if similar.is_empty() {
+        println!("No similar words found.");
+        return;
+    }
+    println!("Found {} similar words.", similar.num_words());
+    for group in similar.groups {
+        let dict_key = match group.variant {
+            Some(v) if v != 1 => format!("{}({})", query, v),
+            _ => query.to_string(),
+        };
+        println!(
+            "Similar to {} ({}):",
+            dict_key,
+            notation.format_syllables(&group.phonemes)
+        );
+        for word in group.words {
             println!("\tsimilar word: {:?}", word);
         }
+    }
+}
+
+/// Handles the `poet dict` subcommands.
+///
+/// `add`, `update` and `remove` edit the user dictionary and save it, and `check` reports
+/// conflicts between the dictionaries.
+fn handle_dict_command(matches: &ArgMatches, shelf: &mut dictionary::Shelf) {
+    let result = match matches.subcommand() {
+        ("check", Some(_)) => {
+            let report = check::check_shelf(shelf);
+            print!("{}", report);
+            std::process::exit(if report.is_empty() { 0 } else { 1 });
+        }
+        ("add", Some(m)) => shelf
+            .add_user_entry(m.value_of("entry").unwrap())
+            .map(|e| format!("Added {} {}", e.dict_key(), e.phonemes)),
+        ("update", Some(m)) => shelf
+            .update_user_entry(m.value_of("entry").unwrap())
+            .map(|e| format!("Updated {} {}", e.dict_key(), e.phonemes)),
+        ("remove", Some(m)) => shelf
+            .remove_user_entry(m.value_of("key").unwrap())
+            .map(|e| format!("Removed {} {}", e.dict_key(), e.phonemes)),
+        _ => {
+            println!("{}", matches.usage());
+            std::process::exit(2);
+        }
+    };
+    match result {
+        Ok(message) => println!("{}", message),
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Handles `poet search`, printing the entries that match the pattern, one per line.
+fn handle_search_command(matches: &ArgMatches, dict: &dyn dictionary::Dictionary) {
+    let pattern: pattern::Pattern = match matches.value_of("pattern").unwrap().parse() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let limit = match matches.value_of("limit").map(|l| l.parse::<usize>()) {
+        None => usize::MAX,
+        Some(Ok(limit)) => limit,
+        Some(Err(e)) => {
+            println!("Error: invalid --limit: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let found = dict.search(&pattern);
+    for entry in found.iter().take(limit) {
+        println!("{} {}", entry.dict_key(), entry.phonemes);
+    }
+    if found.len() > limit {
+        println!("Showing {} of {} matching words.", limit, found.len());
     } else {
-        println!("Not found: {}", query);
+        println!("Found {} matching words.", found.len());
+    }
+    if found.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Handles `poet meter`, printing the words with a stress pattern, grouped by the
+/// pronunciation of the rhyme word if there is one.
+fn handle_meter_command(matches: &ArgMatches, dict: &dyn dictionary::Dictionary) {
+    let fail = |message: String| -> ! {
+        println!("Error: {}", message);
+        std::process::exit(2);
+    };
+    let mut query = dictionary::StressQuery::new(matches.value_of("stress").unwrap())
+        .unwrap_or_else(|e| fail(e));
+    if let Some(onset) = matches.value_of("onset") {
+        query.onset = phoneme::parse_sequence(onset).unwrap_or_else(|e| fail(e));
+    }
+    let limit = match matches.value_of("limit").map(|l| l.parse::<usize>()) {
+        None => usize::MAX,
+        Some(Ok(limit)) => limit,
+        Some(Err(e)) => fail(format!("invalid --limit: {}", e)),
+    };
+    let rhyme = matches.value_of("rhyme");
+    let result =
+        dictionary::by_stress_rhyming_with(dict, &query, rhyme).unwrap_or_else(|e| fail(e));
+    for group in &result.groups {
+        match (rhyme, group.variant) {
+            (Some(word), Some(v)) if v != 1 => {
+                println!("Rhyming with {}({}) ({}):", word, v, group.phonemes)
+            }
+            (Some(word), _) => println!("Rhyming with {} ({}):", word, group.phonemes),
+            (None, _) => {}
+        }
+        for word in group.words.iter().take(limit) {
+            println!("{} {}", word.word, word.phonemes);
+        }
+        if group.words.len() > limit {
+            println!("Showing {} of {} words.", limit, group.words.len());
+        }
+    }
+    println!("Found {} words.", result.num_words());
+    if result.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Handles `poet homophones` and `poet minimal-pairs`, printing the words for each
+/// pronunciation of the word, one per line.
+fn handle_soundalike_command(
+    command: &str,
+    matches: &ArgMatches,
+    dict: &dyn dictionary::Dictionary,
+) {
+    let word = matches.value_of("word").unwrap();
+    let variants = match dict.lookup(word) {
+        Some(variants) => variants,
+        None => {
+            println!("Error: {} isn't in the dictionary", word);
+            std::process::exit(2);
+        }
+    };
+    let mut found = 0;
+    for entry in variants {
+        let (label, result) = match command {
+            "homophones" => ("Homophones", dict.homophones(&entry.phonemes, Some(word))),
+            _ => (
+                "Minimal pairs",
+                dict.minimal_pairs(&entry.phonemes, Some(word)),
+            ),
+        };
+        println!("{} of {} ({}):", label, entry.dict_key(), entry.phonemes);
+        for word in &result.groups[0].words {
+            println!("{} {}", word.word, word.phonemes);
+        }
+        found += result.num_words();
+    }
+    println!("Found {} words.", found);
+    if found == 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Handles `poet dict compile`, which writes the compiled index for the dictionary at `path`.
+fn compile_dictionary(path: &str, mode: dictionary::LoadMode) {
+    match dictionary::DictionaryImpl::compile_index(path, mode) {
+        Ok(dict) => {
+            println!(
+                "Compiled {} terms from {} into {}.",
+                dict.len(),
+                path,
+                dictionary::index_path(path)
+            );
+            dictionary::report_skipped_lines(&dict);
+        }
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
@@ -30,9 +216,19 @@ async fn main() {
             Arg::with_name("dict")
                 .short("d")
                 .long("dict")
-                .value_name("FILE")
-                .help("Path to the cmudict.dict dictionary file.")
-                .takes_value(true),
+                .value_name("[FORMAT:]FILE")
+                .help(
+                    "Path to a dictionary file, ./cmudict.dict by default. Repeat it to stack \
+                     dictionaries, e.g. a British lexicon on top of cmudict. The format can be \
+                     cmudict, cmudict-nostress (without stress digits), ipa-tsv (ipa-dict's \
+                     word-tab-IPA lines) or json, e.g. ipa-tsv:en_UK.txt; otherwise it's ipa-tsv \
+                     for .tsv files, json for .json files, and cmudict for the rest.",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|s| s.parse::<lexicon::Source>().map(|_| ()))
+                .global(true),
         )
         .arg(
             Arg::with_name("userdict")
@@ -40,14 +236,70 @@ async fn main() {
                 .long("userdict")
                 .value_name("FILE")
                 .help("Path to the user dictionary, in cmudict format.")
-                .takes_value(true),
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("merge")
+                .long("merge")
+                .value_name("POLICY")
+                .help(
+                    "How to combine pronunciations of words in both the dictionary and the user \
+                     dictionary: 'override' uses only the user dictionary's, 'append' adds them \
+                     as extra variants, and 'append-new' (the default) adds only new ones.",
+                )
+                .possible_values(&["override", "append", "append-new"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("frequencies")
+                .long("frequencies")
+                .value_name("FILE")
+                .help(
+                    "Path to a word frequency list, with a word and its count on each line, for \
+                     ranking and filtering suggestions by how common they are. Defaults to \
+                     ./frequencies.txt, if it exists.",
+                )
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("dialect")
+                .long("dialect")
+                .value_name("DIALECT")
+                .help(
+                    "The dialect to check rhymes and syllables in when analyzing text, e.g. \
+                     'british' to drop R after vowels. This is also the server's default.",
+                )
+                .possible_values(&["general-american", "canadian", "british", "australian"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("ipa")
+                .long("ipa")
+                .help("Shows pronunciations in IPA instead of ARPABET.")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help(
+                    "Fail if a dictionary file has a malformed line, instead of skipping it \
+                     with a warning.",
+                )
+                .global(true),
         )
         .arg(
             Arg::with_name("query")
                 .short("q")
                 .long("query")
                 .value_name("WORD")
-                .help("Looks up WORD in the dictionary and returns info on it.")
+                .help(
+                    "Looks up WORD in the dictionary and returns info on it. WORD can also be \
+                     a pronunciation, in IPA (e.g. /ˈblɪŋk/) or ARPABET (e.g. 'B L IH1 NG K').",
+                )
                 .takes_value(true)
                 .conflicts_with_all(&["input", "server"]),
         )
@@ -66,36 +318,218 @@ async fn main() {
                 .long("server")
                 .help("Launches the poet web server."),
         )
+        .arg(
+            Arg::with_name("edit-token")
+                .long("edit-token")
+                .value_name("TOKEN")
+                .env("POET_EDIT_TOKEN")
+                .help(
+                    "Allows editing the user dictionary through the server's /api/userdict \
+                     endpoints, for requests with the header 'Authorization: Bearer TOKEN'.",
+                )
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about(
+                    "Lists the words whose phonemes match PATTERN, e.g. \"* AE1 T\" for words \
+                     ending in AE1 T, or \"stress:10 * AH0 N\". Terms can be phonemes, ? for \
+                     any phoneme, * for any run of them, classes like vowel, vowel1, stop or \
+                     nasal, and syllables:N or syllables:MIN-MAX.",
+                )
+                .arg(
+                    Arg::with_name("pattern")
+                        .value_name("PATTERN")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .help("Lists at most N words.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("meter")
+                .about(
+                    "Lists the words with a stress pattern, e.g. 010 for da-DUM-da, using ? for \
+                     any stress.",
+                )
+                .arg(Arg::with_name("stress").value_name("STRESS").required(true))
+                .arg(
+                    Arg::with_name("rhyme")
+                        .long("rhyme")
+                        .value_name("WORD")
+                        .help("Only lists words that rhyme with WORD.")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("onset")
+                        .long("onset")
+                        .value_name("PHONEMES")
+                        .help("Only lists words that start with these sounds, e.g. \"K R\".")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .help("Lists at most N words for each pronunciation of the rhyme.")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("homophones")
+                .about("Lists the words that sound exactly like WORD, e.g. \"pear\" for \"pair\".")
+                .arg(Arg::with_name("word").value_name("WORD").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("minimal-pairs")
+                .about(
+                    "Lists the words one sound away from WORD, by changing, adding or removing \
+                     a phoneme, e.g. \"sheep\" and \"ships\" for \"ship\".",
+                )
+                .arg(Arg::with_name("word").value_name("WORD").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("dict")
+                .about("Edits or checks the user dictionary.")
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Adds an entry, e.g. poet dict add \"word W ER1 D\".")
+                        .arg(Arg::with_name("entry").value_name("ENTRY").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("update")
+                        .about("Replaces the phonemes of an entry, e.g. \"word(2) W AO1 R D\".")
+                        .arg(Arg::with_name("entry").value_name("ENTRY").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Removes an entry by its key, e.g. \"word\" or \"word(2)\".")
+                        .arg(Arg::with_name("key").value_name("KEY").required(true)),
+                )
+                .subcommand(SubCommand::with_name("check").about(
+                    "Reports user dictionary entries that duplicate or conflict with the \
+                     dictionary, or that look wrong, and lines that couldn't be loaded.",
+                ))
+                .subcommand(SubCommand::with_name("compile").about(
+                    "Compiles each cmudict-format dictionary (--dict) into a binary index beside \
+                     it, e.g. cmudict.dict.idx, which later runs load much faster until the \
+                     dictionary changes.",
+                )),
+        )
         .get_matches();
 
-    let cmudict_path = matches.value_of("dict").unwrap_or("./cmudict.dict");
+    let sources: Vec<lexicon::Source> = match matches.values_of("dict") {
+        Some(values) => values.map(|v| v.parse().unwrap()).collect(),
+        None => vec!["./cmudict.dict".parse().unwrap()],
+    };
     let userdict_path = matches.value_of("userdict").unwrap_or("./userdict.dict");
 
+    let load_mode = if matches.is_present("strict") {
+        dictionary::LoadMode::Strict
+    } else {
+        dictionary::LoadMode::Lenient
+    };
+
+    if let Some(("compile", Some(_))) = matches.subcommand_matches("dict").map(|m| m.subcommand()) {
+        for source in &sources {
+            if source.format != lexicon::Format::Cmudict {
+                println!(
+                    "Skipping {}: only cmudict files can be compiled.",
+                    source.path
+                );
+                continue;
+            }
+            compile_dictionary(&source.path, load_mode);
+        }
+        return;
+    }
+
     let mut shelf = poet::dictionary::Shelf::new();
-    shelf
-        .init_cmudict(cmudict_path)
-        .expect("Failed to read cmudict file!");
+    if let Some(policy) = matches.value_of("merge") {
+        shelf.set_merge_policy(policy.parse().unwrap());
+    }
+    shelf.set_load_mode(load_mode);
+    for source in &sources {
+        if let Err(e) = shelf.init_dictionary(&source.path, source.format) {
+            println!("Failed to read dictionary file. Error={}", e);
+            std::process::exit(1);
+        }
+    }
     if let Err(e) = shelf.init_userdict(userdict_path) {
+        if matches.is_present("strict") {
+            println!("Failed to read userdict file. Error={}", e);
+            std::process::exit(1);
+        }
         println!(
             "Failed to read userdict file. Skipping and continuing. Error={}",
             e
         );
     }
+    let frequencies_path = matches.value_of("frequencies");
+    let frequencies_path = frequencies_path.unwrap_or("./frequencies.txt");
+    if matches.is_present("frequencies") || std::path::Path::new(frequencies_path).exists() {
+        if let Err(e) = shelf.init_frequencies(frequencies_path) {
+            if matches.is_present("strict") {
+                println!("Failed to read frequencies file. Error={}", e);
+                std::process::exit(1);
+            }
+            println!(
+                "Failed to read frequencies file. Skipping and continuing. Error={}",
+                e
+            );
+        }
+    }
+
+    if let Some(dict_matches) = matches.subcommand_matches("dict") {
+        handle_dict_command(dict_matches, &mut shelf);
+        return;
+    }
+
+    if let Some(search_matches) = matches.subcommand_matches("search") {
+        handle_search_command(search_matches, shelf.over_all());
+        return;
+    }
+
+    if let Some(meter_matches) = matches.subcommand_matches("meter") {
+        handle_meter_command(meter_matches, shelf.over_all());
+        return;
+    }
+
+    for command in ["homophones", "minimal-pairs"] {
+        if let Some(soundalike_matches) = matches.subcommand_matches(command) {
+            handle_soundalike_command(command, soundalike_matches, shelf.over_all());
+            return;
+        }
+    }
+
+    let notation = if matches.is_present("ipa") {
+        ipa::Notation::Ipa
+    } else {
+        ipa::Notation::Arpabet
+    };
+    let dialect: dialect::Dialect = matches
+        .value_of("dialect")
+        .map_or(dialect::Dialect::default(), |d| d.parse().unwrap());
 
     if let Some(q) = matches.value_of("query") {
         // TODO: Exit with a failure status value if lookup failed.
-        handle_term_query(q, shelf.over_all());
+        handle_term_query(q, shelf.over_all(), notation);
         return;
     }
 
     if let Some(path) = matches.value_of("input") {
         // TODO: Handle errors more gracefully.
-        snippet::analyze_one_file_to_terminal(path, shelf.over_all());
+        snippet::analyze_one_file_to_terminal(path, shelf.over_all(), dialect, notation);
         return;
     }
 
     if matches.is_present("server") {
-        server::run(shelf).await;
+        let edit_token = matches.value_of("edit-token").map(String::from);
+        server::run(shelf, edit_token, dialect).await;
         return;
     }
 
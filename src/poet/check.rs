@@ -0,0 +1,398 @@
+//! Checks the dictionaries on a `Shelf` for conflicts and suspicious entries.
+//!
+//! The first dictionary on the shelf is the base (usually cmudict). Every entry in the later
+//! dictionaries (usually the user dictionary) is compared against the dictionaries before it,
+//! and checked on its own for missing phonemes and for syllable counts that don't fit the
+//! spelling. Lines that couldn't be loaded from any dictionary, e.g. with phonemes that aren't
+//! ARPABET, are reported too.
+//!
+//! ```
+//! let report = check::check_shelf(&shelf);
+//! print!("{}", report);
+//! ```
+use std::fmt;
+
+use crate::poet::dictionary::{DictionaryError, Entry, Shelf};
+use crate::poet::guess;
+
+/// The kinds of problems found by `check_shelf`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// The line couldn't be loaded, e.g. because a phoneme isn't ARPABET.
+    Malformed,
+    /// The same word and pronunciation is already in an earlier dictionary.
+    Duplicate,
+    /// The word is in an earlier dictionary, but with other pronunciations.
+    DiffersFromBase,
+    /// The same variant, e.g. "word(2)", is in an earlier dictionary with other phonemes.
+    VariantConflict,
+    /// The entry has no phonemes at all.
+    MissingPhonemes,
+    /// The number of syllables is far from what the spelling suggests.
+    ImplausibleSyllables,
+}
+
+impl IssueKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            IssueKind::Malformed => "lines that couldn't be loaded",
+            IssueKind::Duplicate => "duplicates of earlier entries",
+            IssueKind::DiffersFromBase => "pronunciations that differ from earlier dictionaries",
+            IssueKind::VariantConflict => "variant numbers used by earlier dictionaries",
+            IssueKind::MissingPhonemes => "entries without phonemes",
+            IssueKind::ImplausibleSyllables => "implausible syllable counts",
+        }
+    }
+}
+
+/// One problem with one entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// The entry's key in its dictionary, e.g. "word(2)". This is empty for malformed lines
+    /// that don't name a word.
+    pub dict_key: String,
+    /// The dictionary the entry is in.
+    pub source: String,
+    /// The 1-based line in `source`, for malformed lines.
+    pub line: Option<usize>,
+    /// A human-readable explanation.
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.source, line, self.message),
+            None if self.dict_key.is_empty() => write!(f, "{}: {}", self.source, self.message),
+            None => write!(f, "{}: {}: {}", self.source, self.dict_key, self.message),
+        }
+    }
+}
+
+/// The results of `check_shelf`.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The number of entries that were checked, i.e. those in all but the first dictionary.
+    pub num_checked: usize,
+    /// The issues found, sorted by kind, source, line and key.
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    /// Returns the issues of the given kind.
+    pub fn of_kind(&self, kind: IssueKind) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(move |i| i.kind == kind)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Checked {} entries; found {} issues.",
+            self.num_checked,
+            self.issues.len()
+        )?;
+        let mut last_kind = None;
+        for issue in &self.issues {
+            if last_kind != Some(issue.kind) {
+                let count = self.of_kind(issue.kind).count();
+                writeln!(f, "\n{} {}:", count, issue.kind.description())?;
+                last_kind = Some(issue.kind);
+            }
+            writeln!(f, "  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// How far the syllable count can be from the spelling's estimate before it's reported.
+const MAX_SYLLABLE_DIFFERENCE: i32 = 2;
+
+/// Compares every dictionary on `shelf` after the first against the ones before it, and checks
+/// their entries for missing phonemes and implausible syllable counts. The lines that every
+/// dictionary skipped while loading are reported as `IssueKind::Malformed`.
+pub fn check_shelf(shelf: &Shelf) -> Report {
+    let mut report = Report::default();
+    let layers: Vec<_> = shelf.layers().collect();
+    for layer in &layers {
+        report
+            .issues
+            .extend(layer.skipped_lines().iter().map(malformed_issue));
+    }
+    for (i, layer) in layers.iter().enumerate().skip(1) {
+        let mut terms: Vec<&str> = layer.terms().collect();
+        terms.sort_unstable();
+        for term in terms {
+            let earlier: Vec<&Entry> = layers[..i]
+                .iter()
+                .filter_map(|d| d.lookup(term))
+                .flatten()
+                .collect();
+            for entry in layer.lookup(term).unwrap() {
+                report.num_checked += 1;
+                check_against_earlier(entry, &earlier, &mut report.issues);
+                check_phonemes(entry, &mut report.issues);
+                check_syllables(entry, &mut report.issues);
+            }
+        }
+    }
+    report.issues.sort_by(|a, b| {
+        (a.kind, &a.source, a.line, &a.dict_key).cmp(&(b.kind, &b.source, b.line, &b.dict_key))
+    });
+    return report;
+}
+
+fn issue(kind: IssueKind, entry: &Entry, message: String) -> Issue {
+    Issue {
+        kind,
+        dict_key: entry.dict_key(),
+        source: entry.source.as_deref().unwrap_or("(unnamed)").to_string(),
+        line: None,
+        message,
+    }
+}
+
+fn malformed_issue(error: &DictionaryError) -> Issue {
+    let (source, line, dict_key, message) = match error {
+        DictionaryError::Io { path, error } => (path, None, String::new(), error.to_string()),
+        DictionaryError::Malformed { path, line, reason } => {
+            (path, Some(*line), String::new(), reason.clone())
+        }
+        DictionaryError::MalformedEntry { path, word, reason } => {
+            (path, None, word.clone(), reason.clone())
+        }
+    };
+    Issue {
+        kind: IssueKind::Malformed,
+        dict_key,
+        source: source.clone(),
+        line,
+        message,
+    }
+}
+
+/// Returns e.g. "red (R EH1 D) in cmudict.dict".
+fn describe(entry: &Entry) -> String {
+    format!(
+        "{} ({}) in {}",
+        entry.dict_key(),
+        entry.phonemes,
+        entry.source.as_deref().unwrap_or("(unnamed)")
+    )
+}
+
+fn check_against_earlier(entry: &Entry, earlier: &[&Entry], issues: &mut Vec<Issue>) {
+    if earlier.is_empty() {
+        return;
+    }
+    if let Some(same) = earlier.iter().find(|e| e.phonemes == entry.phonemes) {
+        let message = format!("{} is a duplicate of {}", entry.phonemes, describe(same));
+        issues.push(issue(IssueKind::Duplicate, entry, message));
+        return;
+    }
+    if let Some(same_variant) = earlier.iter().find(|e| e.variant == entry.variant) {
+        let message = format!(
+            "{} conflicts with {}",
+            entry.phonemes,
+            describe(same_variant)
+        );
+        issues.push(issue(IssueKind::VariantConflict, entry, message));
+    }
+    let others: Vec<String> = earlier.iter().map(|e| describe(e)).collect();
+    let message = format!("{} differs from {}", entry.phonemes, others.join(", "));
+    issues.push(issue(IssueKind::DiffersFromBase, entry, message));
+}
+
+fn check_phonemes(entry: &Entry, issues: &mut Vec<Issue>) {
+    if entry.phonemes.phonemes.is_empty() {
+        let message = "has no phonemes".to_string();
+        issues.push(issue(IssueKind::MissingPhonemes, entry, message));
+    }
+}
+
+fn check_syllables(entry: &Entry, issues: &mut Vec<Issue>) {
+    // The spelling guesser is crude, but good enough to catch e.g. a missing vowel.
+    let expected = match guess::spelling_candidate(&entry.word) {
+        Some(candidate) => candidate.phonemes.num_syllables(),
+        None => return,
+    };
+    let actual = entry.num_syllables();
+    if actual == 0 {
+        let message = format!("{} has no vowels, so no syllables", entry.phonemes);
+        issues.push(issue(IssueKind::ImplausibleSyllables, entry, message));
+    } else if (actual - expected).abs() >= MAX_SYLLABLE_DIFFERENCE {
+        let message = format!(
+            "{} has {} syllables, but the spelling suggests about {}",
+            entry.phonemes, actual, expected
+        );
+        issues.push(issue(IssueKind::ImplausibleSyllables, entry, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::{DictionaryImpl, LoadMode, Phonemes};
+    use crate::poet::lexicon;
+
+    fn test_shelf(user_entries: Vec<&str>) -> Shelf {
+        let mut base = DictionaryImpl::with_name("cmudict.dict");
+        base.insert_all(&vec![
+            "red R EH1 D",
+            "read R EH1 D",
+            "read(2) R IY1 D",
+            "tomato T AH0 M EY1 T OW2",
+        ]);
+        let mut user = DictionaryImpl::with_name("userdict.dict");
+        user.insert_all(&user_entries);
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(base));
+        shelf.push_dictionary(Box::new(user));
+        shelf
+    }
+
+    /// Returns (kind, dict_key) for each issue.
+    fn issues(report: &Report) -> Vec<(IssueKind, &str)> {
+        report
+            .issues
+            .iter()
+            .map(|i| (i.kind, i.dict_key.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn clean_userdict_has_no_issues() {
+        let report = check_shelf(&test_shelf(vec!["zed Z EH1 D", "poesy P OW1 AH0 Z IY0"]));
+        assert_eq!(report.num_checked, 2);
+        assert!(report.is_empty());
+        assert_eq!(report.to_string(), "Checked 2 entries; found 0 issues.\n");
+    }
+
+    #[test]
+    fn finds_duplicates_differences_and_conflicts() {
+        let report = check_shelf(&test_shelf(vec![
+            "red R EH1 D",
+            "read(3) R IY1 D",
+            "read(2) R AY1 D",
+            "tomato(2) T AH0 M AA1 T OW2",
+        ]));
+        assert_eq!(
+            issues(&report),
+            vec![
+                (IssueKind::Duplicate, "read(3)"),
+                (IssueKind::Duplicate, "red"),
+                (IssueKind::DiffersFromBase, "read(2)"),
+                (IssueKind::DiffersFromBase, "tomato(2)"),
+                (IssueKind::VariantConflict, "read(2)"),
+            ]
+        );
+        assert_eq!(
+            report.issues[1].to_string(),
+            "userdict.dict: red: R EH1 D is a duplicate of red (R EH1 D) in cmudict.dict"
+        );
+        assert_eq!(
+            report
+                .of_kind(IssueKind::VariantConflict)
+                .next()
+                .unwrap()
+                .message,
+            "R AY1 D conflicts with read(2) (R IY1 D) in cmudict.dict"
+        );
+    }
+
+    #[test]
+    fn finds_missing_phonemes() {
+        let mut shelf = test_shelf(vec![]);
+        let mut user = DictionaryImpl::with_name("userdict.dict");
+        // Parsing rejects lines without phonemes, so this has to be built directly.
+        user.insert(Entry {
+            word: "zed".to_string(),
+            phonemes: Phonemes::from_phonemes(vec![]),
+            variant: 1,
+            source: None,
+            metadata: None,
+        });
+        shelf.push_dictionary(Box::new(user));
+        let report = check_shelf(&shelf);
+        let missing: Vec<&Issue> = report.of_kind(IssueKind::MissingPhonemes).collect();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(
+            missing[0].to_string(),
+            "userdict.dict: zed: has no phonemes"
+        );
+    }
+
+    #[test]
+    fn reports_lines_that_could_not_be_loaded() {
+        let path = std::env::temp_dir().join(format!("poet-{}-check.dict", std::process::id()));
+        std::fs::write(&path, "red R EH1 D\nbed B QX1 D\nfed\n").unwrap();
+        let path = path.to_str().unwrap();
+        let base = DictionaryImpl::new_from_cmudict_file(path, LoadMode::Lenient).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let user = lexicon::from_text(
+            "userdict.json",
+            r#"{"zed": ["Z EH1 D"], "qed": 5}"#,
+            lexicon::Format::Json,
+            LoadMode::Lenient,
+        )
+        .unwrap();
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(base));
+        shelf.push_dictionary(Box::new(user));
+
+        let report = check_shelf(&shelf);
+        let malformed: Vec<String> = report
+            .of_kind(IssueKind::Malformed)
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(malformed.len(), 3);
+        assert!(malformed[0].starts_with(&format!("{}:2: ", path)));
+        assert!(malformed[0].contains("QX1"));
+        assert!(malformed[1].starts_with(&format!("{}:3: ", path)));
+        assert!(malformed[2].starts_with("userdict.json: qed: "));
+        assert!(report
+            .to_string()
+            .contains("\n3 lines that couldn't be loaded:\n"));
+    }
+
+    #[test]
+    fn finds_implausible_syllable_counts() {
+        let report = check_shelf(&test_shelf(vec![
+            "cat K AE1 T",
+            "catamaran K AE1 T",
+            "bee B IY1 AH0 IY0 AH0",
+        ]));
+        assert_eq!(
+            issues(&report)
+                .into_iter()
+                .filter(|(kind, _)| *kind == IssueKind::ImplausibleSyllables)
+                .collect::<Vec<_>>(),
+            vec![
+                (IssueKind::ImplausibleSyllables, "bee"),
+                (IssueKind::ImplausibleSyllables, "catamaran"),
+            ]
+        );
+        let text = report.to_string();
+        assert!(text.contains("\n2 implausible syllable counts:\n"));
+        assert!(
+            text.contains("catamaran: K AE1 T has 1 syllables, but the spelling suggests about 4")
+        );
+    }
+
+    #[test]
+    fn only_one_dictionary_checks_nothing() {
+        let mut shelf = Shelf::new();
+        let mut base = DictionaryImpl::with_name("cmudict.dict");
+        base.insert_all(&vec!["zed Z EH1 D"]);
+        shelf.push_dictionary(Box::new(base));
+        let report = check_shelf(&shelf);
+        assert_eq!(report.num_checked, 0);
+        assert!(report.is_empty());
+    }
+}
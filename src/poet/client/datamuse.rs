@@ -7,6 +7,7 @@
 //! See <https://www.datamuse.com/api/>.
 
 use rocket::serde::Deserialize;
+use std::sync::Arc;
 use url::Url;
 
 use crate::poet::*;
@@ -46,6 +47,12 @@ struct UrlBuilder {
     ///
     /// Default `true`, because it's common right now.
     want_pronunciation: bool,
+
+    /// Metadata Flag 'p': Whether to return the parts of speech.
+    want_parts_of_speech: bool,
+
+    /// Metadata Flag 'f': Whether to return the word frequency.
+    want_frequency: bool,
 }
 
 impl UrlBuilder {
@@ -59,6 +66,8 @@ impl UrlBuilder {
             max: None,
             want_syllable_count: false,
             want_pronunciation: true,
+            want_parts_of_speech: false,
+            want_frequency: false,
         }
     }
 
@@ -116,6 +125,18 @@ impl UrlBuilder {
         self
     }
 
+    /// Requests that the results include their parts of speech `&md=p`.
+    fn want_parts_of_speech(mut self) -> Self {
+        self.want_parts_of_speech = true;
+        self
+    }
+
+    /// Requests that the results include their frequency per million words `&md=f`.
+    fn want_frequency(mut self) -> Self {
+        self.want_frequency = true;
+        self
+    }
+
     /// Builds and returns the request URL.
     fn build(&self) -> String {
         let mut url = Url::parse("https://api.datamuse.com/words?").unwrap();
@@ -149,6 +170,12 @@ impl UrlBuilder {
         if self.want_pronunciation {
             md_flags.push('r');
         }
+        if self.want_parts_of_speech {
+            md_flags.push('p');
+        }
+        if self.want_frequency {
+            md_flags.push('f');
+        }
         if md_flags.len() > 0 {
             query_pairs.append_pair("md", &md_flags);
         }
@@ -171,8 +198,8 @@ impl UrlBuilder {
 /// ```
 ///
 /// The `pron:` tags have phonemes. Other tags include `f:<float>` for word frequency per
-/// million words, and `n`, `v`, `adj`, `adv`, and `u` for parts of speech. Neither of these
-/// (`md=p` and `md=f`) are currently requested.
+/// million words (with `md=f`), and `n`, `v`, `adj`, `adv`, and `u` for parts of speech (with
+/// `md=p`), which become the entry's `metadata`.
 #[allow(non_snake_case)] // Field names have to match the remote JSON API.
 #[derive(Debug, Deserialize)]
 #[serde(crate = "rocket::serde")]
@@ -184,19 +211,32 @@ struct WordsApiItem {
 }
 
 impl WordsApiItem {
-    /// Converts this response element to an `Entry`.
+    /// Converts this response element to an `Entry`, with any metadata tags as its `metadata`.
     ///
     /// Requires that the item has a `pron:` tag (enabled by default on all requests).
-    fn to_entry(&self) -> dictionary::Entry {
+    ///
+    /// Returns the reason if the pronunciation has anything that isn't a phoneme.
+    fn to_entry(&self) -> Result<dictionary::Entry, String> {
+        let mut metadata = metadata::EntryMetadata::default();
+        let mut pron = "";
         for t in &self.tags {
-            if t.starts_with("pron:") {
+            if let Some(phonemes) = t.strip_prefix("pron:") {
                 // TODO: Check for multiple pronunciations. I haven't seen any in the responses
                 // thus far, though it might be good to warn if one appears.
-                return dictionary::Entry::from_parts(&self.word, &t[5..]);
+                if pron.is_empty() {
+                    pron = phonemes;
+                }
+            } else {
+                // Other tags, like "query" for the query echo, are ignored.
+                metadata.add_tag(t);
             }
         }
-        // TODO: This is not ideal, but ok for now.
-        return dictionary::Entry::from_parts(&self.word, "");
+        // TODO: An empty pronunciation is not ideal, but ok for now.
+        let mut entry = dictionary::Entry::from_parts(&self.word, pron)?;
+        if !metadata.is_empty() {
+            entry.metadata = Some(Arc::new(metadata));
+        }
+        return Ok(entry);
     }
 }
 
@@ -215,7 +255,12 @@ impl Client {
         &self,
         term: &str,
     ) -> Result<Option<dictionary::Entry>, Box<dyn std::error::Error>> {
-        let url = UrlBuilder::new().spelled_like(term).query_echo().build();
+        let url = UrlBuilder::new()
+            .spelled_like(term)
+            .query_echo()
+            .want_parts_of_speech()
+            .want_frequency()
+            .build();
         let resp = self.do_words_request(&url).await?;
 
         if resp.is_empty() {
@@ -233,7 +278,7 @@ impl Client {
                 &r.word, term
             );
         }
-        return Ok(Some(r.to_entry()));
+        return Ok(Some(r.to_entry()?));
     }
 
     /// Issues a request to `url` and parses the result as a `/words` api call.
@@ -288,6 +333,17 @@ mod tests {
                 .build(),
             "https://api.datamuse.com/words?sp=flower&qe=sp&max=42&md=r"
         );
+
+        // Testing with metadata.
+        assert_eq!(
+            UrlBuilder::new()
+                .spelled_like("flower")
+                .query_echo()
+                .want_parts_of_speech()
+                .want_frequency()
+                .build(),
+            "https://api.datamuse.com/words?sp=flower&qe=sp&max=1&md=rpf"
+        );
     }
 
     #[test]
@@ -301,10 +357,35 @@ mod tests {
                 String::from("f:51.905237"),
             ],
         };
-        let entry = input.to_entry();
+        let entry = input.to_entry().unwrap();
         assert_eq!(entry.word, "flowers");
         assert_eq!(entry.variant, 1);
         assert_eq!(entry.phonemes.phonemes, vec!["F", "L", "AW1", "ER0", "Z"]);
         assert_eq!(entry.phonemes.num_syllables(), 2);
+        assert_eq!(entry.metadata.unwrap().frequency, Some(51905));
+
+        let input = WordsApiItem {
+            word: String::from("light"),
+            score: 3000,
+            numSyllables: None,
+            tags: vec![
+                String::from("query"),
+                String::from("n"),
+                String::from("v"),
+                String::from("adj"),
+                String::from("pron:L AY1 T "),
+            ],
+        };
+        let entry = input.to_entry().unwrap();
+        assert_eq!(entry.phonemes.phonemes, vec!["L", "AY1", "T"]);
+        assert_eq!(entry.metadata.unwrap().to_string(), "n v adj");
+
+        let input = WordsApiItem {
+            word: String::from("zzz"),
+            score: 1,
+            numSyllables: None,
+            tags: vec![String::from("pron:Z ")],
+        };
+        assert_eq!(input.to_entry().unwrap().metadata, None);
     }
 }
@@ -0,0 +1,269 @@
+//! Dialect profiles, for checking rhymes and syllables as they sound outside General American.
+//!
+//! cmudict spells words as they're said in General American English, so a poem written with
+//! British or Australian English in mind can fail to rhyme where it does for its reader (e.g.
+//! "farther" and "father", which sound alike without the R) or pass where it doesn't. A
+//! `Dialect` rewrites a word's `Phonemes` with a few sound changes before they're compared:
+//!
+//! ```
+//! let farther = Dialect::British.rewrite(&Entry::new("farther F AA1 R DH ER0").phonemes);
+//! assert_eq!(farther.to_string(), "F AA1 DH AH0");
+//! ```
+//!
+//! The rules go by sound alone, so they are approximations: words that are exceptions in the
+//! dialect, like "mass" (which keeps the vowel of "trap" in British English), aren't modeled.
+use crate::poet::dictionary::Phonemes;
+use crate::poet::phoneme::{Phoneme, Stress, Symbol};
+use std::fmt;
+
+/// A sound change that a dialect makes, relative to General American.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// A final "-ary", "-ery" or "-ory" loses its vowel, e.g. "secretary" has three syllables.
+    /// This only applies when the vowel has secondary stress and doesn't follow an R.
+    WeakAry,
+    /// AE becomes AA before F, TH or S and then a consonant or the end of the word, e.g. in
+    /// "bath", "grass" and "after".
+    TrapBath,
+    /// AE also becomes AA before N and then T, S or CH, e.g. in "dance" and "can't".
+    TrapBathNasal,
+    /// AO becomes AA, except before R, so "caught" sounds like "cot".
+    CotCaught,
+    /// R is dropped after a vowel unless another vowel follows, and unstressed ER becomes AH,
+    /// so "farther" sounds like "father". This should come after the rules that look for R.
+    NonRhotic,
+}
+
+impl Rule {
+    /// Applies the rule to `phonemes`.
+    fn apply(&self, phonemes: &[Phoneme]) -> Vec<Phoneme> {
+        let is_vowel_at = |i: usize| phonemes.get(i).is_some_and(|ph| ph.is_vowel());
+        let is_at = |i: usize, symbols: &[Symbol]| {
+            phonemes
+                .get(i)
+                .is_some_and(|ph| symbols.contains(&ph.symbol()))
+        };
+        let mut out = Vec::with_capacity(phonemes.len());
+        for (i, ph) in phonemes.iter().enumerate() {
+            let symbol = ph.symbol();
+            match self {
+                Rule::WeakAry => {
+                    let n = phonemes.len();
+                    if n >= 4
+                        && i == n - 3
+                        && ph.stress() == Some(Stress::Secondary)
+                        && is_at(i + 1, &[Symbol::R])
+                        && phonemes[n - 1].symbol() == Symbol::IY
+                        && !is_at(i - 1, &[Symbol::R])
+                    {
+                        continue;
+                    }
+                }
+                Rule::TrapBath => {
+                    if symbol == Symbol::AE
+                        && is_at(i + 1, &[Symbol::F, Symbol::TH, Symbol::S])
+                        && !is_vowel_at(i + 2)
+                    {
+                        out.push(ph.with_symbol(Symbol::AA));
+                        continue;
+                    }
+                }
+                Rule::TrapBathNasal => {
+                    if symbol == Symbol::AE
+                        && is_at(i + 1, &[Symbol::N])
+                        && is_at(i + 2, &[Symbol::T, Symbol::S, Symbol::CH])
+                        && !is_vowel_at(i + 3)
+                    {
+                        out.push(ph.with_symbol(Symbol::AA));
+                        continue;
+                    }
+                }
+                Rule::CotCaught => {
+                    if symbol == Symbol::AO && !is_at(i + 1, &[Symbol::R]) {
+                        out.push(ph.with_symbol(Symbol::AA));
+                        continue;
+                    }
+                }
+                Rule::NonRhotic => {
+                    if symbol == Symbol::R && i > 0 && is_vowel_at(i - 1) && !is_vowel_at(i + 1) {
+                        continue;
+                    }
+                    if symbol == Symbol::ER && ph.stress() == Some(Stress::Unstressed) {
+                        out.push(ph.with_symbol(Symbol::AH));
+                        continue;
+                    }
+                }
+            }
+            out.push(*ph);
+        }
+        return out;
+    }
+}
+
+/// A named dialect, with the rules that turn General American pronunciations into its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// The dialect of cmudict, which needs no rules.
+    #[default]
+    GeneralAmerican,
+    /// Rhotic like General American, but with the cot-caught merger.
+    Canadian,
+    /// Received Pronunciation: non-rhotic, with the trap-bath split.
+    British,
+    /// Non-rhotic, with the trap-bath split except before N, e.g. in "dance".
+    Australian,
+}
+
+impl Dialect {
+    /// Every dialect, in the order they're offered.
+    pub const ALL: [Dialect; 4] = [
+        Dialect::GeneralAmerican,
+        Dialect::Canadian,
+        Dialect::British,
+        Dialect::Australian,
+    ];
+
+    /// Returns the name of the dialect, as used on the command line and in forms, e.g.
+    /// "general-american".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::GeneralAmerican => "general-american",
+            Dialect::Canadian => "canadian",
+            Dialect::British => "british",
+            Dialect::Australian => "australian",
+        }
+    }
+
+    /// Returns the rules of the dialect, in the order they're applied.
+    pub fn rules(&self) -> &'static [Rule] {
+        match self {
+            Dialect::GeneralAmerican => &[],
+            Dialect::Canadian => &[Rule::CotCaught],
+            Dialect::British => &[
+                Rule::WeakAry,
+                Rule::TrapBath,
+                Rule::TrapBathNasal,
+                Rule::NonRhotic,
+            ],
+            Dialect::Australian => &[Rule::WeakAry, Rule::TrapBath, Rule::NonRhotic],
+        }
+    }
+
+    /// Returns `phonemes` as they sound in this dialect.
+    pub fn rewrite(&self, phonemes: &Phonemes) -> Phonemes {
+        let mut out = phonemes.phonemes.clone();
+        for rule in self.rules() {
+            out = rule.apply(&out);
+        }
+        return Phonemes::from_phonemes(out);
+    }
+
+    /// Returns whether the two pronunciations rhyme in this dialect.
+    pub fn rhymes(&self, a: &Phonemes, b: &Phonemes) -> bool {
+        if self.rules().is_empty() {
+            return a.rhymes_with(b);
+        }
+        return self.rewrite(a).rhymes_with(&self.rewrite(b));
+    }
+
+    /// Returns the number of syllables in `phonemes` in this dialect.
+    pub fn num_syllables(&self, phonemes: &Phonemes) -> i32 {
+        if self.rules().is_empty() {
+            return phonemes.num_syllables();
+        }
+        return self.rewrite(phonemes).num_syllables();
+    }
+}
+
+impl std::str::FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Dialect::ALL.iter().find(|d| d.name() == s) {
+            Some(dialect) => Ok(*dialect),
+            None => Err(format!("unknown dialect: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    /// Writes the name of the language, e.g. "British English".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Dialect::GeneralAmerican => "General American English",
+            Dialect::Canadian => "Canadian English",
+            Dialect::British => "British English",
+            Dialect::Australian => "Australian English",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Entry;
+
+    fn rewrite(dialect: Dialect, line: &str) -> String {
+        dialect.rewrite(&Entry::new(line).phonemes).to_string()
+    }
+
+    #[test]
+    fn rewrites_phonemes() {
+        assert_eq!(
+            rewrite(Dialect::British, "farther F AA1 R DH ER0"),
+            "F AA1 DH AH0"
+        );
+        assert_eq!(rewrite(Dialect::British, "red R EH1 D"), "R EH1 D");
+        assert_eq!(rewrite(Dialect::British, "error EH1 R ER0"), "EH1 R AH0");
+        assert_eq!(rewrite(Dialect::British, "bird B ER1 D"), "B ER1 D");
+        assert_eq!(rewrite(Dialect::British, "bath B AE1 TH"), "B AA1 TH");
+        assert_eq!(
+            rewrite(Dialect::British, "classic K L AE1 S IH0 K"),
+            "K L AE1 S IH0 K"
+        );
+        assert_eq!(rewrite(Dialect::British, "dance D AE1 N S"), "D AA1 N S");
+        assert_eq!(rewrite(Dialect::Australian, "dance D AE1 N S"), "D AE1 N S");
+        assert_eq!(
+            rewrite(Dialect::British, "secretary S EH1 K R AH0 T EH2 R IY0"),
+            "S EH1 K R AH0 T R IY0"
+        );
+        assert_eq!(
+            rewrite(Dialect::British, "library L AY1 B R EH2 R IY0"),
+            "L AY1 B R EH2 R IY0"
+        );
+        assert_eq!(rewrite(Dialect::Canadian, "caught K AO1 T"), "K AA1 T");
+        assert_eq!(rewrite(Dialect::Canadian, "north N AO1 R TH"), "N AO1 R TH");
+        assert_eq!(
+            rewrite(Dialect::GeneralAmerican, "farther F AA1 R DH ER0"),
+            "F AA1 R DH ER0"
+        );
+    }
+
+    #[test]
+    fn compares_in_dialect() {
+        let phonemes = |line: &str| Entry::new(line).phonemes;
+        let lore = phonemes("lore L AO1 R");
+        let law = phonemes("law L AO1");
+        assert!(!Dialect::GeneralAmerican.rhymes(&lore, &law));
+        assert!(Dialect::British.rhymes(&lore, &law));
+
+        let cot = phonemes("cot K AA1 T");
+        let caught = phonemes("caught K AO1 T");
+        assert!(!Dialect::GeneralAmerican.rhymes(&cot, &caught));
+        assert!(Dialect::Canadian.rhymes(&cot, &caught));
+
+        let military = phonemes("military M IH1 L AH0 T EH2 R IY0");
+        assert_eq!(Dialect::GeneralAmerican.num_syllables(&military), 4);
+        assert_eq!(Dialect::British.num_syllables(&military), 3);
+    }
+
+    #[test]
+    fn parses_names() {
+        for dialect in Dialect::ALL {
+            assert_eq!(dialect.name().parse(), Ok(dialect));
+        }
+        assert!("klingon".parse::<Dialect>().is_err());
+        assert_eq!(Dialect::default().to_string(), "General American English");
+    }
+}
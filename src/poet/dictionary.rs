@@ -29,17 +29,27 @@
 //! * <https://cmusphinx.github.io/wiki/tutorialdict/>
 //! * <http://www.speech.cs.cmu.edu/tools/lextool.html>
 //!
+use crate::poet::frequency::{Frequencies, FrequencyBand};
+use crate::poet::ipa;
+use crate::poet::lexicon::{self, Format};
+use crate::poet::metadata::{self, EntryMetadata, MetadataFile, PartOfSpeech};
+use crate::poet::pattern::Pattern;
+use crate::poet::phoneme::{Phoneme, Stress, Symbol};
+use crate::poet::syllable::{self, Syllable};
 use rocket::serde::Serialize;
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
 
 /// Represents the phonemes of a word, in ARPABET / cmudict format.
-#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Phonemes {
     /// The individual phonemes as listed, in the original order e.g. `["SH", "R", "IH1", "M", "P"]`.
-    pub phonemes: Vec<String>,
+    pub phonemes: Vec<Phoneme>,
 
     /// The number of syllables, identified by the number of vowel sounds.
     syllables: i32,
@@ -57,24 +67,23 @@ impl Phonemes {
     ///
     /// Args:
     /// * `tokens` - A slice like &["SH", "R", "IH1", "M", "P"], e.g. from a raw input.
-    fn from_slice(&mut self, tokens: &[&str]) {
-        self.phonemes = Vec::with_capacity(tokens.len());
-        self.syllables = 0;
-
-        use regex::Regex;
-        lazy_static! {
-            // This matches phonemes like "AA1", "N" and "AH0". If there is an integer
-            // part, it's a vowel sound.
-            static ref PHONEME_RE: Regex = Regex::new(r"[A-Z]+([0-9]+)?").unwrap();
-        }
+    ///
+    /// Returns the reason if any token isn't a phoneme, leaving `self` unchanged.
+    fn from_slice(&mut self, tokens: &[&str]) -> Result<(), String> {
+        let phonemes = tokens
+            .iter()
+            .map(|ph| ph.parse())
+            .collect::<Result<Vec<Phoneme>, String>>()?;
+        *self = Phonemes::from_phonemes(phonemes);
+        Ok(())
+    }
 
-        for ph in tokens {
-            let ph_cap = PHONEME_RE.captures(&ph).unwrap();
-            self.phonemes.push(String::from(&ph_cap[0]));
-            // Phonemes with integers indicate the main vowel sounds.
-            if ph_cap.get(1).is_some() {
-                self.syllables += 1;
-            }
+    /// Creates a sequence from already-parsed phonemes.
+    pub fn from_phonemes(phonemes: Vec<Phoneme>) -> Phonemes {
+        let syllables = phonemes.iter().filter(|ph| ph.is_vowel()).count() as i32;
+        Phonemes {
+            phonemes,
+            syllables,
         }
     }
 
@@ -83,55 +92,148 @@ impl Phonemes {
         self.syllables
     }
 
-    /// Returns a string that, when used as a sort key, places similar sequences together.
+    /// Splits the phonemes into syllables. See `syllable`.
+    pub fn syllables(&self) -> Vec<Syllable> {
+        syllable::syllabify(&self.phonemes)
+    }
+
+    /// Returns the syllables in ARPABET, separated by " · ", e.g. "HH AH0 · L OW1".
+    pub fn to_syllabified_string(&self) -> String {
+        let syllables = self.syllables();
+        if syllables.len() < 2 {
+            return self.to_string();
+        }
+        let syllables: Vec<String> = syllables.iter().map(|s| s.to_string()).collect();
+        return syllables.join(" · ");
+    }
+
+    /// Returns the pronunciation in IPA, e.g. "həˈloʊ" for "HH AH0 L OW1". See `ipa`.
+    pub fn to_ipa(&self) -> String {
+        ipa::to_ipa(&self.phonemes)
+    }
+
+    /// Reads a pronunciation in IPA, e.g. "/həˈloʊ/". See `ipa`.
+    pub fn from_ipa(text: &str) -> Result<Phonemes, String> {
+        ipa::from_ipa(text)
+    }
+
+    /// Returns a sort key that places similar sequences together.
     ///
     /// This is just the reversed phoneme sequence.
-    fn similarity_key(&self) -> String {
-        let mut result = String::with_capacity(4 * 100); // 100 chars should be plenty.
-        for ph in self.phonemes.iter().rev() {
-            result.push_str(ph);
-            result.push(' ');
-        }
-        return result;
+    fn similarity_key(&self) -> Vec<Phoneme> {
+        self.phonemes.iter().rev().copied().collect()
     }
 
-    /// Returns the phonemes for the last n syllables, reversed (for sorting).
+    /// Returns a sort key that places words starting with the same sounds together.
     ///
-    /// This is used for selecting ranges of similar words sorted by similarity_keys.
-    fn last_n_syllables(&self, syllable_count: usize) -> String {
-        let mut result = String::with_capacity(4 * 100); // 100 chars should be plenty.
-        let mut vowel_count: usize = 0;
-        for ph in self.phonemes.iter().rev() {
-            let is_vowel = ph.matches(char::is_numeric).count() > 0;
-            result.push_str(ph);
-            result.push(' ');
+    /// This is the sequence of symbols, without the stress of the vowels.
+    fn alliteration_key(&self) -> Vec<Symbol> {
+        self.phonemes.iter().map(|p| p.symbol()).collect()
+    }
+
+    /// Returns the start of the word that `span` covers, without stress. For
+    /// `Alliteration::Onset`, that's the consonants before the first vowel, or just the vowel
+    /// if the word starts with one.
+    fn leading_symbols(&self, span: Alliteration) -> Vec<Symbol> {
+        let len = match span {
+            Alliteration::Onset => match self.phonemes.iter().position(|p| p.is_vowel()) {
+                Some(0) => 1,
+                Some(pos) => pos,
+                None => self.phonemes.len(),
+            },
+            Alliteration::Phonemes(n) => n.min(self.phonemes.len()),
+        };
+        self.phonemes[..len].iter().map(|p| p.symbol()).collect()
+    }
 
-            if is_vowel {
+    /// Returns the phonemes for the last n syllables, but without the onset of the first one,
+    /// i.e. from the nth-last vowel to the end. For one syllable, this is its rime.
+    ///
+    /// Reversed, this is a prefix of the similarity_key()s of words that share those syllables.
+    fn last_n_syllables(&self, syllable_count: usize) -> &[Phoneme] {
+        let mut vowel_count: usize = 0;
+        for (i, ph) in self.phonemes.iter().enumerate().rev() {
+            if ph.is_vowel() {
                 vowel_count += 1;
                 if vowel_count >= syllable_count {
-                    break;
+                    return &self.phonemes[i..];
                 }
             }
         }
-        return result;
+        return &self.phonemes;
     }
 
     /// Returns whether this rhymes with another set of phonemes.
     pub fn rhymes_with(&self, other: &Self) -> bool {
-        // This is a hack but it effectively compares the last syllable of the two words.
+        // Compares the rimes of the last syllables, without splitting up the rest.
         return self.last_n_syllables(1) == other.last_n_syllables(1);
     }
 
-    /// Computes a similarity score between two words. Higher scores are more similar.
+    /// Returns the stress digits of the vowels, in order, e.g. "102" for "AE1 P AH0 L S AO2 S".
+    pub fn stress_pattern(&self) -> String {
+        self.phonemes
+            .iter()
+            .filter_map(|ph| ph.stress())
+            .map(|stress| char::from(b'0' + stress as u8))
+            .collect()
+    }
+
+    /// Returns the phonemes from the last stressed vowel through the end of the word.
     ///
-    /// Args:
-    /// * `a_phonemes` - The phonemes for the first word.
-    /// * `b_phonemes` - The phonemes for the second word.
+    /// This is the part of a word that has to match for a perfect rhyme, e.g. "AE2 M" for
+    /// "P R OW1 G R AE2 M". If no vowel is stressed, this starts at the last vowel instead.
+    fn stressed_tail(&self) -> &[Phoneme] {
+        let start = self
+            .phonemes
+            .iter()
+            .rposition(|ph| ph.is_stressed())
+            .or_else(|| self.phonemes.iter().rposition(|ph| ph.is_vowel()))
+            .unwrap_or(0);
+        &self.phonemes[start..]
+    }
+
+    /// Returns the symbol that starts the `stressed_tail`, i.e. the rhyme vowel, or the first
+    /// consonant if there are no vowels. None if there are no phonemes.
+    fn rhyme_symbol(&self) -> Option<Symbol> {
+        self.stressed_tail().first().map(|ph| ph.symbol())
+    }
+
+    /// Classifies how `other` rhymes with this sequence. See `RhymeKind`.
+    fn rhyme_kind(&self, other: &Self) -> RhymeKind {
+        if self.phonemes == other.phonemes {
+            return RhymeKind::Identical;
+        }
+        // The stress level of the vowels doesn't matter here, e.g. "diagram" and "cram".
+        let a = self.stressed_tail().iter().map(|ph| ph.symbol());
+        let b = other.stressed_tail().iter().map(|ph| ph.symbol());
+        if a.eq(b) {
+            RhymeKind::Perfect
+        } else {
+            RhymeKind::Partial
+        }
+    }
+
+    /// Computes a similarity score between two words. Higher scores are more similar.
     ///
+    /// The endings from the last stressed vowel (see `stressed_tail`) are aligned by
+    /// `alignment_cost`, and the score is 100 minus the cost as a percentage of leaving every
+    /// phoneme unmatched. So perfect rhymes score 100, "shape" and "break" 91, and "cat" and
+    /// "dog" 34. Each identical phoneme just before both endings adds one more, which ranks
+    /// "program" and "programme" above "program" and "diagram".
     fn similarity_score(&self, other: &Self) -> i32 {
-        let mut score: i32 = 0;
+        let a = self.stressed_tail();
+        let b = other.stressed_tail();
+        let worst = GAP_COST * (a.len() + b.len()) as u32;
+        let mut score = match worst {
+            0 => 100,
+            _ => 100 - (100 * alignment_cost(a, b) + worst / 2) / worst,
+        } as i32;
 
-        for (a, b) in self.phonemes.iter().rev().zip(other.phonemes.iter().rev()) {
+        let a_rest = self.phonemes[..self.phonemes.len() - a.len()].iter().rev();
+        let b_rest = other.phonemes[..other.phonemes.len() - b.len()]
+            .iter()
+            .rev();
+        for (a, b) in a_rest.zip(b_rest) {
             if a == b {
                 score += 1;
             } else {
@@ -142,9 +244,85 @@ impl Phonemes {
     }
 }
 
+/// The cost of leaving a phoneme unmatched in `alignment_cost`.
+const GAP_COST: u32 = 8;
+
+/// How many times over a difference between vowels counts in `alignment_cost`, since the vowel
+/// carries most of a rhyme.
+const VOWEL_WEIGHT: u32 = 3;
+
+/// Only the last this-many phonemes of each sequence are aligned, to keep it cheap.
+const MAX_ALIGNED: usize = 16;
+
+/// Returns whether words whose rhyme symbols (see `Phonemes::rhyme_symbol`) are `a` and `b`
+/// can be near rhymes, i.e. whether lining the two up costs no more than leaving one of them
+/// unmatched in `alignment_cost`. Words with rhyme vowels further apart than that rarely reach
+/// `NEAR_RHYME_MIN_SCORE`, so `near_rhymes` doesn't score them.
+fn near_rhyme_symbols(a: Symbol, b: Symbol) -> bool {
+    let mut cost = a.distance(&b);
+    if a.is_vowel() && b.is_vowel() {
+        cost *= VOWEL_WEIGHT;
+    }
+    return cost <= GAP_COST;
+}
+
+/// Returns the lowest total cost of lining up the phonemes of `a` and `b` in order.
+///
+/// Each pair of lined-up phonemes costs their `Symbol::distance` (times `VOWEL_WEIGHT` for two
+/// vowels; stress is ignored), and each phoneme left without a partner costs `GAP_COST`. This is
+/// the usual edit distance table, e.g. "EY1 P S" lines up with "EY1 K" for a cost of 3 + 8.
+fn alignment_cost(a: &[Phoneme], b: &[Phoneme]) -> u32 {
+    let a = &a[a.len().saturating_sub(MAX_ALIGNED)..];
+    let b = &b[b.len().saturating_sub(MAX_ALIGNED)..];
+    // Rolling rows of the table, where row[j] is the cost of aligning a[..i] with b[..j].
+    let mut prev = [0u32; MAX_ALIGNED + 1];
+    let mut row = [0u32; MAX_ALIGNED + 1];
+    for (j, cost) in prev.iter_mut().enumerate().take(b.len() + 1) {
+        *cost = GAP_COST * j as u32;
+    }
+    for (i, pa) in a.iter().enumerate() {
+        row[0] = GAP_COST * (i as u32 + 1);
+        for (j, pb) in b.iter().enumerate() {
+            let mut substitution = pa.symbol().distance(&pb.symbol());
+            if pa.is_vowel() && pb.is_vowel() {
+                substitution *= VOWEL_WEIGHT;
+            }
+            row[j + 1] = (prev[j] + substitution)
+                .min(prev[j + 1] + GAP_COST)
+                .min(row[j] + GAP_COST);
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    return prev[b.len()];
+}
+
+/// Returns whether `b` is `a` with exactly one phoneme substituted, inserted or deleted, e.g.
+/// "SH IH1 P" and "SH IY1 P" ("ship" and "sheep").
+fn one_edit_apart(a: &[Phoneme], b: &[Phoneme]) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let same = short.iter().zip(long).take_while(|(x, y)| x == y).count();
+    if short.len() == long.len() {
+        return same < short.len() && short[same + 1..] == long[same + 1..];
+    }
+    return short[same..] == long[same + 1..];
+}
+
 impl fmt::Display for Phonemes {
+    /// Writes the phonemes in ARPABET, e.g. "HH AH0 L OW1", or with `{:#}`, in IPA.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.phonemes.join(" "))
+        if f.alternate() {
+            return write!(f, "{}", self.to_ipa());
+        }
+        for (i, ph) in self.phonemes.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", ph)?;
+        }
+        Ok(())
     }
 }
 
@@ -159,10 +337,33 @@ pub struct Entry {
     pub phonemes: Phonemes,
     /// The variant, e.g. 2 for the term `aluminium(2)`. Default 1.
     pub variant: i32,
+    /// The dictionary the entry came from, e.g. "./userdict.dict". None if it isn't from a
+    /// named dictionary.
+    pub source: Option<Arc<str>>,
+    /// Parts of speech and such, from a sidecar file or Datamuse. See `metadata`.
+    pub metadata: Option<Arc<EntryMetadata>>,
+}
+
+/// Returns the part of a cmudict line before any comment ('#' through the end of line).
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(comment_start) => &line[0..comment_start],
+        None => line,
+    }
 }
 
 impl Entry {
-    /// Constructs an Entry from the given line, assumed to be in cmudict format.
+    /// Constructs an Entry from the given line, which must be in cmudict format.
+    ///
+    /// Panics if the line is malformed; use `Entry::parse` for anything read from a file
+    /// or a user.
+    #[cfg(test)]
+    pub fn new(line: &str) -> Entry {
+        return Entry::parse(line).unwrap();
+    }
+
+    /// Parses an Entry from the given line in cmudict format, or returns the reason it
+    /// couldn't be parsed.
     ///
     /// Example inputs:
     /// ```
@@ -175,71 +376,90 @@ impl Entry {
     /// achill AE1 K IH0 L # place, irish
     /// achill's AE1 K IH0 L Z
     /// ```
-    pub fn new(line: &str) -> Entry {
-        let mut trimmed_line = line;
-        // Strip comments if present ('#' through the end of line).
-        let comment_start = line.find('#');
-        if comment_start.is_some() {
-            trimmed_line = &line[0..comment_start.unwrap()];
-        }
-
-        // Split the rest on whitespace and use regexen to pull out the important parts.
+    pub fn parse(line: &str) -> Result<Entry, String> {
+        // Split on whitespace and use regexen to pull out the important parts.
         use regex::Regex;
         lazy_static! {
             // This matches the term and optional (N) suffix, e.g. from "aalborg(2)".
             // Capture group 1 has the term text and capture 3 has the integer, if any.
-            static ref TERM_RE: Regex = Regex::new(r"([^ ()#]*)(\(([0-9]+)\))?").unwrap();
+            static ref TERM_RE: Regex = Regex::new(r"^([^()#]+)(\(([0-9]+)\))?$").unwrap();
+        }
+        let tokens: Vec<&str> = strip_comment(line).split_whitespace().collect();
+        let term = match tokens.first() {
+            Some(term) => term,
+            None => return Err("empty line".to_string()),
+        };
+        let term_cap = TERM_RE
+            .captures(term)
+            .ok_or_else(|| format!("invalid term \"{}\"", term))?;
+        if tokens.len() < 2 {
+            return Err(format!("no phonemes for \"{}\"", term));
         }
-        let tokens: Vec<&str> = trimmed_line.split_whitespace().collect();
 
-        let term_cap = TERM_RE.captures(&tokens[0]).unwrap();
         let mut result = Entry {
             word: String::from(&term_cap[1]),
             phonemes: Phonemes::new(),
             variant: 1,
+            source: None,
+            metadata: None,
         };
-        if term_cap.get(3).is_some() {
-            result.variant = term_cap[3].parse().unwrap();
+        if let Some(variant) = term_cap.get(3) {
+            result.variant = match variant.as_str().parse() {
+                Ok(v) if v >= 1 => v,
+                _ => return Err(format!("invalid variant number in \"{}\"", term)),
+            };
         }
 
-        result.phonemes.from_slice(&tokens[1..]);
+        result.phonemes.from_slice(&tokens[1..])?;
 
-        return result;
+        return Ok(result);
     }
 
-    /// Constructs an Entry from the given word and phonetic sequence.
+    /// Constructs an Entry from the given word and phonetic sequence, or returns the reason
+    /// if the sequence has anything that isn't a phoneme.
     ///
     /// ```
-    /// let entry = Entry::from_parts("shrimp", "SH R IH1 M P");
+    /// let entry = Entry::from_parts("shrimp", "SH R IH1 M P")?;
     /// ```
-    pub fn from_parts(term: &str, phonemes: &str) -> Entry {
-        let tokens: Vec<&str> = phonemes.trim().split_whitespace().collect();
+    pub fn from_parts(term: &str, phonemes: &str) -> Result<Entry, String> {
+        let tokens: Vec<&str> = phonemes.split_whitespace().collect();
         let mut phonemes = Phonemes::new();
-        phonemes.from_slice(&tokens);
-        return Entry {
+        phonemes.from_slice(&tokens)?;
+        return Ok(Entry {
             word: String::from(term),
             phonemes: phonemes,
             variant: 1,
-        };
+            source: None,
+            metadata: None,
+        });
     }
 
-    /// Returns a string that, when used as a sort key, places similar words together.
-    fn similarity_key(&self) -> String {
-        // NOTE: This is mostly used as an internal data structure hack that could
-        // be done with a comparator.
-        let mut key = self.phonemes.similarity_key();
-        key.push_str(&self.dict_key()); // To disambiguate homonyms -- dict_key() is unique.
-        return key;
+    /// Returns a sort key that places similar words together.
+    ///
+    /// Homonyms have the same key; `reverse_list` pairs it with the term and variant.
+    fn similarity_key(&self) -> Vec<Phoneme> {
+        return self.phonemes.similarity_key();
     }
 
-    pub fn rhymes_with(&self, other: &Self) -> bool {
-        return self.phonemes.rhymes_with(&other.phonemes);
+    /// Returns a sort key that places words starting with the same sounds together.
+    ///
+    /// Homonyms have the same key; `forward_list` pairs it with the term and variant.
+    fn alliteration_key(&self) -> Vec<Symbol> {
+        return self.phonemes.alliteration_key();
     }
 
     pub fn num_syllables(&self) -> i32 {
         return self.phonemes.num_syllables();
     }
 
+    /// Returns the parts of speech of the word, if they're known.
+    pub fn parts_of_speech(&self) -> &[PartOfSpeech] {
+        match &self.metadata {
+            Some(metadata) => &metadata.pos,
+            None => &[],
+        }
+    }
+
     /// Returns the text if the term were in the original dictionary.
     ///
     /// For the first variant, this is just the word. For later variants, it has a "(N)" suffix,
@@ -254,96 +474,637 @@ impl Entry {
 }
 
 impl fmt::Display for Entry {
+    /// Writes the entry with its phonemes in ARPABET, or with `{:#}`, in IPA.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phonemes = if f.alternate() {
+            self.phonemes.to_ipa()
+        } else {
+            self.phonemes.to_string()
+        };
         write!(
             f,
             "\"{}\" ({}); variant={}, syllables={}",
             &self.word,
-            &self.phonemes,
+            phonemes,
             &self.variant,
             self.num_syllables()
-        )
+        )?;
+        if let Some(source) = &self.source {
+            write!(f, "; from {}", source)?;
+        }
+        if let Some(metadata) = &self.metadata {
+            write!(f, "; tagged {}", metadata)?;
+        }
+        Ok(())
     }
 }
 
 /// A place where Dictionaries are kept. Used to search over a collection of dictionaries.
 ///
-/// CAVEATS
-///
-/// This is designed for the case where is one large base dictionary, and up to a few
-/// patch dictionaries that mostly fill in missing entries in the base dictionary. It
-/// falls down after that because:
-///
-/// * The `lookup` methods return the first collection of entries that are found.
+/// When a word is in more than one dictionary, the entries are combined according to the
+/// shelf's `MergePolicy`, in the order the dictionaries were added. Each `Entry` keeps the
+/// name of the dictionary it came from in `source`.
 ///
-/// These are TODOs.
+/// The user dictionary, if loaded with `init_userdict`, is kept separately so that it can be
+/// edited at runtime with `add_user_entry`, `update_user_entry` and `remove_user_entry`. It
+/// comes after all of the other dictionaries, and every edit is saved back to its file.
 pub struct Shelf {
     dictionaries: Vec<Box<dyn Dictionary + std::marker::Send>>,
+    user: Option<UserDictionary>,
+    policy: MergePolicy,
+    load_mode: LoadMode,
+    /// The combined entries for each word that's in more than one dictionary.
+    merged: HashMap<String, Vec<Entry>>,
+    /// How common each word is, if a frequency list was loaded.
+    frequencies: Option<Frequencies>,
+    /// Whether any of the dictionaries had a sidecar metadata file.
+    has_metadata: bool,
+    /// Whether there's a frequency list or any sidecar metadata had frequencies.
+    has_frequencies: bool,
+}
+
+/// How a `Shelf` combines the entries for a word that's in more than one dictionary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Entries from later dictionaries replace all of the entries from earlier ones.
+    Override,
+    /// Entries from later dictionaries are added as extra variants, numbered after the
+    /// earlier ones.
+    AppendVariants,
+    /// Like `AppendVariants`, but skips pronunciations that earlier dictionaries already have.
+    #[default]
+    AppendNew,
+}
+
+impl std::str::FromStr for MergePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "override" => Ok(MergePolicy::Override),
+            "append" => Ok(MergePolicy::AppendVariants),
+            "append-new" => Ok(MergePolicy::AppendNew),
+            _ => Err(format!(
+                "unknown merge policy: {} (expected override, append or append-new)",
+                s
+            )),
+        }
+    }
+}
+
+impl MergePolicy {
+    /// Combines the entries for one word from several dictionaries, in dictionary order.
+    fn merge(&self, found: &[&Vec<Entry>]) -> Vec<Entry> {
+        let mut out: Vec<Entry> = vec![];
+        for entries in found {
+            if *self == MergePolicy::Override {
+                out = entries.to_vec();
+                continue;
+            }
+            let next_variant = out.iter().map(|e| e.variant).max().unwrap_or(0) + 1;
+            let mut added = 0;
+            for entry in entries.iter() {
+                if *self == MergePolicy::AppendNew
+                    && out.iter().any(|e| e.phonemes == entry.phonemes)
+                {
+                    continue;
+                }
+                let mut entry = entry.clone();
+                if next_variant > 1 {
+                    entry.variant = next_variant + added;
+                }
+                added += 1;
+                out.push(entry);
+            }
+        }
+        return out;
+    }
+}
+
+/// How to handle malformed lines when loading a dictionary file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoadMode {
+    /// Fail on the first malformed line.
+    Strict,
+    /// Skip malformed lines, and remember them so they can be reported.
+    #[default]
+    Lenient,
+}
+
+/// Errors from loading a dictionary file.
+#[derive(Debug)]
+pub enum DictionaryError {
+    /// The file couldn't be read.
+    Io { path: String, error: std::io::Error },
+    /// A line couldn't be parsed. `line` is 1-based.
+    Malformed {
+        path: String,
+        line: usize,
+        reason: String,
+    },
+    /// An entry in a file without lines to speak of, e.g. a JSON lexicon, couldn't be parsed.
+    MalformedEntry {
+        path: String,
+        word: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DictionaryError::Io { path, error } => write!(f, "{}: {}", path, error),
+            DictionaryError::Malformed { path, line, reason } => {
+                write!(f, "{}:{}: {}", path, line, reason)
+            }
+            DictionaryError::MalformedEntry { path, word, reason } => {
+                write!(f, "{}: \"{}\": {}", path, word, reason)
+            }
+        }
+    }
+}
+
+impl Error for DictionaryError {}
+
+/// The first bytes of a compiled dictionary index.
+const INDEX_MAGIC: &[u8; 8] = b"POETIDX\0";
+
+/// The layout of the compiled dictionary index. Indexes with any other version are ignored, so
+/// bump this whenever the layout changes.
+///
+/// Version 2, with little-endian integers and strings as a u32 length then UTF-8 bytes:
+///
+/// * `INDEX_MAGIC`, then this version as a u32.
+/// * The checksum of the source text, as a u64.
+/// * The number of entries as a u32, then for each: the word, the variant as a u32, the
+///   number of phonemes as a u32, and one `Phoneme::to_byte()` per phoneme. Entries are
+///   sorted by word and variant.
+/// * The number of rhyme index rows as a u32, then the position of each row's entry in the
+///   list above, as a u32, in `reverse_list` order.
+/// * The number of alliteration index rows as a u32, then the same for `forward_list`.
+/// * The number of skipped lines as a u32, then for each: the line number as a u32 and the
+///   reason.
+/// * The checksum of everything above, as a u64.
+const INDEX_VERSION: u32 = 2;
+
+/// A row of `reverse_list` or `forward_list` while reading the index, taken once it's placed.
+type IndexRow<K> = Option<(Vec<K>, (String, i32))>;
+
+/// Returns where the compiled index for the dictionary at `path` is kept.
+pub fn index_path(path: &str) -> String {
+    format!("{}.idx", path)
+}
+
+/// Returns the 64-bit FNV-1a hash of `bytes`. This is used to tell whether an index was
+/// compiled from the current source file, and whether the index itself is intact.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+/// Appends the fields of a compiled index.
+#[derive(Default)]
+struct IndexWriter {
+    bytes: Vec<u8>,
+}
+
+impl IndexWriter {
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Reads the fields of a compiled index, failing if it ends early.
+struct IndexReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> IndexReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.saturating_add(len);
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or("it ends unexpectedly")?;
+        self.pos = end;
+        return Ok(bytes);
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        return Ok(u32::from_le_bytes(bytes.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let bytes = self.take(8)?;
+        return Ok(u64::from_le_bytes(bytes.try_into().unwrap()));
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+        return String::from_utf8(bytes.to_vec()).map_err(|_| "it has invalid text".to_string());
+    }
+}
+
+/// The editable user dictionary and the file it's saved to.
+struct UserDictionary {
+    dict: DictionaryImpl,
+    path: String,
+}
+
+/// Errors from editing the user dictionary.
+#[derive(Debug)]
+pub enum UserDictError {
+    /// No user dictionary was loaded, so there is nothing to edit.
+    NotLoaded,
+    /// The entry text isn't a valid cmudict line, e.g. "word W ER1 D".
+    InvalidEntry(String),
+    /// The term and variant are already in the user dictionary.
+    AlreadyExists(String),
+    /// The term and variant aren't in the user dictionary.
+    NotFound(String),
+    /// Saving the user dictionary failed. The edit was rolled back.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for UserDictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UserDictError::NotLoaded => write!(f, "no user dictionary is loaded"),
+            UserDictError::InvalidEntry(line) => write!(
+                f,
+                "invalid entry \"{}\"; expected a term and phonemes, e.g. \"word W ER1 D\"",
+                line
+            ),
+            UserDictError::AlreadyExists(key) => {
+                write!(f, "{} is already in the user dictionary", key)
+            }
+            UserDictError::NotFound(key) => write!(f, "{} is not in the user dictionary", key),
+            UserDictError::Io(e) => write!(f, "failed to save the user dictionary: {}", e),
+        }
+    }
+}
+
+impl Error for UserDictError {}
+
+/// Parses and validates a user-supplied line in cmudict format, e.g. "word(2) W ER1 D".
+///
+/// Terms are lowercased and phonemes uppercased, to match the cmudict conventions.
+pub fn parse_user_entry(line: &str) -> Result<Entry, UserDictError> {
+    use regex::Regex;
+    lazy_static! {
+        static ref ENTRY_RE: Regex =
+            Regex::new(r"^[^\s()#]+(\([0-9]+\))?(\s+[A-Za-z]+[0-9]?)+$").unwrap();
+    }
+    let trimmed = line.trim();
+    if !ENTRY_RE.is_match(trimmed) {
+        return Err(UserDictError::InvalidEntry(trimmed.to_string()));
+    }
+    let mut tokens = trimmed.split_whitespace();
+    let term = tokens.next().unwrap().to_lowercase();
+    let phonemes: Vec<String> = tokens.map(|t| t.to_uppercase()).collect();
+    return Entry::parse(&format!("{} {}", term, phonemes.join(" ")))
+        .map_err(|_| UserDictError::InvalidEntry(trimmed.to_string()));
+}
+
+/// Splits a dictionary key like "word(2)" into its term and variant.
+fn parse_dict_key(key: &str) -> Result<(String, i32), UserDictError> {
+    use regex::Regex;
+    lazy_static! {
+        static ref KEY_RE: Regex = Regex::new(r"^([^\s()#]+)(\(([0-9]+)\))?$").unwrap();
+    }
+    let invalid = || UserDictError::InvalidEntry(key.to_string());
+    let cap = KEY_RE.captures(key.trim()).ok_or_else(invalid)?;
+    let variant = match cap.get(3) {
+        Some(v) => v.as_str().parse().map_err(|_| invalid())?,
+        None => 1,
+    };
+    return Ok((cap[1].to_lowercase(), variant));
+}
+
+/// The most skipped lines to list individually when loading a dictionary.
+const MAX_SKIPPED_LINES_TO_REPORT: usize = 10;
+
+/// Prints a summary of the malformed lines that were skipped while loading `dict`.
+pub fn report_skipped_lines(dict: &DictionaryImpl) {
+    report_skipped(
+        dict.name.as_deref().unwrap_or("(unnamed)"),
+        dict.skipped_lines(),
+    );
+}
+
+/// Prints a summary of the malformed lines that were skipped while loading the file `name`.
+fn report_skipped(name: &str, skipped: &[DictionaryError]) {
+    if skipped.is_empty() {
+        return;
+    }
+    println!("Skipped {} malformed line(s) in {}:", skipped.len(), name);
+    for error in skipped.iter().take(MAX_SKIPPED_LINES_TO_REPORT) {
+        println!("  {}", error);
+    }
+    if skipped.len() > MAX_SKIPPED_LINES_TO_REPORT {
+        println!(
+            "  ...and {} more.",
+            skipped.len() - MAX_SKIPPED_LINES_TO_REPORT
+        );
+    }
 }
 
 impl Shelf {
     pub fn new() -> Shelf {
         Shelf {
             dictionaries: vec![],
+            user: None,
+            policy: MergePolicy::default(),
+            load_mode: LoadMode::default(),
+            merged: HashMap::new(),
+            frequencies: None,
+            has_metadata: false,
+            has_frequencies: false,
         }
     }
 
-    pub fn init_cmudict(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
-        println!("Loading cmudict from {}...", path);
-        let dict = DictionaryImpl::new_from_cmudict_file(path)?;
+    /// Loads a dictionary in the given format (see `lexicon`), and its sidecar metadata file,
+    /// if any. It comes after the dictionaries loaded before it, and before the user dictionary.
+    ///
+    /// cmudict files are loaded from their compiled index if that's up to date (see
+    /// `DictionaryImpl::load()`).
+    pub fn init_dictionary(&mut self, path: &str, format: Format) -> Result<(), Box<dyn Error>> {
+        println!("Loading {} dictionary from {}...", format, path);
+        let mut dict = lexicon::load(path, format, self.load_mode)?;
+        report_skipped_lines(&dict);
+        self.load_sidecar(&mut dict, path)?;
         self.dictionaries.push(Box::new(dict));
+        self.remerge();
         Ok(())
     }
 
+    /// Loads the editable user dictionary, and its sidecar metadata file, if any. If `path`
+    /// doesn't exist, it starts out empty and the file is created on the first edit.
     pub fn init_userdict(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
         println!("Loading (optionally) a user dictionary from {}...", path);
-        let dict = DictionaryImpl::new_from_cmudict_file(path)?;
-        self.dictionaries.push(Box::new(dict));
+        let mut dict = if std::path::Path::new(path).exists() {
+            let dict = DictionaryImpl::new_from_cmudict_file(path, self.load_mode)?;
+            report_skipped_lines(&dict);
+            dict
+        } else {
+            println!(
+                "No user dictionary at {}; starting with an empty one.",
+                path
+            );
+            DictionaryImpl::with_name(path)
+        };
+        self.load_sidecar(&mut dict, path)?;
+        self.user = Some(UserDictionary {
+            dict,
+            path: path.to_string(),
+        });
+        self.remerge();
+        Ok(())
+    }
+
+    /// Loads a word frequency list (see `frequency`), for ranking and filtering results by how
+    /// common the words are.
+    pub fn init_frequencies(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        println!("Loading word frequencies from {}...", path);
+        let frequencies = Frequencies::load(path, self.load_mode)?;
+        report_skipped(path, frequencies.skipped_lines());
+        self.set_frequencies(frequencies);
+        Ok(())
+    }
+
+    /// Applies the sidecar metadata file for the dictionary at `path` (see `metadata`) to
+    /// `dict`, if there is one.
+    fn load_sidecar(
+        &mut self,
+        dict: &mut DictionaryImpl,
+        path: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let sidecar = metadata::sidecar_path(path);
+        if !std::path::Path::new(&sidecar).exists() {
+            return Ok(());
+        }
+        println!("Loading entry metadata from {}...", sidecar);
+        let metadata = MetadataFile::load(&sidecar, self.load_mode)?;
+        report_skipped(&sidecar, metadata.skipped_lines());
+        dict.apply_metadata(&metadata);
+        self.has_metadata = true;
+        self.has_frequencies |= metadata.has_frequencies();
         Ok(())
     }
 
+    /// Returns whether any dictionary had metadata, e.g. for filtering by part of speech.
+    pub fn has_metadata(&self) -> bool {
+        self.has_metadata
+    }
+
+    /// Returns whether any words have frequencies, from a frequency list or metadata, e.g. for
+    /// sorting and filtering by frequency.
+    pub fn has_frequencies(&self) -> bool {
+        self.has_frequencies
+    }
+
+    /// Sets the word frequencies used to fill in `SimilarWord::frequency`.
+    pub fn set_frequencies(&mut self, frequencies: Frequencies) {
+        self.frequencies = Some(frequencies);
+        self.has_frequencies = true;
+    }
+
+    /// Adds a new entry, given as a cmudict line like "word W ER1 D", to the user dictionary
+    /// and saves it.
+    pub fn add_user_entry(&mut self, line: &str) -> Result<Entry, UserDictError> {
+        let entry = parse_user_entry(line)?;
+        let user = self.user.as_mut().ok_or(UserDictError::NotLoaded)?;
+        if user
+            .dict
+            .lookup_variant(&entry.word, entry.variant)
+            .is_some()
+        {
+            return Err(UserDictError::AlreadyExists(entry.dict_key()));
+        }
+        user.dict.insert(entry.clone());
+        if let Err(e) = user.save() {
+            user.dict.remove(&entry.word, entry.variant);
+            return Err(e);
+        }
+        // Return the saved copy, which has the user dictionary as its source.
+        let saved = user
+            .dict
+            .lookup_variant(&entry.word, entry.variant)
+            .unwrap()
+            .clone();
+        self.remerge_word(&entry.word);
+        return Ok(saved);
+    }
+
+    /// Replaces the phonemes of an existing user dictionary entry and saves it.
+    pub fn update_user_entry(&mut self, line: &str) -> Result<Entry, UserDictError> {
+        let entry = parse_user_entry(line)?;
+        let user = self.user.as_mut().ok_or(UserDictError::NotLoaded)?;
+        let old = user
+            .dict
+            .remove(&entry.word, entry.variant)
+            .ok_or_else(|| UserDictError::NotFound(entry.dict_key()))?;
+        user.dict.insert(entry.clone());
+        if let Err(e) = user.save() {
+            user.dict.remove(&entry.word, entry.variant);
+            user.dict.insert(old);
+            return Err(e);
+        }
+        // Return the saved copy, which has the user dictionary as its source.
+        let saved = user
+            .dict
+            .lookup_variant(&entry.word, entry.variant)
+            .unwrap()
+            .clone();
+        self.remerge_word(&entry.word);
+        return Ok(saved);
+    }
+
+    /// Removes the user dictionary entry with the given key, e.g. "word" or "word(2)", and
+    /// saves it.
+    pub fn remove_user_entry(&mut self, key: &str) -> Result<Entry, UserDictError> {
+        let (word, variant) = parse_dict_key(key)?;
+        let user = self.user.as_mut().ok_or(UserDictError::NotLoaded)?;
+        let old = user
+            .dict
+            .remove(&word, variant)
+            .ok_or_else(|| UserDictError::NotFound(key.trim().to_string()))?;
+        if let Err(e) = user.save() {
+            user.dict.insert(old);
+            return Err(e);
+        }
+        self.remerge_word(&word);
+        return Ok(old);
+    }
+
+    /// Sets how malformed lines are handled by later `init_dictionary()` and `init_userdict()`
+    /// calls.
+    pub fn set_load_mode(&mut self, mode: LoadMode) {
+        self.load_mode = mode;
+    }
+
+    /// Sets how entries for words in more than one dictionary are combined.
+    pub fn set_merge_policy(&mut self, policy: MergePolicy) {
+        self.policy = policy;
+        self.remerge();
+    }
+
+    /// Rebuilds `merged` for every word that's in more than one dictionary.
+    fn remerge(&mut self) {
+        let mut words: HashSet<String> = HashSet::new();
+        for d in self.layers().skip(1) {
+            words.extend(d.terms().map(String::from));
+        }
+        self.merged.clear();
+        for word in words {
+            self.remerge_word(&word);
+        }
+    }
+
+    /// Rebuilds `merged` for one word, e.g. after an edit.
+    fn remerge_word(&mut self, word: &str) {
+        let found: Vec<&Vec<Entry>> = self.layers().filter_map(|d| d.lookup(word)).collect();
+        if found.len() > 1 {
+            let merged = self.policy.merge(&found);
+            self.merged.insert(word.to_string(), merged);
+        } else {
+            self.merged.remove(word);
+        }
+    }
+
+    /// Returns the dictionaries in search order, with the user dictionary last.
+    pub fn layers(&self) -> impl Iterator<Item = &dyn Dictionary> {
+        self.dictionaries
+            .iter()
+            .map(|d| d.as_ref() as &dyn Dictionary)
+            .chain(self.user.iter().map(|u| &u.dict as &dyn Dictionary))
+    }
+
     /// Returns a Dictionary that searches over all loaded dictionaries.
-    ///
-    /// See CAVEATS for `Shelf`.
     pub fn over_all(&self) -> &dyn Dictionary {
         return self;
     }
 
     #[cfg(test)]
     // Adds a dictionary to the end of the dictionary list, transferring ownership.
-    fn push_dictionary(&mut self, dict: Box<dyn Dictionary + std::marker::Send>) {
+    pub fn push_dictionary(&mut self, dict: Box<dyn Dictionary + std::marker::Send>) {
         self.dictionaries.push(dict);
+        self.remerge();
+    }
+
+    /// Drops pronunciations that the merge policy left out, e.g. ones that were overridden,
+    /// and fills in the frequencies of the rest.
+    fn retain_merged(&self, words: &mut Vec<SimilarWord>) {
+        words.retain(|w| match self.merged.get(&w.word) {
+            Some(entries) => entries.iter().any(|e| e.phonemes == w.phonemes),
+            None => true,
+        });
+        for word in words {
+            word.frequency = self.frequency(&word.word);
+        }
+    }
+}
+
+impl UserDictionary {
+    fn save(&self) -> Result<(), UserDictError> {
+        self.dict
+            .write_cmudict_file(&self.path)
+            .map_err(UserDictError::Io)
     }
 }
 
 impl Dictionary for Shelf {
-    /// See CAVEATS for `Shelf`.
+    /// Returns the merged entries if the term is in more than one dictionary.
     fn lookup(&self, term: &str) -> Option<&Vec<Entry>> {
-        // TODO: This return type doesn't work well. Address merging and collisions.
-        for d in &self.dictionaries {
-            let result = d.lookup(term);
-            if result.is_some() {
-                return result;
-            }
+        if let Some(merged) = self.merged.get(term) {
+            return Some(merged);
         }
-        None
+        self.layers().find_map(|d| d.lookup(term))
     }
 
-    /// See CAVEATS for `Shelf`.
+    /// Looks up the given variant, as numbered after merging.
     fn lookup_variant(&self, term: &str, variant: i32) -> Option<&Entry> {
-        // TODO: This return type doesn't work well. Address merging and collisions.
-        for d in &self.dictionaries {
-            let result = d.lookup_variant(term, variant);
-            if result.is_some() {
-                return result;
-            }
+        if let Some(merged) = self.merged.get(term) {
+            return merged.iter().find(|e| e.variant == variant);
+        }
+        self.layers().find_map(|d| d.lookup_variant(term, variant))
+    }
+
+    fn terms(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        let mut seen = HashSet::new();
+        Box::new(
+            self.layers()
+                .flat_map(|d| d.terms())
+                .filter(move |t| seen.insert(*t)),
+        )
+    }
+
+    /// Uses the frequency list if there is one, or else the entries' metadata.
+    fn frequency(&self, term: &str) -> Option<u64> {
+        if let Some(frequencies) = &self.frequencies {
+            return Some(frequencies.get(term));
         }
-        None
+        self.layers().filter_map(|d| d.frequency(term)).max()
     }
 
-    /// See CAVEATS for `Shelf`.
     fn similar(&self, query: &str) -> SimilarResult {
-        let mut out = SimilarResult { words: vec![] };
+        let mut out = SimilarResult { groups: vec![] };
 
         // Returns all of the query words across all sub-dictionaries.
         let query_variants = self.lookup(query);
@@ -352,43 +1113,180 @@ impl Dictionary for Shelf {
         }
 
         for query_variant in query_variants.unwrap() {
-            for d in &self.dictionaries {
-                let mut result = d.similar_to_phonemes(&query_variant.phonemes, Some(query));
-                out.words.append(&mut result.words);
+            let mut group = self.similar_to_phonemes(&query_variant.phonemes, Some(query));
+            if let Some(mut g) = group.groups.pop() {
+                g.variant = Some(query_variant.variant);
+                out.groups.push(g);
             }
         }
-        out.words.sort();
+        out.remove_duplicates();
         return out;
     }
 
     fn similar_to_phonemes(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
-        let mut out = SimilarResult { words: vec![] };
-        for d in &self.dictionaries {
+        let mut group = SimilarGroup::new(phonemes, None);
+        for d in self.layers() {
             let mut result = d.similar_to_phonemes(phonemes, query);
-            out.words.append(&mut result.words);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
         }
-        out.words.sort();
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
         return out;
     }
-}
-
-/// A container for a collection of entries.
-///
-/// Either construct one and populate it with individual entries, or initialize one from
-/// a text file in `cmudict.dict` format.
-#[derive(Debug)]
-pub struct DictionaryImpl {
-    entries: std::collections::HashMap<String, Vec<Entry>>,
 
-    // This stores Entry::similarity_key()s to (term + variant). MUST REMAIN SORTED.
+    fn near_rhymes(&self, phonemes: &Phonemes, query: Option<&str>, limit: usize) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        // Each layer's words are only cut to `limit` after merging, since the merge may drop some.
+        for d in self.layers() {
+            let mut result = d.near_rhymes(phonemes, query, usize::MAX);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
+        }
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
+        out.groups[0].words.truncate(limit);
+        return out;
+    }
+
+    fn search(&self, pattern: &Pattern) -> Vec<&Entry> {
+        let mut words: Vec<&str> = self
+            .layers()
+            .flat_map(|d| d.search(pattern))
+            .map(|e| e.word.as_str())
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        // Check the merged entries, since the merge policy may have dropped or renumbered some.
+        return words
+            .into_iter()
+            .filter_map(|word| self.lookup(word))
+            .flatten()
+            .filter(|e| pattern.matches(&e.phonemes))
+            .collect();
+    }
+
+    fn by_stress(&self, query: &StressQuery) -> SimilarResult {
+        let mut group = SimilarGroup::new(query.rhyme.as_ref().unwrap_or(&Phonemes::new()), None);
+        for d in self.layers() {
+            let mut result = d.by_stress(query);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
+        }
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
+        return out;
+    }
+
+    fn alliterations(
+        &self,
+        phonemes: &Phonemes,
+        query: Option<&str>,
+        span: Alliteration,
+        limit: usize,
+    ) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        // As with near rhymes, each layer's words are only cut to `limit` after merging.
+        for d in self.layers() {
+            let mut result = d.alliterations(phonemes, query, span, usize::MAX);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
+        }
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
+        out.groups[0].words.truncate(limit);
+        return out;
+    }
+
+    fn homophones(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        for d in self.layers() {
+            let mut result = d.homophones(phonemes, query);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
+        }
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
+        return out;
+    }
+
+    fn minimal_pairs(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        for d in self.layers() {
+            let mut result = d.minimal_pairs(phonemes, query);
+            for g in &mut result.groups {
+                group.words.append(&mut g.words);
+            }
+        }
+        self.retain_merged(&mut group.words);
+        group.words.sort();
+        let mut out = SimilarResult {
+            groups: vec![group],
+        };
+        out.remove_duplicates();
+        return out;
+    }
+}
+
+/// A container for a collection of entries.
+///
+/// Either construct one and populate it with individual entries, or initialize one from
+/// a text file in `cmudict.dict` format.
+#[derive(Debug)]
+pub struct DictionaryImpl {
+    /// The name of the dictionary, usually its path, copied into each Entry's `source`.
+    name: Option<Arc<str>>,
+
+    entries: std::collections::HashMap<String, Vec<Entry>>,
+
+    // This stores Entry::similarity_key()s to (term + variant). MUST REMAIN SORTED.
     //
-    // e.g. ("L AH0 V AH1 SH shovel", ("shovel", 1))
+    // e.g. ([L, AH0, V, AH1, SH], ("shovel", 1))
     //
     // TODO: Replace the vector with a BTreeMap or a tree / trie.
     //
     // NOTE: I attempted to switch the value type to an &Entry, which turned into a
     // lifetime mess. On a deadline; skipping for now.
-    reverse_list: Vec<(String, (String, i32))>,
+    reverse_list: Vec<(Vec<Phoneme>, (String, i32))>,
+
+    /// Like `reverse_list`, but with the Entry::alliteration_key()s, for finding words by how
+    /// they start. MUST REMAIN SORTED.
+    forward_list: Vec<(Vec<Symbol>, (String, i32))>,
+
+    /// Maps each stress pattern (see `Phonemes::stress_pattern`) to the entries that have it.
+    stress_index: HashMap<String, Vec<(String, i32)>>,
+
+    /// Maps each rhyme symbol (see `Phonemes::rhyme_symbol`) to the entries that have it, so
+    /// `near_rhymes` only scores the words that could be near rhymes.
+    rhyme_index: HashMap<Symbol, Vec<(String, i32)>>,
+
+    /// Malformed lines skipped while loading in `LoadMode::Lenient`.
+    skipped: Vec<DictionaryError>,
 }
 
 /// Represents a single word along with associated meta-data.
@@ -407,17 +1305,588 @@ pub struct SimilarWord {
 
     /// The word's phonemes.
     pub phonemes: Phonemes,
+
+    /// How the word rhymes with the query.
+    pub kind: RhymeKind,
+
+    /// How many times the word occurs per billion words, if known. See
+    /// `Dictionary::frequency`.
+    pub frequency: Option<u64>,
+
+    /// The parts of speech the word can be, if known. See `Entry::metadata`.
+    pub pos: Vec<PartOfSpeech>,
 }
 
-/// Return value for Dictionary::similar(), holding all the results.
+/// Describes how closely a `SimilarWord` rhymes with the query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum RhymeKind {
+    /// The phonemes are exactly the same, e.g. "red" and "read".
+    Identical,
+    /// The sounds match from the last stressed vowel onward, e.g. "diagram" and "program".
+    Perfect,
+    /// Only the last syllable matches, e.g. "apple" and "chapel" (whose stress differs).
+    Partial,
+    /// The endings sound alike without sharing the last syllable, e.g. "shape" and "break".
+    /// These only come from `Dictionary::near_rhymes`.
+    Near,
+    /// The word was found without a rhyme to compare it to, e.g. by `Dictionary::by_stress`.
+    Unrhymed,
+}
+
+impl std::str::FromStr for RhymeKind {
+    type Err = String;
+
+    /// Parses the lowercase name of the kind, e.g. "perfect".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "identical" => Ok(RhymeKind::Identical),
+            "perfect" => Ok(RhymeKind::Perfect),
+            "partial" => Ok(RhymeKind::Partial),
+            "near" => Ok(RhymeKind::Near),
+            "unrhymed" => Ok(RhymeKind::Unrhymed),
+            _ => Err(format!("unknown rhyme kind: {}", s)),
+        }
+    }
+}
+
+/// The ways a `SimilarResult` can be sorted. See `SimilarResult::sort_by`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SimilarOrder {
+    /// Decreasing by score, then alphabetically. This is the default.
+    #[default]
+    Score,
+    /// Alphabetically by the word.
+    Word,
+    /// Increasing by number of syllables, then decreasing by score.
+    Syllables,
+    /// Decreasing by frequency, so the most common words come first, then decreasing by score.
+    Frequency,
+}
+
+impl std::str::FromStr for SimilarOrder {
+    type Err = String;
+
+    /// Parses the lowercase name of the order, e.g. "syllables".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "score" => Ok(SimilarOrder::Score),
+            "word" => Ok(SimilarOrder::Word),
+            "syllables" => Ok(SimilarOrder::Syllables),
+            "frequency" => Ok(SimilarOrder::Frequency),
+            _ => Err(format!("unknown sort order: {}", s)),
+        }
+    }
+}
+
+/// Criteria for narrowing down the words in a `SimilarResult`. See `SimilarResult::filter`.
+///
+/// All of the criteria are optional, and the default filter keeps everything.
+#[derive(Clone, Debug, Default)]
+pub struct SimilarFilter {
+    /// The minimum number of syllables, inclusive.
+    pub min_syllables: Option<i32>,
+    /// The maximum number of syllables, inclusive.
+    pub max_syllables: Option<i32>,
+    /// The minimum `SimilarWord::score`, inclusive.
+    pub min_score: Option<i32>,
+    /// Only keep words that rhyme in this way.
+    pub kind: Option<RhymeKind>,
+    /// Only keep words with exactly this stress pattern, e.g. "10". See `Phonemes::stress_pattern`.
+    pub stress: Option<String>,
+    /// Only keep words this common. Words without a frequency never match.
+    pub frequency: Option<FrequencyBand>,
+    /// Only keep words that can be this part of speech. Words without metadata never match.
+    pub pos: Option<PartOfSpeech>,
+}
+
+impl SimilarFilter {
+    /// Returns whether `word` meets all of the criteria.
+    pub fn matches(&self, word: &SimilarWord) -> bool {
+        if let Some(n) = self.min_syllables {
+            if word.syllables < n {
+                return false;
+            }
+        }
+        if let Some(n) = self.max_syllables {
+            if word.syllables > n {
+                return false;
+            }
+        }
+        if let Some(n) = self.min_score {
+            if word.score < n {
+                return false;
+            }
+        }
+        if let Some(kind) = self.kind {
+            if word.kind != kind {
+                return false;
+            }
+        }
+        if let Some(stress) = &self.stress {
+            if &word.phonemes.stress_pattern() != stress {
+                return false;
+            }
+        }
+        if let Some(band) = self.frequency {
+            if word.frequency.map(FrequencyBand::of) != Some(band) {
+                return false;
+            }
+        }
+        if let Some(pos) = self.pos {
+            if !word.pos.contains(&pos) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A query for `Dictionary::by_stress`: words with a stress pattern, e.g. "010" for
+/// "da-DUM-da", that may also have to rhyme with something and start with some sounds.
+#[derive(Clone, Debug, Default)]
+pub struct StressQuery {
+    /// The stress digits of the vowels, as in `Phonemes::stress_pattern`, where `?` allows any
+    /// stress. Use `StressQuery::new` to check it.
+    pub stress: String,
+    /// Only keep words that rhyme with these phonemes, as `Dictionary::similar_to_phonemes`
+    /// would find them.
+    pub rhyme: Option<Phonemes>,
+    /// The word `rhyme` came from, which is left out of the results.
+    pub rhyme_word: Option<String>,
+    /// Only keep words that start with these sounds. The stress of vowels is ignored.
+    pub onset: Vec<Phoneme>,
+}
+
+impl StressQuery {
+    /// Returns a query for the stress pattern, e.g. "010", or an error if it has anything but
+    /// the digits 0, 1 and 2 and `?`.
+    pub fn new(stress: &str) -> Result<StressQuery, String> {
+        if stress.is_empty() || !stress.chars().all(|c| matches!(c, '0' | '1' | '2' | '?')) {
+            return Err(format!(
+                "invalid stress pattern \"{}\"; use the digits 0, 1 and 2, and ? for any",
+                stress
+            ));
+        }
+        return Ok(StressQuery {
+            stress: stress.to_string(),
+            ..Default::default()
+        });
+    }
+
+    /// Returns whether `pattern`, from `Phonemes::stress_pattern`, fits `stress`.
+    fn matches_stress(&self, pattern: &str) -> bool {
+        pattern.len() == self.stress.len()
+            && pattern
+                .chars()
+                .zip(self.stress.chars())
+                .all(|(p, s)| s == '?' || p == s)
+    }
+
+    /// Returns `entry` as a result, if it meets the rhyme and onset criteria.
+    fn check(&self, entry: &Entry) -> Option<SimilarWord> {
+        let phonemes = &entry.phonemes.phonemes;
+        if phonemes.len() < self.onset.len()
+            || !self
+                .onset
+                .iter()
+                .zip(phonemes)
+                .all(|(a, b)| a.symbol() == b.symbol())
+        {
+            return None;
+        }
+        let (score, kind) = match &self.rhyme {
+            Some(rhyme) => {
+                if self.rhyme_word.as_deref() == Some(entry.word.as_str())
+                    || !rhyme.rhymes_with(&entry.phonemes)
+                {
+                    return None;
+                }
+                (
+                    rhyme.similarity_score(&entry.phonemes),
+                    rhyme.rhyme_kind(&entry.phonemes),
+                )
+            }
+            None => (0, RhymeKind::Unrhymed),
+        };
+        return Some(SimilarWord {
+            word: entry.word.clone(),
+            syllables: entry.num_syllables(),
+            score,
+            phonemes: entry.phonemes.clone(),
+            kind,
+            frequency: None,
+            pos: entry.parts_of_speech().to_vec(),
+        });
+    }
+}
+
+/// How much of the start of a word has to match for `Dictionary::alliterations`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alliteration {
+    /// The consonants before the first vowel, e.g. F for "phantom" or S T R for "strong". For a
+    /// word that starts with a vowel, e.g. "apple", it's that vowel.
+    Onset,
+    /// The first N phonemes, e.g. F AE for "phantom" with N = 2.
+    Phonemes(usize),
+}
+
+/// Runs `query` once for each pronunciation of `rhyme_word`, like `Dictionary::similar`, so
+/// there's one group per variant of the word. Without a rhyme word, this is just `by_stress`.
+///
+/// Returns an error if `rhyme_word` isn't in the dictionary.
+pub fn by_stress_rhyming_with(
+    dict: &dyn Dictionary,
+    query: &StressQuery,
+    rhyme_word: Option<&str>,
+) -> Result<SimilarResult, String> {
+    let word = match rhyme_word {
+        Some(word) => word,
+        None => return Ok(dict.by_stress(query)),
+    };
+    let variants = dict
+        .lookup(word)
+        .ok_or_else(|| format!("{} isn't in the dictionary", word))?;
+    let mut out = SimilarResult { groups: vec![] };
+    for entry in variants {
+        let variant_query = StressQuery {
+            rhyme: Some(entry.phonemes.clone()),
+            rhyme_word: Some(word.to_string()),
+            ..query.clone()
+        };
+        let mut result = dict.by_stress(&variant_query);
+        if let Some(mut group) = result.groups.pop() {
+            group.variant = Some(entry.variant);
+            out.groups.push(group);
+        }
+    }
+    out.remove_duplicates();
+    return Ok(out);
+}
+
+/// The most words in a phrase from `mosaic_rhymes`, e.g. 3 for "was spit all".
+const MAX_MOSAIC_WORDS: usize = 3;
+
+/// The most words `mosaic_rhymes` tries in each place in a phrase, and the most endings it tries
+/// after each first word. The lookup page finds mosaic rhymes while it holds the shelf, so this
+/// keeps the work bounded on a large dictionary.
+const MAX_MOSAIC_CANDIDATES: usize = 200;
+
+/// Returns up to `limit` phrases of two or more words that rhyme with the given phonemes when
+/// they're said together, e.g. "know it" for "poet" or "door hinge" for "orange".
+///
+/// The first word ends with the start of the target's ending (see `Phonemes::stressed_tail`),
+/// including its stressed vowel, and the words after it sound like the rest when they're said
+/// without stress (see `Phoneme::reduced`). Since
+/// the rest needs a vowel of its own, only targets with unstressed syllables after the rhyming
+/// vowel, like "poet" but not "cat", have any.
+///
+/// The result has exactly one group, with `variant` set to None. Each phrase is a
+/// `SimilarWord` with its words separated by spaces, scored like `similar_to_phonemes`, and
+/// with the kind `RhymeKind::Perfect` or `RhymeKind::Near`. They're ordered by score, then the
+/// most natural first: fewer words, then more common words (by the rarest one, if there are
+/// frequencies), then fewer syllables.
+pub fn mosaic_rhymes(
+    dict: &dyn Dictionary,
+    phonemes: &Phonemes,
+    query: Option<&str>,
+    limit: usize,
+) -> SimilarResult {
+    let mut group = SimilarGroup::new(phonemes, None);
+    let tail = phonemes.stressed_tail();
+    let not_query = |e: &&Entry| query != Some(e.word.as_str());
+    for split in 1..tail.len() {
+        let (head, rest) = tail.split_at(split);
+        if !rest.iter().any(|ph| ph.is_vowel()) {
+            break;
+        }
+        // Score the endings once, with the head standing in for the first word.
+        let endings: Vec<(Vec<&Entry>, i32)> = mosaic_endings(dict, rest, MAX_MOSAIC_WORDS - 1)
+            .into_iter()
+            .filter(|words| words.iter().all(not_query))
+            .filter_map(|words| {
+                let sounds = Phonemes::from_phonemes([head, &unstressed(&words)].concat());
+                let score = phonemes.similarity_score(&sounds);
+                (score >= NEAR_RHYME_MIN_SCORE).then_some((words, score))
+            })
+            .collect();
+        if endings.is_empty() {
+            continue;
+        }
+        let pattern: Pattern = match format!("* {}", Phonemes::from_phonemes(head.to_vec())).parse()
+        {
+            Ok(pattern) => pattern,
+            Err(_) => continue,
+        };
+        let firsts: Vec<&Entry> = dict
+            .search(&pattern)
+            .into_iter()
+            .filter(not_query)
+            .collect();
+        let firsts = most_common(dict, firsts, limit);
+        for (words, _) in &endings {
+            for first in &firsts {
+                let mut sounds = first.phonemes.phonemes.clone();
+                sounds.extend(unstressed(words));
+                let sounds = Phonemes::from_phonemes(sounds);
+                let kind = match phonemes.rhyme_kind(&sounds) {
+                    RhymeKind::Perfect | RhymeKind::Identical => RhymeKind::Perfect,
+                    _ => RhymeKind::Near,
+                };
+                let phrase: Vec<&str> = std::iter::once(*first)
+                    .chain(words.iter().copied())
+                    .map(|e| e.word.as_str())
+                    .collect();
+                // A phrase is as common as its rarest word.
+                let frequency = phrase.iter().map(|w| dict.frequency(w)).min().flatten();
+                group.words.push(SimilarWord {
+                    word: phrase.join(" "),
+                    syllables: sounds.num_syllables(),
+                    score: phonemes.similarity_score(&sounds),
+                    phonemes: sounds,
+                    kind,
+                    frequency,
+                    pos: vec![],
+                });
+            }
+        }
+    }
+    group.words.sort_by(|a, b| {
+        let words = |w: &SimilarWord| w.word.matches(' ').count();
+        (b.score, words(a), b.frequency, a.syllables, &a.word).cmp(&(
+            a.score,
+            words(b),
+            a.frequency,
+            b.syllables,
+            &b.word,
+        ))
+    });
+    // Keep the best pronunciation of each phrase.
+    let mut seen = HashSet::new();
+    group.words.retain(|w| seen.insert(w.word.clone()));
+    group.words.truncate(limit);
+    return SimilarResult {
+        groups: vec![group],
+    };
+}
+
+/// Returns the sequences of up to `max_words` words that could sound like `rest`, the end of a
+/// mosaic rhyme. Every word but the last has exactly the sounds of its part of `rest`, ignoring
+/// stress, and the last only has to end the same way with as many syllables. The caller scores
+/// them.
+///
+/// There are at most `MAX_MOSAIC_CANDIDATES` of them, with the fewest words and then the most
+/// common words first.
+fn mosaic_endings<'a>(
+    dict: &'a dyn Dictionary,
+    rest: &[Phoneme],
+    max_words: usize,
+) -> Vec<Vec<&'a Entry>> {
+    let syllables = rest.iter().filter(|ph| ph.is_vowel()).count();
+    let last = rest[rest.len() - 1];
+    let mut lasts: Vec<&Entry> = vec![];
+    for ending in with_any_stress(&[last]) {
+        if let Ok(pattern) = format!("syllables:{} * {}", syllables, ending[0]).parse::<Pattern>() {
+            lasts.extend(dict.search(&pattern));
+        }
+    }
+    let mut out: Vec<Vec<&Entry>> = most_common(dict, lasts, MAX_MOSAIC_CANDIDATES)
+        .into_iter()
+        .map(|e| vec![e])
+        .collect();
+    if max_words < 2 {
+        return out;
+    }
+    for split in 1..rest.len() {
+        let (word, after) = rest.split_at(split);
+        if !word.iter().any(|ph| ph.is_vowel()) || !after.iter().any(|ph| ph.is_vowel()) {
+            continue;
+        }
+        let mut middles: Vec<&Entry> = vec![];
+        for sounds in with_any_stress(word) {
+            let homophones = dict.homophones(&Phonemes::from_phonemes(sounds), None);
+            middles.extend(homophones.groups[0].words.iter().filter_map(|w| {
+                dict.lookup(&w.word)?
+                    .iter()
+                    .find(|e| e.phonemes == w.phonemes)
+            }));
+        }
+        if middles.is_empty() {
+            continue;
+        }
+        let middles = most_common(dict, middles, MAX_MOSAIC_CANDIDATES);
+        for ending in mosaic_endings(dict, after, max_words - 1) {
+            for middle in &middles {
+                if out.len() >= MAX_MOSAIC_CANDIDATES {
+                    return out;
+                }
+                out.push([vec![*middle], ending.clone()].concat());
+            }
+        }
+    }
+    return out;
+}
+
+/// Returns `phonemes` with each combination of stresses on its vowels, e.g. "N OW0", "N OW1"
+/// and "N OW2" for "N OW1".
+fn with_any_stress(phonemes: &[Phoneme]) -> Vec<Vec<Phoneme>> {
+    let mut out: Vec<Vec<Phoneme>> = vec![vec![]];
+    for ph in phonemes {
+        let choices: Vec<Phoneme> = if ph.is_vowel() {
+            [Stress::Unstressed, Stress::Primary, Stress::Secondary]
+                .iter()
+                .map(|stress| ph.with_stress(*stress))
+                .collect()
+        } else {
+            vec![*ph]
+        };
+        out = out
+            .iter()
+            .flat_map(|start| {
+                choices
+                    .iter()
+                    .map(move |ph| [start.as_slice(), &[*ph]].concat())
+            })
+            .collect();
+    }
+    return out;
+}
+
+/// Returns up to `limit` of `entries`, the most common first (if there are frequencies), then
+/// those with the fewest syllables.
+fn most_common<'a>(
+    dict: &dyn Dictionary,
+    mut entries: Vec<&'a Entry>,
+    limit: usize,
+) -> Vec<&'a Entry> {
+    entries.sort_by_key(|e| {
+        let frequency = dict.frequency(&e.word);
+        (
+            std::cmp::Reverse(frequency),
+            e.num_syllables(),
+            e.word.clone(),
+        )
+    });
+    entries.truncate(limit);
+    return entries;
+}
+
+/// Returns the phonemes of `words` one after another, as they sound after a stressed word
+/// (see `Phoneme::reduced`).
+fn unstressed(words: &[&Entry]) -> Vec<Phoneme> {
+    words
+        .iter()
+        .flat_map(|e| e.phonemes.phonemes.iter())
+        .map(|ph| ph.reduced())
+        .collect()
+}
+
+/// The words that are similar to one pronunciation of the query.
 // NOTE! If this structure is changed, verify that the templates still render.
 #[derive(Clone, Debug, Serialize)]
 #[serde(crate = "rocket::serde")]
-pub struct SimilarResult {
+pub struct SimilarGroup {
+    /// The variant of the query word that these words are similar to, e.g. 2 for "wind(2)".
+    ///
+    /// This is None when the query was a bare phoneme sequence instead of a word.
+    pub variant: Option<i32>,
+
+    /// The phonemes of the query that these words are similar to.
+    pub phonemes: Phonemes,
+
     /// All of the similar words sorted decreasing by similarity.
     pub words: Vec<SimilarWord>,
 }
 
+impl SimilarGroup {
+    fn new(phonemes: &Phonemes, variant: Option<i32>) -> SimilarGroup {
+        SimilarGroup {
+            variant,
+            phonemes: phonemes.clone(),
+            words: vec![],
+        }
+    }
+}
+
+/// Return value for Dictionary::similar(), holding all the results.
+///
+/// The results are grouped by the pronunciation of the query, since e.g. "wind" (the weather)
+/// and "wind(2)" (the clock) rhyme with very different words.
+// NOTE! If this structure is changed, verify that the templates still render.
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SimilarResult {
+    /// One group per pronunciation of the query, in the order of the query's variants.
+    pub groups: Vec<SimilarGroup>,
+}
+
+impl SimilarResult {
+    /// Returns the total number of words across all of the groups.
+    pub fn num_words(&self) -> usize {
+        self.groups.iter().map(|g| g.words.len()).sum()
+    }
+
+    /// Returns whether there are no words in any of the groups.
+    pub fn is_empty(&self) -> bool {
+        self.groups.iter().all(|g| g.words.is_empty())
+    }
+
+    /// Removes the words in every group that don't match `filter`.
+    pub fn filter(&mut self, filter: &SimilarFilter) {
+        for group in &mut self.groups {
+            group.words.retain(|w| filter.matches(w));
+        }
+    }
+
+    /// Sorts the words within each group.
+    pub fn sort_by(&mut self, order: SimilarOrder) {
+        for group in &mut self.groups {
+            match order {
+                SimilarOrder::Score => group.words.sort(),
+                SimilarOrder::Word => group.words.sort_by(|a, b| a.word.cmp(&b.word)),
+                SimilarOrder::Syllables => group
+                    .words
+                    .sort_by(|a, b| a.syllables.cmp(&b.syllables).then_with(|| a.cmp(b))),
+                SimilarOrder::Frequency => group
+                    .words
+                    .sort_by(|a, b| b.frequency.cmp(&a.frequency).then_with(|| a.cmp(b))),
+            }
+        }
+    }
+
+    /// Removes words (by text and phonemes) that appear more than once in the result.
+    ///
+    /// A word that is similar to several pronunciations of the query is kept only in the
+    /// group where it scores highest, or the earliest group if the scores are tied. This also
+    /// removes identical words found in several dictionaries.
+    fn remove_duplicates(&mut self) {
+        use std::collections::HashMap;
+        // Maps (word, phonemes) to the (group index, score) where the word should be kept.
+        let mut best: HashMap<(String, Phonemes), (usize, i32)> = HashMap::new();
+        for (i, group) in self.groups.iter().enumerate() {
+            for w in &group.words {
+                let key = (w.word.clone(), w.phonemes.clone());
+                let kept = best.entry(key).or_insert((i, w.score));
+                if w.score > kept.1 {
+                    *kept = (i, w.score);
+                }
+            }
+        }
+        for (i, group) in self.groups.iter_mut().enumerate() {
+            group.words.retain(|w| {
+                let key = (w.word.clone(), w.phonemes.clone());
+                // Removing the key means only the first copy in the best group is kept.
+                if best.get(&key) == Some(&(i, w.score)) {
+                    best.remove(&key);
+                    true
+                } else {
+                    false
+                }
+            });
+        }
+    }
+}
+
 impl Ord for SimilarWord {
     fn cmp(&self, other: &Self) -> Ordering {
         // Want to sort descending by score.
@@ -462,97 +1931,577 @@ pub trait Dictionary {
     /// `lookup_variant("era", 2)` would return the "IH1 R AH0" entry above.
     fn lookup_variant(&self, term: &str, variant: i32) -> Option<&Entry>;
 
+    /// Returns all of the terms in the dictionary, in no particular order.
+    fn terms(&self) -> Box<dyn Iterator<Item = &str> + '_>;
+
+    /// Returns how many times `term` occurs per billion words of text, or None if it isn't
+    /// known. With a frequency list (see `Shelf::init_frequencies`), terms that aren't in the
+    /// list are 0; otherwise this comes from the entries' metadata, if any.
+    fn frequency(&self, term: &str) -> Option<u64>;
+
     /// Returns a collection of words that are similar to (rhyme with) the given word.
     ///
     /// Args:
     /// * `query` - a single word of user text try and rhyme with.
     ///
-    /// The results are ordered in decreasing order of similarity to the query word.
+    /// The results are grouped by the pronunciations (variants) of the query, and each group is
+    /// ordered in decreasing order of similarity. Words appear in at most one group.
     ///
     /// TODO: Rename "similar" with "rhyme" everywhere, because it's more accurate.
     fn similar(&self, query: &str) -> SimilarResult;
 
     /// Returns a collection of words that are similar to (rhyme with) the given phonemes.
     ///
+    /// The result has exactly one group, with `variant` set to None.
+    ///
     /// Args:
     /// * `phonemes` - the phonemes of the word to match against
     /// * `query` - a single word of user text (only used to strip self-syns, so optional).
     fn similar_to_phonemes(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult;
+
+    /// Returns up to `limit` words whose endings sound like the given phonemes, but that don't
+    /// share the last syllable, so `similar_to_phonemes` leaves them out.
+    ///
+    /// The result has exactly one group, with `variant` set to None, ordered by decreasing
+    /// score. Only words scoring at least `NEAR_RHYME_MIN_SCORE` are included, with the kind
+    /// `RhymeKind::Near`, e.g. "break" (91) and "grapes" (80) for "shape".
+    ///
+    /// This scores every entry with a rhyme vowel close to the query's, so it's slower than
+    /// `similar()`.
+    fn near_rhymes(&self, phonemes: &Phonemes, query: Option<&str>, limit: usize) -> SimilarResult;
+
+    /// Returns every entry whose phonemes match `pattern`, sorted by word and variant.
+    ///
+    /// See `pattern` for the syntax, e.g. "* AE1 T" for words ending in "AE1 T".
+    fn search(&self, pattern: &Pattern) -> Vec<&Entry>;
+
+    /// Returns the words that fit `query`'s stress pattern, rhyme and onset.
+    ///
+    /// The result has exactly one group, with `variant` set to None and the phonemes of the
+    /// rhyme, if any. With a rhyme, the words are scored and ordered as in `similar()`;
+    /// without one, they all score 0 with the kind `RhymeKind::Unrhymed`, in alphabetical order.
+    fn by_stress(&self, query: &StressQuery) -> SimilarResult;
+
+    /// Returns up to `limit` words that start with the same sounds as the given phonemes, as
+    /// far as `span` reaches, e.g. "fantastic" and "photo" for "phantom". The stress of vowels
+    /// is ignored.
+    ///
+    /// The result has exactly one group, with `variant` set to None. The words have the kind
+    /// `RhymeKind::Unrhymed` and score the number of phonemes they share with the start of the
+    /// query, so the closest come first.
+    fn alliterations(
+        &self,
+        phonemes: &Phonemes,
+        query: Option<&str>,
+        span: Alliteration,
+        limit: usize,
+    ) -> SimilarResult;
+
+    /// Returns the words with exactly the given phonemes, e.g. "pear" and "pare" for "pair".
+    ///
+    /// The result has exactly one group, with `variant` set to None. The words all score 100
+    /// with the kind `RhymeKind::Identical`, in alphabetical order.
+    fn homophones(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult;
+
+    /// Returns the words one phoneme away from the given phonemes, by substituting, inserting
+    /// or deleting one, e.g. "sheep", "shop" and "slip" for "ship".
+    ///
+    /// The result has exactly one group, with `variant` set to None. The words have the kind
+    /// `RhymeKind::Unrhymed` and score 0, in alphabetical order.
+    fn minimal_pairs(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult;
+
+    /// Returns the malformed lines that were skipped when loading the dictionary, if it was
+    /// loaded from a file.
+    fn skipped_lines(&self) -> &[DictionaryError] {
+        &[]
+    }
 }
 
+/// The lowest `SimilarWord::score` returned by `Dictionary::near_rhymes`.
+pub const NEAR_RHYME_MIN_SCORE: i32 = 75;
+
 // TODO: Replace this wasteful and crude similarity algorithm.
 //
 // The current algorithm works by:
 //   - Keep a sorted vector of the reverse phonemes (so similar endings appear adjacent).
 //   - Return any terms that share the very last syllable sound.
+//   - Score them by aligning their endings (see `Phonemes::similarity_score`).
 //
-// TODO: Prioritize terms that are more similar (share more phonemes at the tail).
+// Near rhymes don't share the last syllable, so those are found by scoring every entry.
 impl DictionaryImpl {
     /// Creates a new empty Dictionary.
     pub fn new() -> DictionaryImpl {
         DictionaryImpl {
+            name: None,
             entries: std::collections::HashMap::new(),
             reverse_list: vec![],
+            forward_list: vec![],
+            stress_index: HashMap::new(),
+            rhyme_index: HashMap::new(),
+            skipped: vec![],
         }
     }
 
-    /// Creates a new dictionary, populated from the given text file.
-    pub fn new_from_cmudict_file(path: &str) -> Result<DictionaryImpl, Box<dyn Error>> {
+    /// Creates a new empty Dictionary, whose entries will have `name` as their source.
+    pub fn with_name(name: &str) -> DictionaryImpl {
         let mut dict = DictionaryImpl::new();
+        dict.name = Some(Arc::from(name));
+        dict
+    }
 
-        use std::io::{BufRead, BufReader};
-        let f = std::fs::File::open(path)?;
-        let br = BufReader::new(f);
-        for line in br.lines() {
-            dict.insert_internal(Entry::new(line?.trim()));
+    /// Sets the `metadata` of every entry that's in the given sidecar file.
+    pub fn apply_metadata(&mut self, metadata: &MetadataFile) {
+        for entry in self.entries.values_mut().flatten() {
+            if let Some(m) = metadata.get(&entry.word, entry.variant) {
+                entry.metadata = Some(m.clone());
+            }
         }
-        dict.reverse_list.sort();
-        return Ok(dict);
     }
 
-    /// Inserts a single entry.
-    #[cfg(test)] // TODO: Remove?
-    pub fn insert(&mut self, entry: Entry) {
-        self.insert_internal(entry);
-        self.reverse_list.sort();
+    /// Creates a new dictionary, populated from the given text file.
+    ///
+    /// Blank lines and comments are ignored. Malformed lines fail the load in
+    /// `LoadMode::Strict`, and are skipped and listed in `skipped_lines()` otherwise.
+    pub fn new_from_cmudict_file(
+        path: &str,
+        mode: LoadMode,
+    ) -> Result<DictionaryImpl, DictionaryError> {
+        let f = std::fs::File::open(path).map_err(|error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        return DictionaryImpl::from_cmudict_reader(path, std::io::BufReader::new(f), mode);
     }
 
-    /// Inserts a single entry as though it would appear as a single line of the cmudict file.
-    #[cfg(test)] // TODO: Remove?
-    pub fn insert_raw(&mut self, line: &str) {
-        let entry = Entry::new(line);
-        self.insert(entry);
+    /// Like `new_from_cmudict_file()`, but reads the text from `reader`. `path` names the
+    /// dictionary and is used in errors.
+    fn from_cmudict_reader(
+        path: &str,
+        reader: impl std::io::BufRead,
+        mode: LoadMode,
+    ) -> Result<DictionaryImpl, DictionaryError> {
+        let mut dict = DictionaryImpl::with_name(path);
+        let io_error = |error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        };
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(io_error)?;
+            if strip_comment(&line).trim().is_empty() {
+                continue;
+            }
+            match Entry::parse(&line) {
+                Ok(entry) => dict.insert_internal(entry),
+                Err(reason) => {
+                    let error = DictionaryError::Malformed {
+                        path: path.to_string(),
+                        line: i + 1,
+                        reason,
+                    };
+                    if mode == LoadMode::Strict {
+                        return Err(error);
+                    }
+                    dict.skipped.push(error);
+                }
+            }
+        }
+        dict.reverse_list.sort();
+        dict.forward_list.sort();
+        return Ok(dict);
     }
 
-    /// Inserts all of the items in `lines` as though they were individually insert_raw()d.
-    #[cfg(test)] // TODO: Remove?
-    pub fn insert_all(&mut self, lines: &Vec<&str>) {
-        for line in lines {
-            let entry = Entry::new(line);
-            self.insert_internal(entry);
+    /// Creates a new dictionary from entries that were read some other way, e.g. by `lexicon`,
+    /// with the malformed ones that were `skipped`.
+    pub fn from_entries(
+        path: &str,
+        entries: Vec<Entry>,
+        skipped: Vec<DictionaryError>,
+    ) -> DictionaryImpl {
+        let mut dict = DictionaryImpl::with_name(path);
+        for entry in entries {
+            dict.insert_internal(entry);
         }
-        self.reverse_list.sort();
+        dict.reverse_list.sort();
+        dict.forward_list.sort();
+        dict.skipped = skipped;
+        return dict;
     }
 
-    fn insert_internal(&mut self, entry: Entry) {
-        self.reverse_list
-            .push((entry.similarity_key(), (entry.word.clone(), entry.variant)));
-        // word is used in the forward list in order to match as many options as possible from a
-        // user's text.
-        let key = entry.word.clone();
-        if let Some(v) = self.entries.get_mut(&key) {
-            v.push(entry);
-        } else {
-            self.entries.insert(key, vec![entry]);
+    /// Loads the dictionary file at `path`, from its compiled index if that's fresh.
+    ///
+    /// The index (see `compile_index()`) is used only if it was compiled from exactly the
+    /// current contents of `path`. Otherwise, this says why and falls back to parsing the text.
+    pub fn load(path: &str, mode: LoadMode) -> Result<DictionaryImpl, DictionaryError> {
+        let source = std::fs::read(path).map_err(|error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        let index = index_path(path);
+        if std::path::Path::new(&index).exists() {
+            match DictionaryImpl::from_index(&index, path, checksum(&source)) {
+                Ok(mut dict) => {
+                    if mode == LoadMode::Strict && !dict.skipped.is_empty() {
+                        return Err(dict.skipped.remove(0));
+                    }
+                    return Ok(dict);
+                }
+                Err(reason) => println!("Ignoring {}: {}", index, reason),
+            }
         }
+        return DictionaryImpl::from_cmudict_reader(path, &source[..], mode);
     }
 
-    /// Returns the number of entries in the dictionary.
-    #[cfg(test)] // TODO: Remove?
-    pub fn len(&self) -> usize {
-        return self.entries.len();
+    /// Parses the dictionary file at `path` and writes its compiled index beside it, at
+    /// `index_path(path)`. Returns the parsed dictionary.
+    pub fn compile_index(path: &str, mode: LoadMode) -> Result<DictionaryImpl, DictionaryError> {
+        let index = index_path(path);
+        let source = std::fs::read(path).map_err(|error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        let dict = DictionaryImpl::from_cmudict_reader(path, &source[..], mode)?;
+        dict.write_index(&index, checksum(&source))
+            .map_err(|error| DictionaryError::Io { path: index, error })?;
+        return Ok(dict);
     }
-}
+
+    /// Writes the compiled index. See `INDEX_VERSION` for the layout.
+    fn write_index(&self, path: &str, source_checksum: u64) -> std::io::Result<()> {
+        let mut all: Vec<&Entry> = self.entries.values().flatten().collect();
+        all.sort_by(|a, b| (&a.word, a.variant).cmp(&(&b.word, b.variant)));
+        let positions: HashMap<(&str, i32), u32> = all
+            .iter()
+            .enumerate()
+            .map(|(i, e)| ((e.word.as_str(), e.variant), i as u32))
+            .collect();
+
+        let mut out = IndexWriter::default();
+        out.bytes.extend_from_slice(INDEX_MAGIC);
+        out.u32(INDEX_VERSION);
+        out.u64(source_checksum);
+        out.u32(all.len() as u32);
+        for entry in &all {
+            out.str(&entry.word);
+            out.u32(entry.variant as u32);
+            out.u32(entry.phonemes.phonemes.len() as u32);
+            for ph in &entry.phonemes.phonemes {
+                out.bytes.push(ph.to_byte());
+            }
+        }
+        out.u32(self.reverse_list.len() as u32);
+        for (_, (word, variant)) in &self.reverse_list {
+            out.u32(positions[&(word.as_str(), *variant)]);
+        }
+        out.u32(self.forward_list.len() as u32);
+        for (_, (word, variant)) in &self.forward_list {
+            out.u32(positions[&(word.as_str(), *variant)]);
+        }
+        out.u32(self.skipped.len() as u32);
+        for error in &self.skipped {
+            if let DictionaryError::Malformed { line, reason, .. } = error {
+                out.u32(*line as u32);
+                out.str(reason);
+            }
+        }
+        let trailer = checksum(&out.bytes);
+        out.u64(trailer);
+
+        let tmp_path = format!("{}.tmp", path);
+        std::fs::write(&tmp_path, &out.bytes)?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Reads a compiled index for the dictionary at `source_path`, or returns why it can't be
+    /// used, e.g. because it's corrupt or was compiled from a different version of the source.
+    fn from_index(
+        path: &str,
+        source_path: &str,
+        source_checksum: u64,
+    ) -> Result<DictionaryImpl, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        if bytes.len() < INDEX_MAGIC.len() + 8 || &bytes[..INDEX_MAGIC.len()] != INDEX_MAGIC {
+            return Err("it isn't a compiled dictionary".to_string());
+        }
+        let (body, trailer) = bytes.split_at(bytes.len() - 8);
+        if checksum(body).to_le_bytes() != trailer {
+            return Err("it's corrupt; run 'poet dict compile' to rebuild it".to_string());
+        }
+        let mut input = IndexReader {
+            bytes: body,
+            pos: INDEX_MAGIC.len(),
+        };
+        let version = input.u32()?;
+        if version != INDEX_VERSION {
+            return Err(format!(
+                "it's version {}, not {}; run 'poet dict compile' to rebuild it",
+                version, INDEX_VERSION
+            ));
+        }
+        if input.u64()? != source_checksum {
+            return Err(format!(
+                "{} has changed since it was compiled; run 'poet dict compile' to update it",
+                source_path
+            ));
+        }
+
+        let mut dict = DictionaryImpl::with_name(source_path);
+        let num_entries = input.u32()? as usize;
+        dict.entries.reserve(num_entries);
+        // The reverse_list row for each entry, in entry order, to be put in rhyme order below.
+        let mut rows: Vec<IndexRow<Phoneme>> = Vec::with_capacity(num_entries);
+        // Likewise for forward_list.
+        let mut forward_rows: Vec<IndexRow<Symbol>> = Vec::with_capacity(num_entries);
+        for _ in 0..num_entries {
+            let word = input.str()?;
+            let variant = input.u32()? as i32;
+            let len = input.u32()? as usize;
+            let phonemes = input
+                .take(len)?
+                .iter()
+                .map(|b| Phoneme::from_byte(*b))
+                .collect::<Option<Vec<Phoneme>>>()
+                .ok_or("it has an invalid phoneme")?;
+            let entry = Entry {
+                word: word.clone(),
+                phonemes: Phonemes::from_phonemes(phonemes),
+                variant,
+                source: dict.name.clone(),
+                metadata: None,
+            };
+            rows.push(Some((entry.similarity_key(), (word.clone(), variant))));
+            forward_rows.push(Some((entry.alliteration_key(), (word.clone(), variant))));
+            dict.index_stress(&entry);
+            dict.index_rhyme(&entry);
+            dict.entries.entry(word).or_default().push(entry);
+        }
+
+        // The reverse and forward lists were saved in sorted order, so they don't need sorting
+        // again.
+        let num_reverse = input.u32()? as usize;
+        dict.reverse_list.reserve(num_reverse);
+        for _ in 0..num_reverse {
+            let row = rows
+                .get_mut(input.u32()? as usize)
+                .and_then(|row| row.take())
+                .ok_or("it has an invalid rhyme index")?;
+            dict.reverse_list.push(row);
+        }
+        let num_forward = input.u32()? as usize;
+        dict.forward_list.reserve(num_forward);
+        for _ in 0..num_forward {
+            let row = forward_rows
+                .get_mut(input.u32()? as usize)
+                .and_then(|row| row.take())
+                .ok_or("it has an invalid alliteration index")?;
+            dict.forward_list.push(row);
+        }
+
+        let num_skipped = input.u32()? as usize;
+        for _ in 0..num_skipped {
+            dict.skipped.push(DictionaryError::Malformed {
+                path: source_path.to_string(),
+                line: input.u32()? as usize,
+                reason: input.str()?,
+            });
+        }
+        return Ok(dict);
+    }
+
+    /// Inserts a single entry, keeping `reverse_list` and `forward_list` sorted without a full
+    /// re-sort.
+    pub fn insert(&mut self, mut entry: Entry) {
+        entry.source = self.name.clone();
+        let reverse_key = (entry.similarity_key(), (entry.word.clone(), entry.variant));
+        let pos = match self.reverse_list.binary_search(&reverse_key) {
+            Ok(pos) | Err(pos) => pos,
+        };
+        self.reverse_list.insert(pos, reverse_key);
+        let forward_key = (
+            entry.alliteration_key(),
+            (entry.word.clone(), entry.variant),
+        );
+        let pos = match self.forward_list.binary_search(&forward_key) {
+            Ok(pos) | Err(pos) => pos,
+        };
+        self.forward_list.insert(pos, forward_key);
+        self.index_stress(&entry);
+        self.index_rhyme(&entry);
+        self.entries
+            .entry(entry.word.clone())
+            .or_default()
+            .push(entry);
+    }
+
+    /// Removes the entry for the given term and variant, returning it if it was present.
+    pub fn remove(&mut self, word: &str, variant: i32) -> Option<Entry> {
+        let entries = self.entries.get_mut(word)?;
+        let index = entries.iter().position(|e| e.variant == variant)?;
+        let entry = entries.remove(index);
+        if entries.is_empty() {
+            self.entries.remove(word);
+        }
+        let reverse_key = (entry.similarity_key(), (entry.word.clone(), entry.variant));
+        if let Ok(pos) = self.reverse_list.binary_search(&reverse_key) {
+            self.reverse_list.remove(pos);
+        }
+        let forward_key = (
+            entry.alliteration_key(),
+            (entry.word.clone(), entry.variant),
+        );
+        if let Ok(pos) = self.forward_list.binary_search(&forward_key) {
+            self.forward_list.remove(pos);
+        }
+        let pattern = entry.phonemes.stress_pattern();
+        if let Some(keys) = self.stress_index.get_mut(&pattern) {
+            keys.retain(|(w, v)| (w.as_str(), *v) != (word, variant));
+            if keys.is_empty() {
+                self.stress_index.remove(&pattern);
+            }
+        }
+        if let Some(symbol) = entry.phonemes.rhyme_symbol() {
+            if let Some(keys) = self.rhyme_index.get_mut(&symbol) {
+                keys.retain(|(w, v)| (w.as_str(), *v) != (word, variant));
+                if keys.is_empty() {
+                    self.rhyme_index.remove(&symbol);
+                }
+            }
+        }
+        return Some(entry);
+    }
+
+    /// Writes all of the entries to `path` in cmudict format, sorted by term and variant.
+    ///
+    /// The file is written to a temporary file beside `path` and then renamed over it, so
+    /// a failure part way through leaves the original file untouched. Comments from the
+    /// original file are not preserved.
+    pub fn write_cmudict_file(&self, path: &str) -> std::io::Result<()> {
+        use std::io::Write;
+
+        let mut all: Vec<&Entry> = self.entries.values().flatten().collect();
+        all.sort_by(|a, b| (&a.word, a.variant).cmp(&(&b.word, b.variant)));
+
+        let tmp_path = format!("{}.tmp", path);
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&tmp_path)?);
+        for entry in all {
+            writeln!(out, "{} {}", entry.dict_key(), entry.phonemes)?;
+        }
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    /// Inserts a single entry as though it would appear as a single line of the cmudict file.
+    #[cfg(test)] // TODO: Remove?
+    pub fn insert_raw(&mut self, line: &str) {
+        let entry = Entry::new(line);
+        self.insert(entry);
+    }
+
+    /// Inserts all of the items in `lines` as though they were individually insert_raw()d.
+    #[cfg(test)] // TODO: Remove?
+    pub fn insert_all(&mut self, lines: &Vec<&str>) {
+        for line in lines {
+            let entry = Entry::new(line);
+            self.insert_internal(entry);
+        }
+        self.reverse_list.sort();
+        self.forward_list.sort();
+    }
+
+    fn insert_internal(&mut self, mut entry: Entry) {
+        entry.source = self.name.clone();
+        self.reverse_list
+            .push((entry.similarity_key(), (entry.word.clone(), entry.variant)));
+        self.forward_list.push((
+            entry.alliteration_key(),
+            (entry.word.clone(), entry.variant),
+        ));
+        self.index_stress(&entry);
+        self.index_rhyme(&entry);
+        // word is used in the forward list in order to match as many options as possible from a
+        // user's text.
+        let key = entry.word.clone();
+        if let Some(v) = self.entries.get_mut(&key) {
+            v.push(entry);
+        } else {
+            self.entries.insert(key, vec![entry]);
+        }
+    }
+
+    /// Adds `entry` to the `stress_index`.
+    fn index_stress(&mut self, entry: &Entry) {
+        self.stress_index
+            .entry(entry.phonemes.stress_pattern())
+            .or_default()
+            .push((entry.word.clone(), entry.variant));
+    }
+
+    /// Adds `entry` to the `rhyme_index`.
+    fn index_rhyme(&mut self, entry: &Entry) {
+        if let Some(symbol) = entry.phonemes.rhyme_symbol() {
+            self.rhyme_index
+                .entry(symbol)
+                .or_default()
+                .push((entry.word.clone(), entry.variant));
+        }
+    }
+
+    /// Returns the terms and variants of the entries whose phonemes end with `suffix`.
+    fn ending_with(&self, suffix: &[Phoneme]) -> impl Iterator<Item = &(String, i32)> {
+        let key_prefix: Vec<Phoneme> = suffix.iter().rev().copied().collect();
+        let start = self
+            .reverse_list
+            .partition_point(|(key, _)| key.as_slice() < key_prefix.as_slice());
+        let len =
+            self.reverse_list[start..].partition_point(|(key, _)| key.starts_with(&key_prefix));
+        self.reverse_list[start..start + len].iter().map(|(_, k)| k)
+    }
+
+    /// Returns the terms and variants of the entries whose phonemes start with `prefix`,
+    /// ignoring the stress of vowels.
+    fn starting_with(&self, prefix: &[Phoneme]) -> impl Iterator<Item = &(String, i32)> {
+        let key_prefix: Vec<Symbol> = prefix.iter().map(|p| p.symbol()).collect();
+        let start = self
+            .forward_list
+            .partition_point(|(key, _)| key.as_slice() < key_prefix.as_slice());
+        let len =
+            self.forward_list[start..].partition_point(|(key, _)| key.starts_with(&key_prefix));
+        self.forward_list[start..start + len].iter().map(|(_, k)| k)
+    }
+
+    /// Returns the entries for `keys` as results with the same score and kind, leaving out
+    /// `query`.
+    fn results_for<'a>(
+        &self,
+        keys: impl Iterator<Item = &'a (String, i32)>,
+        query: Option<&str>,
+        kind: RhymeKind,
+        score: i32,
+    ) -> Vec<SimilarWord> {
+        let mut words: Vec<SimilarWord> = keys
+            .filter(|(word, _)| query != Some(word.as_str()))
+            .map(|(word, variant)| {
+                let entry = self.lookup_variant(word, *variant).unwrap();
+                SimilarWord {
+                    word: word.clone(),
+                    syllables: entry.num_syllables(),
+                    score,
+                    phonemes: entry.phonemes.clone(),
+                    kind,
+                    frequency: None,
+                    pos: entry.parts_of_speech().to_vec(),
+                }
+            })
+            .collect();
+        words.sort();
+        return words;
+    }
+
+    /// Returns the number of entries in the dictionary.
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+}
 
 impl Dictionary for DictionaryImpl {
     /// Returns the all of the Entries for the given term, or None.
@@ -560,6 +2509,14 @@ impl Dictionary for DictionaryImpl {
         return self.entries.get(term);
     }
 
+    fn skipped_lines(&self) -> &[DictionaryError] {
+        &self.skipped
+    }
+
+    fn terms(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(self.entries.keys().map(|k| k.as_str()))
+    }
+
     /// Looks up the given term and exact variant.
     fn lookup_variant(&self, term: &str, variant: i32) -> Option<&Entry> {
         if let Some(v) = self.entries.get(term) {
@@ -572,12 +2529,21 @@ impl Dictionary for DictionaryImpl {
         return None;
     }
 
+    /// Returns the highest frequency in the metadata of the term's entries.
+    fn frequency(&self, term: &str) -> Option<u64> {
+        return self
+            .lookup(term)?
+            .iter()
+            .filter_map(|e| e.metadata.as_ref()?.frequency)
+            .max();
+    }
+
     /// Returns terms that share the last syllable with the given term.
     ///
     /// TODO: Replace the return value with something that doesn't have so many copies.
     /// TODO: Make the filtering more discerning, rather than boolean on the last syllable.
     fn similar(&self, query: &str) -> SimilarResult {
-        let mut result = SimilarResult { words: vec![] };
+        let mut result = SimilarResult { groups: vec![] };
 
         let query_variants = self.lookup(query);
         if query_variants.is_none() {
@@ -589,45 +2555,34 @@ impl Dictionary for DictionaryImpl {
         //
         // For example, "our" can be pronounced either to rhyme with "sour" or "far".
         for query_variant in query_variants.unwrap() {
-            // Select entries in the reverse_list that have the same last syllable.
-            // NOTE: This is a "crude approximation" since it excludes some legitimate rhumes.
-            // NOTE: This is a linear scan, which sucks, but it's good enough for now.
-
-            let key_prefix: String = query_variant.phonemes.last_n_syllables(1 /* syllable */);
-            for (prefix, (word, variant)) in &self.reverse_list {
-                if !prefix.starts_with(key_prefix.as_str()) {
-                    continue;
-                }
-                if word == query {
-                    continue; // Ignore self-syns.
-                }
-
-                // This is an exact lookup for the other word variant.
-                // Not-None because reverse_list should be 1:1 with the main map.
-                let potential_rhyme = self.lookup_variant(&word, *variant).unwrap();
-                let score = query_variant
-                    .phonemes
-                    .similarity_score(&potential_rhyme.phonemes);
-                result.words.push(SimilarWord {
-                    word: word.clone(),
-                    syllables: potential_rhyme.num_syllables(),
-                    score: score,
-                    phonemes: potential_rhyme.phonemes.clone(),
-                });
+            let mut group = self.similar_to_phonemes(&query_variant.phonemes, Some(query));
+            if let Some(mut g) = group.groups.pop() {
+                g.variant = Some(query_variant.variant);
+                result.groups.push(g);
             }
         }
-
-        // TODO: This mashes everything together, which is not great. Switch to grouped results.
-        result.words.sort();
+        result.remove_duplicates();
         return result;
     }
 
     fn similar_to_phonemes(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
-        let mut result = SimilarResult { words: vec![] };
-        let key_prefix: String = phonemes.last_n_syllables(1 /* syllable */);
-        for (prefix, (word, variant)) in &self.reverse_list {
-            if !prefix.starts_with(key_prefix.as_str()) {
-                continue;
+        let mut group = SimilarGroup::new(phonemes, None);
+
+        // Select entries in the reverse_list that have the same last syllable. Since the list
+        // is sorted, they're all together, starting at the first key that isn't less.
+        // NOTE: This is a "crude approximation" since it excludes some legitimate rhymes.
+        let key_prefix: Vec<Phoneme> = phonemes
+            .last_n_syllables(1 /* syllable */)
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        let start = self
+            .reverse_list
+            .partition_point(|(key, _)| key.as_slice() < key_prefix.as_slice());
+        for (key, (word, variant)) in &self.reverse_list[start..] {
+            if !key.starts_with(&key_prefix) {
+                break;
             }
             if query.is_some() && word == query.unwrap() {
                 continue; // Ignore self-syns.
@@ -637,15 +2592,189 @@ impl Dictionary for DictionaryImpl {
             // Not-None because reverse_list should be 1:1 with the main map.
             let potential_rhyme = self.lookup_variant(&word, *variant).unwrap();
             let score = phonemes.similarity_score(&potential_rhyme.phonemes);
-            result.words.push(SimilarWord {
+            group.words.push(SimilarWord {
                 word: word.clone(),
                 syllables: potential_rhyme.num_syllables(),
                 score: score,
                 phonemes: potential_rhyme.phonemes.clone(),
+                kind: phonemes.rhyme_kind(&potential_rhyme.phonemes),
+                frequency: None,
+                pos: potential_rhyme.parts_of_speech().to_vec(),
             });
         }
-        result.words.sort();
-        return result;
+        group.words.sort();
+        return SimilarResult {
+            groups: vec![group],
+        };
+    }
+
+    fn near_rhymes(&self, phonemes: &Phonemes, query: Option<&str>, limit: usize) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        let rhyme_symbol = phonemes.rhyme_symbol();
+        let last_syllable = phonemes.last_n_syllables(1);
+        // Only the words with a rhyme vowel close to the query's can score high enough.
+        let candidates = self
+            .rhyme_index
+            .iter()
+            .filter(|(symbol, _)| rhyme_symbol.is_some_and(|r| near_rhyme_symbols(r, **symbol)))
+            .flat_map(|(_, keys)| keys);
+        for (word, variant) in candidates {
+            if query == Some(word.as_str()) {
+                continue; // Ignore self-syns.
+            }
+            // Not-None because rhyme_index is 1:1 with the main map.
+            let entry = self.lookup_variant(word, *variant).unwrap();
+            // Words sharing the last syllable are what similar() finds.
+            if entry.phonemes.last_n_syllables(1) == last_syllable {
+                continue;
+            }
+            let score = phonemes.similarity_score(&entry.phonemes);
+            if score < NEAR_RHYME_MIN_SCORE {
+                continue;
+            }
+            group.words.push(SimilarWord {
+                word: entry.word.clone(),
+                syllables: entry.num_syllables(),
+                score,
+                phonemes: entry.phonemes.clone(),
+                kind: RhymeKind::Near,
+                frequency: None,
+                pos: entry.parts_of_speech().to_vec(),
+            });
+        }
+        group.words.sort();
+        group.words.truncate(limit);
+        return SimilarResult {
+            groups: vec![group],
+        };
+    }
+
+    fn search(&self, pattern: &Pattern) -> Vec<&Entry> {
+        // Like similar_to_phonemes(), only the keys starting with the pattern's fixed ending
+        // need to be checked. Without one, that's all of them.
+        let key_prefix: Vec<Phoneme> = pattern.literal_suffix().into_iter().rev().collect();
+        let start = self
+            .reverse_list
+            .partition_point(|(key, _)| key.as_slice() < key_prefix.as_slice());
+        let mut found: Vec<&Entry> = vec![];
+        for (key, (word, variant)) in &self.reverse_list[start..] {
+            if !key.starts_with(&key_prefix) {
+                break;
+            }
+            let entry = self.lookup_variant(word, *variant).unwrap();
+            if pattern.matches(&entry.phonemes) {
+                found.push(entry);
+            }
+        }
+        found.sort_by(|a, b| (&a.word, a.variant).cmp(&(&b.word, b.variant)));
+        return found;
+    }
+
+    fn by_stress(&self, query: &StressQuery) -> SimilarResult {
+        let mut group = SimilarGroup::new(query.rhyme.as_ref().unwrap_or(&Phonemes::new()), None);
+        // There are far fewer patterns than entries, so checking each one is cheap.
+        for (pattern, keys) in &self.stress_index {
+            if !query.matches_stress(pattern) {
+                continue;
+            }
+            for (word, variant) in keys {
+                let entry = self.lookup_variant(word, *variant).unwrap();
+                if let Some(similar) = query.check(entry) {
+                    group.words.push(similar);
+                }
+            }
+        }
+        group.words.sort();
+        return SimilarResult {
+            groups: vec![group],
+        };
+    }
+
+    fn alliterations(
+        &self,
+        phonemes: &Phonemes,
+        query: Option<&str>,
+        span: Alliteration,
+        limit: usize,
+    ) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        // Like similar_to_phonemes(), but from the front: the words starting with the prefix
+        // are all together in forward_list.
+        let key_prefix = phonemes.leading_symbols(span);
+        let query_key = phonemes.alliteration_key();
+        let start = self
+            .forward_list
+            .partition_point(|(key, _)| key.as_slice() < key_prefix.as_slice());
+        for (key, (word, variant)) in &self.forward_list[start..] {
+            if !key.starts_with(&key_prefix) {
+                break;
+            }
+            if query == Some(word.as_str()) {
+                continue; // Ignore self-syns.
+            }
+            let entry = self.lookup_variant(word, *variant).unwrap();
+            let shared = key
+                .iter()
+                .zip(&query_key)
+                .take_while(|(a, b)| a == b)
+                .count();
+            group.words.push(SimilarWord {
+                word: word.clone(),
+                syllables: entry.num_syllables(),
+                score: shared as i32,
+                phonemes: entry.phonemes.clone(),
+                kind: RhymeKind::Unrhymed,
+                frequency: None,
+                pos: entry.parts_of_speech().to_vec(),
+            });
+        }
+        group.words.sort();
+        group.words.truncate(limit);
+        return SimilarResult {
+            groups: vec![group],
+        };
+    }
+
+    fn homophones(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        let keys = self
+            .ending_with(&phonemes.phonemes)
+            .filter(|(word, variant)| {
+                self.lookup_variant(word, *variant).unwrap().phonemes == *phonemes
+            });
+        group.words = self.results_for(keys, query, RhymeKind::Identical, 100);
+        return SimilarResult {
+            groups: vec![group],
+        };
+    }
+
+    fn minimal_pairs(&self, phonemes: &Phonemes, query: Option<&str>) -> SimilarResult {
+        let mut group = SimilarGroup::new(phonemes, None);
+        let ph = &phonemes.phonemes;
+        let mut keys: HashSet<&(String, i32)> = HashSet::new();
+        // A word one phoneme away at position i shares everything before i and after it, so
+        // only the words in the smaller of those two ranges need to be checked. (For words of
+        // one phoneme, that's every word.)
+        for i in 0..=ph.len() {
+            let prefix = &ph[..i];
+            let suffix = &ph[(i + 1).min(ph.len())..];
+            let candidates: Box<dyn Iterator<Item = &(String, i32)>> =
+                if prefix.len() >= suffix.len() {
+                    Box::new(self.starting_with(prefix))
+                } else {
+                    Box::new(self.ending_with(suffix))
+                };
+            for key in candidates {
+                let entry = self.lookup_variant(&key.0, key.1).unwrap();
+                if one_edit_apart(ph, &entry.phonemes.phonemes) {
+                    keys.insert(key);
+                }
+            }
+        }
+        group.words = self.results_for(keys.into_iter(), query, RhymeKind::Unrhymed, 0);
+        return SimilarResult {
+            groups: vec![group],
+        };
     }
 }
 
@@ -710,9 +2839,46 @@ mod tests {
         assert_eq!(Entry::new("gdp G IY1 D IY1 P IY1").num_syllables(), 3);
     }
 
+    #[test]
+    fn test_parser_rejects_malformed_lines() {
+        assert_eq!(Entry::parse("").unwrap_err(), "empty line");
+        assert_eq!(
+            Entry::parse("  # just a comment").unwrap_err(),
+            "empty line"
+        );
+        assert_eq!(
+            Entry::parse("lonely").unwrap_err(),
+            "no phonemes for \"lonely\""
+        );
+        assert_eq!(
+            Entry::parse("odd(2 AA1 D").unwrap_err(),
+            "invalid term \"odd(2\""
+        );
+        assert_eq!(
+            Entry::parse("odd(0) AA1 D").unwrap_err(),
+            "invalid variant number in \"odd(0)\""
+        );
+        assert_eq!(
+            Entry::parse("odd(99999999999) AA1 D").unwrap_err(),
+            "invalid variant number in \"odd(99999999999)\""
+        );
+        assert_eq!(
+            Entry::parse("odd aa1 D").unwrap_err(),
+            "unknown phoneme \"aa1\""
+        );
+    }
+
+    #[test]
+    fn test_entry_from_parts_with_invalid_phonemes() {
+        assert_eq!(
+            Entry::from_parts("odd", "AA1 d?").unwrap_err(),
+            "unknown phoneme \"d?\""
+        );
+    }
+
     #[test]
     fn test_entry_from_parts() {
-        let entry = Entry::from_parts("aardvark", " AA1 R D V AA2 R K  ");
+        let entry = Entry::from_parts("aardvark", " AA1 R D V AA2 R K  ").unwrap();
         assert_eq!(&entry.word, "aardvark");
         assert_eq!(entry.variant, 1);
         assert_eq!(entry.num_syllables(), 2);
@@ -731,10 +2897,79 @@ mod tests {
         assert_eq!(dict.len(), 3);
     }
 
+    #[test]
+    fn test_dictionary_insert_keeps_reverse_list_sorted() {
+        let mut dict = DictionaryImpl::new();
+        for line in &[
+            "tree T R IY1",
+            "a AH0",
+            "free F R IY1",
+            "bee B IY1",
+            "sea S IY1",
+        ] {
+            dict.insert(Entry::new(line));
+        }
+        let mut sorted = dict.reverse_list.clone();
+        sorted.sort();
+        assert_eq!(dict.reverse_list, sorted);
+        assert_eq!(dict.reverse_list.len(), 5);
+        let mut sorted = dict.forward_list.clone();
+        sorted.sort();
+        assert_eq!(dict.forward_list, sorted);
+    }
+
+    #[test]
+    fn test_dictionary_remove() {
+        let values = vec!["our AW1 ER0", "our(2) AW1 R", "far F AA1 R"];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        assert_eq!(dict.remove("our", 2).unwrap().dict_key(), "our(2)");
+        assert!(dict.remove("our", 2).is_none());
+        assert!(dict.lookup_variant("our", 1).is_some());
+        assert_eq!(dict.reverse_list.len(), 2);
+
+        assert!(dict.remove("our", 1).is_some());
+        assert!(dict.lookup("our").is_none());
+        assert_eq!(dict.len(), 1);
+        assert_eq!(dict.reverse_list.len(), 1);
+    }
+
+    /// Returns a path in the temp directory that is unique to this process and `name`.
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("poet-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_dictionary_write_cmudict_file() {
+        let path = temp_path("write.dict");
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&vec!["our AW1 ER0", "far F AA1 R", "our(2) AW1 R"]);
+        dict.write_cmudict_file(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "far F AA1 R\nour AW1 ER0\nour(2) AW1 R\n"
+        );
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+
+        let reread = DictionaryImpl::new_from_cmudict_file(&path, LoadMode::Strict).unwrap();
+        assert_eq!(
+            reread
+                .lookup_variant("our", 2)
+                .unwrap()
+                .phonemes
+                .to_string(),
+            "AW1 R"
+        );
+        assert_eq!(reread.reverse_list, dict.reverse_list);
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn test_dictionary_insert_raw() {
         let mut dict = DictionaryImpl::new();
-        dict.insert_raw("a H0");
+        dict.insert_raw("a AH0");
         dict.insert_raw("a.m. EY2 EH1 M");
         assert_eq!(dict.len(), 2);
     }
@@ -810,7 +3045,7 @@ mod tests {
             let shelf = Shelf::new();
             assert!(shelf.over_all().lookup("foo").is_none());
             assert!(shelf.over_all().lookup_variant("foo", 1).is_none());
-            assert!(shelf.over_all().similar("foo").words.is_empty());
+            assert!(shelf.over_all().similar("foo").is_empty());
         }
 
         fn push_dictionary_with_entries(shelf: &mut Shelf, entries: Vec<&str>) {
@@ -821,7 +3056,7 @@ mod tests {
 
         #[test]
         fn lookup_over_multiple_dictionaries() {
-            // Current known / expected behavior: Should return all matches in earliest dictionary.
+            // Words in both dictionaries are merged with the default policy (see merge_policies).
             let mut shelf = Shelf::new();
             push_dictionary_with_entries(
                 &mut shelf,
@@ -847,11 +3082,13 @@ mod tests {
             }
             {
                 let in_both = shelf.over_all().lookup("avocado").unwrap();
-                assert_eq!(in_both.len(), 2);
-                // If the word came from dict1, it would only have one syllable.
+                assert_eq!(in_both.len(), 3);
+                // The entry from the second dictionary has only one syllable.
                 assert_eq!(in_both[0].num_syllables(), 4);
                 assert_eq!(in_both[1].num_syllables(), 4);
                 assert_eq!(in_both[1].variant, 2);
+                assert_eq!(in_both[2].num_syllables(), 1);
+                assert_eq!(in_both[2].variant, 3);
             }
             {
                 let only_in_second = shelf.over_all().lookup("mango").unwrap();
@@ -869,6 +3106,122 @@ mod tests {
             }
         }
 
+        /// Returns a shelf with "cmudict" and "userdict" dictionaries that both have "tomato".
+        fn shelf_with_tomatoes(policy: MergePolicy) -> Shelf {
+            let mut shelf = Shelf::new();
+            shelf.set_merge_policy(policy);
+            let mut base = DictionaryImpl::with_name("cmudict");
+            base.insert_all(&vec![
+                "tomato T AH0 M EY1 T OW2",
+                "tomato(2) T AH0 M AA1 T OW2",
+                "potato P AH0 T EY1 T OW2",
+            ]);
+            shelf.push_dictionary(Box::new(base));
+            let mut user = DictionaryImpl::with_name("userdict");
+            user.insert_all(&vec![
+                "tomato T AH0 M AA1 T OW2",
+                "tomato(2) T OW1 M AA0 T OW0",
+            ]);
+            shelf.push_dictionary(Box::new(user));
+            shelf
+        }
+
+        /// Returns (dict_key, phonemes, source) for each entry of "tomato".
+        fn tomatoes(shelf: &Shelf) -> Vec<(String, String, String)> {
+            let entries = shelf.over_all().lookup("tomato").unwrap();
+            entries
+                .iter()
+                .map(|e| {
+                    let source = e.source.as_deref().unwrap_or("").to_string();
+                    (e.dict_key(), e.phonemes.to_string(), source)
+                })
+                .collect()
+        }
+
+        fn tomato(key: &str, phonemes: &str, source: &str) -> (String, String, String) {
+            (key.to_string(), phonemes.to_string(), source.to_string())
+        }
+
+        #[test]
+        fn merge_policies() {
+            let shelf = shelf_with_tomatoes(MergePolicy::Override);
+            assert_eq!(
+                tomatoes(&shelf),
+                vec![
+                    tomato("tomato", "T AH0 M AA1 T OW2", "userdict"),
+                    tomato("tomato(2)", "T OW1 M AA0 T OW0", "userdict"),
+                ]
+            );
+            assert_eq!(
+                shelf
+                    .over_all()
+                    .lookup_variant("tomato", 2)
+                    .unwrap()
+                    .source
+                    .as_deref(),
+                Some("userdict")
+            );
+            // Overridden pronunciations don't turn up as rhymes.
+            let potato = shelf.over_all().lookup("potato").unwrap()[0]
+                .phonemes
+                .clone();
+            let similar = shelf
+                .over_all()
+                .similar_to_phonemes(&potato, Some("potato"));
+            let rhymes: Vec<String> = similar.groups[0]
+                .words
+                .iter()
+                .map(|w| w.phonemes.to_string())
+                .collect();
+            assert_eq!(rhymes, vec!["T AH0 M AA1 T OW2"]);
+
+            let shelf = shelf_with_tomatoes(MergePolicy::AppendVariants);
+            assert_eq!(
+                tomatoes(&shelf),
+                vec![
+                    tomato("tomato", "T AH0 M EY1 T OW2", "cmudict"),
+                    tomato("tomato(2)", "T AH0 M AA1 T OW2", "cmudict"),
+                    tomato("tomato(3)", "T AH0 M AA1 T OW2", "userdict"),
+                    tomato("tomato(4)", "T OW1 M AA0 T OW0", "userdict"),
+                ]
+            );
+
+            let mut shelf = shelf_with_tomatoes(MergePolicy::AppendNew);
+            let expected = vec![
+                tomato("tomato", "T AH0 M EY1 T OW2", "cmudict"),
+                tomato("tomato(2)", "T AH0 M AA1 T OW2", "cmudict"),
+                tomato("tomato(3)", "T OW1 M AA0 T OW0", "userdict"),
+            ];
+            assert_eq!(tomatoes(&shelf), expected);
+            assert_eq!(
+                shelf
+                    .over_all()
+                    .lookup_variant("tomato", 3)
+                    .unwrap()
+                    .phonemes
+                    .to_string(),
+                "T OW1 M AA0 T OW0"
+            );
+            assert_eq!(
+                shelf.over_all().lookup("potato").unwrap()[0]
+                    .source
+                    .as_deref(),
+                Some("cmudict")
+            );
+
+            // Changing the policy re-merges.
+            shelf.set_merge_policy(MergePolicy::Override);
+            assert_eq!(tomatoes(&shelf).len(), 2);
+        }
+
+        #[test]
+        fn merge_policy_from_str() {
+            assert_eq!("override".parse(), Ok(MergePolicy::Override));
+            assert_eq!("append".parse(), Ok(MergePolicy::AppendVariants));
+            assert_eq!("append-new".parse(), Ok(MergePolicy::AppendNew));
+            assert!("other".parse::<MergePolicy>().is_err());
+        }
+
         #[test]
         fn lookup_variant_over_multiple_dictionaries() {
             // Current known / expected behavior: Should return match in earliest dictionary.
@@ -958,14 +3311,14 @@ mod tests {
             let dict = shelf.over_all();
             {
                 // "far" should be similar to the our(3) variant.
-                let words = dict.similar("far").words;
+                let words = &dict.similar("far").groups[0].words;
                 assert_eq!(words.len(), 1);
                 assert_eq!(words[0].word, "our");
                 assert_eq!(words[0].phonemes.phonemes, vec!["AA1", "R"]);
             }
             {
                 // "red" should have 3 rhyming words from the two dictionaries.
-                let words = dict.similar("red").words;
+                let words = &dict.similar("red").groups[0].words;
                 assert_eq!(words.len(), 3);
                 assert_eq!(words[0].word, "read");
                 assert_eq!(words[1].word, "reade");
@@ -973,62 +3326,244 @@ mod tests {
                 assert_eq!(words[2].phonemes.phonemes, vec!["R", "EH1", "D"]);
             }
             {
-                // "our" should have rhyming words from all variants.
+                // "our" should have rhyming words from all variants, grouped by variant.
                 // "sour" appears twice because both variants rhyme.
-                let words = dict.similar("our").words;
-                assert_eq!(words.len(), 3);
-                assert_eq!(words[0].word, "far");
-                assert_eq!(words[1].word, "sour");
-                assert_eq!(words[1].phonemes.phonemes, vec!["S", "AW1", "ER0"]);
-                assert_eq!(words[2].word, "sour");
-                assert_eq!(words[2].phonemes.phonemes, vec!["S", "AW1", "R"]);
+                let groups = dict.similar("our").groups;
+                assert_eq!(groups.len(), 3);
+                assert_eq!(groups[0].variant, Some(1));
+                assert_eq!(groups[0].words.len(), 1);
+                assert_eq!(groups[0].words[0].word, "sour");
+                assert_eq!(
+                    groups[0].words[0].phonemes.phonemes,
+                    vec!["S", "AW1", "ER0"]
+                );
+                assert_eq!(groups[1].variant, Some(2));
+                assert_eq!(groups[1].words.len(), 1);
+                assert_eq!(groups[1].words[0].word, "sour");
+                assert_eq!(groups[1].words[0].phonemes.phonemes, vec!["S", "AW1", "R"]);
+                assert_eq!(groups[2].variant, Some(3));
+                assert_eq!(groups[2].words.len(), 1);
+                assert_eq!(groups[2].words[0].word, "far");
             }
         }
     }
 
-    #[test]
-    fn test_similarity_score() {
-        let bayous = Entry::new("bayous B AY1 UW0 Z");
-        let fondues = Entry::new("fondues F AA1 N D UW0 Z");
-        let virtues = Entry::new("virtues V ER1 CH UW0 Z");
-        assert_eq!(bayous.phonemes.similarity_score(&fondues.phonemes), 2);
-        assert_eq!(fondues.phonemes.similarity_score(&virtues.phonemes), 2);
-
-        let diagram = Entry::new("diagram D AY1 AH0 G R AE2 M");
-        let polygram = Entry::new("polygram P AA1 L IY2 G R AE2 M");
-        let program = Entry::new("program P R OW1 G R AE2 M");
-        let programme = Entry::new("programme P R OW1 G R AE2 M");
-        assert_eq!(diagram.phonemes.similarity_score(&polygram.phonemes), 4);
-        assert_eq!(diagram.phonemes.similarity_score(&program.phonemes), 4);
-        assert_eq!(program.phonemes.similarity_score(&programme.phonemes), 7);
-
-        let apple = Entry::new("apple AE1 P AH0 L");
-        let apple_s = Entry::new("apple's AE1 P AH0 L Z");
-        let apples = Entry::new("apples AE1 P AH0 L Z");
-        assert_eq!(apple.phonemes.similarity_score(&apple_s.phonemes), 0);
-        assert_eq!(apple.phonemes.similarity_score(&apples.phonemes), 0);
-        assert_eq!(apple_s.phonemes.similarity_score(&apples.phonemes), 5);
-
-        let mango = Entry::new("mango M AE1 NG G OW0");
-        let mangoes = Entry::new("mangoes M AE1 NG G OW0 Z");
-        let mangold = Entry::new("mangold M AE1 N G OW2 L D");
-        assert_eq!(mango.phonemes.similarity_score(&mangoes.phonemes), 0);
-        assert_eq!(mango.phonemes.similarity_score(&mangold.phonemes), 0);
-        assert_eq!(mangoes.phonemes.similarity_score(&mangold.phonemes), 0);
-    }
+    mod userdict {
+        use super::*;
 
-    // This helper calls `dict.similar(query)` and checks that the returned words are `expected`.
-    fn assert_similar_terms_are(dict: &DictionaryImpl, query: &str, expected: &Vec<&str>) {
-        let result = dict.similar(query);
-        let result_words: Vec<String> = result
-            .words
-            .iter()
-            .map(|similar_entry| similar_entry.word.clone())
-            .collect();
-        assert_eq!(&result_words, expected);
-    }
+        /// Returns a shelf with a small base dictionary and an empty user dictionary at `path`.
+        fn shelf_with_userdict(path: &str) -> Shelf {
+            let mut base = DictionaryImpl::new();
+            base.insert_all(&vec!["red R EH1 D", "bed B EH1 D"]);
+            let mut shelf = Shelf::new();
+            shelf.push_dictionary(Box::new(base));
+            shelf.init_userdict(path).unwrap();
+            shelf
+        }
 
-    #[test]
+        #[test]
+        fn edits_without_a_userdict_fail() {
+            let mut shelf = Shelf::new();
+            assert!(matches!(
+                shelf.add_user_entry("zed Z EH1 D"),
+                Err(UserDictError::NotLoaded)
+            ));
+        }
+
+        #[test]
+        fn add_takes_effect_and_persists() {
+            let path = temp_path("userdict-add.dict");
+            let mut shelf = shelf_with_userdict(&path);
+
+            let entry = shelf.add_user_entry("  Zed z eh1 d ").unwrap();
+            assert_eq!(entry.dict_key(), "zed");
+            assert_eq!(entry.phonemes.to_string(), "Z EH1 D");
+            assert!(shelf.over_all().lookup("zed").is_some());
+            let similar = shelf.over_all().similar("red");
+            assert!(similar.groups[0].words.iter().any(|w| w.word == "zed"));
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "zed Z EH1 D\n");
+
+            let mut reloaded = Shelf::new();
+            reloaded.init_userdict(&path).unwrap();
+            assert!(reloaded.over_all().lookup("zed").is_some());
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn added_words_are_near_rhymes() {
+            let path = temp_path("userdict-near.dict");
+            let mut shelf = shelf_with_userdict(&path);
+            let shape = Entry::new("shape SH EY1 P").phonemes;
+            let near_rhymes = |shelf: &Shelf| -> Vec<String> {
+                first_group_words(&shelf.near_rhymes(&shape, Some("shape"), 10))
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            };
+            assert!(!near_rhymes(&shelf).contains(&"break".to_string()));
+
+            shelf.add_user_entry("break B R EY1 K").unwrap();
+            assert!(near_rhymes(&shelf).contains(&"break".to_string()));
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn add_rejects_invalid_and_duplicate_entries() {
+            let path = temp_path("userdict-reject.dict");
+            let mut shelf = shelf_with_userdict(&path);
+            for line in &[
+                "",
+                "zed",
+                "zed Z EH1 D # comment",
+                "ze(d Z EH1 D",
+                "zed Z-EH1 D",
+            ] {
+                assert!(
+                    matches!(
+                        shelf.add_user_entry(line),
+                        Err(UserDictError::InvalidEntry(_))
+                    ),
+                    "{}",
+                    line
+                );
+            }
+            shelf.add_user_entry("zed Z EH1 D").unwrap();
+            assert!(matches!(
+                shelf.add_user_entry("zed Z IY1"),
+                Err(UserDictError::AlreadyExists(_))
+            ));
+            shelf.add_user_entry("zed(2) Z IY1").unwrap();
+            assert_eq!(shelf.over_all().lookup("zed").unwrap().len(), 2);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn edits_to_words_in_other_dictionaries_are_merged() {
+            let path = temp_path("userdict-merge.dict");
+            let mut shelf = shelf_with_userdict(&path);
+            let entry = shelf.add_user_entry("red R AY1 D").unwrap();
+            assert_eq!(entry.dict_key(), "red");
+            assert_eq!(entry.source.as_deref(), Some(path.as_str()));
+
+            let entries = shelf.over_all().lookup("red").unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[1].dict_key(), "red(2)");
+            assert_eq!(entries[1].phonemes.to_string(), "R AY1 D");
+            assert_eq!(entries[1].source.as_deref(), Some(path.as_str()));
+
+            shelf.remove_user_entry("red").unwrap();
+            assert_eq!(shelf.over_all().lookup("red").unwrap().len(), 1);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn update_and_remove() {
+            let path = temp_path("userdict-update.dict");
+            let mut shelf = shelf_with_userdict(&path);
+            assert!(matches!(
+                shelf.update_user_entry("zed Z IY1"),
+                Err(UserDictError::NotFound(_))
+            ));
+            shelf.add_user_entry("zed Z EH1 D").unwrap();
+            shelf.add_user_entry("zed(2) Z EH1 D").unwrap();
+
+            shelf.update_user_entry("zed(2) Z IY1").unwrap();
+            let entry = shelf.over_all().lookup_variant("zed", 2).unwrap();
+            assert_eq!(entry.phonemes.to_string(), "Z IY1");
+            let similar = shelf.over_all().similar("red");
+            assert_eq!(similar.groups[0].words.len(), 2); // bed, zed
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                "zed Z EH1 D\nzed(2) Z IY1\n"
+            );
+
+            assert_eq!(shelf.remove_user_entry("zed").unwrap().dict_key(), "zed");
+            assert!(shelf.over_all().lookup_variant("zed", 1).is_none());
+            assert!(matches!(
+                shelf.remove_user_entry("zed"),
+                Err(UserDictError::NotFound(_))
+            ));
+            assert!(matches!(
+                shelf.remove_user_entry("red"),
+                Err(UserDictError::NotFound(_))
+            ));
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "zed(2) Z IY1\n");
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn failed_save_rolls_back() {
+            let dir = temp_path("userdict-missing-dir");
+            let path = format!("{}/userdict.dict", dir);
+            let mut shelf = shelf_with_userdict(&path);
+            assert!(matches!(
+                shelf.add_user_entry("zed Z EH1 D"),
+                Err(UserDictError::Io(_))
+            ));
+            assert!(shelf.over_all().lookup("zed").is_none());
+        }
+    }
+
+    #[test]
+    fn test_similarity_score() {
+        let score = |a: &str, b: &str| {
+            let a = Entry::new(a).phonemes;
+            let b = Entry::new(b).phonemes;
+            assert_eq!(a.similarity_score(&b), b.similarity_score(&a));
+            a.similarity_score(&b)
+        };
+        let bayous = "bayous B AY1 UW0 Z";
+        let fondues = "fondues F AA1 N D UW0 Z";
+        let virtues = "virtues V ER1 CH UW0 Z";
+        assert_eq!(score(bayous, fondues), 61);
+        assert_eq!(score(fondues, virtues), 65);
+
+        // Perfect rhymes score 100, plus one per matching phoneme before the stressed vowel.
+        let diagram = "diagram D AY1 AH0 G R AE2 M";
+        let polygram = "polygram P AA1 L IY2 G R AE2 M";
+        let program = "program P R OW1 G R AE2 M";
+        let programme = "programme P R OW1 G R AE2 M";
+        assert_eq!(score(diagram, polygram), 102);
+        assert_eq!(score(diagram, program), 102);
+        assert_eq!(score(program, programme), 105);
+
+        let apple = "apple AE1 P AH0 L";
+        let apple_s = "apple's AE1 P AH0 L Z";
+        let apples = "apples AE1 P AH0 L Z";
+        assert_eq!(score(apple, apple_s), 89);
+        assert_eq!(score(apple, apples), 89);
+        assert_eq!(score(apple_s, apples), 100);
+
+        let mango = "mango M AE1 NG G OW0";
+        let mangoes = "mangoes M AE1 NG G OW0 Z";
+        let mangold = "mangold M AE1 N G OW2 L D";
+        assert_eq!(score(mango, mangoes), 90);
+        assert_eq!(score(mango, mangold), 39);
+        assert_eq!(score(mangoes, mangold), 44);
+
+        // Closer sounds score higher, even past the first difference.
+        let cat = "cat K AE1 T";
+        assert_eq!(score(cat, "cap K AE1 P"), 92);
+        assert_eq!(score(cat, "cot K AA1 T"), 63);
+        assert_eq!(score(cat, "dog D AO1 G"), 34);
+        assert_eq!(score("shape SH EY1 P", "break B R EY1 K"), 91);
+    }
+
+    // This helper calls `dict.similar(query)` and checks that the returned words are `expected`.
+    //
+    // The words from all of the groups are concatenated, in group order.
+    fn assert_similar_terms_are(dict: &DictionaryImpl, query: &str, expected: &Vec<&str>) {
+        let result = dict.similar(query);
+        let result_words: Vec<String> = result
+            .groups
+            .iter()
+            .flat_map(|group| group.words.iter())
+            .map(|similar_entry| similar_entry.word.clone())
+            .collect();
+        assert_eq!(&result_words, expected);
+    }
+
+    #[test]
     fn test_similar() {
         let values = vec![
             // These words rhyme.
@@ -1058,13 +3593,13 @@ mod tests {
         let mut dict = DictionaryImpl::new();
         dict.insert_all(&values);
 
-        assert_similar_terms_are(&dict, "bayous", &vec!["fondues", "virtues"]);
+        assert_similar_terms_are(&dict, "bayous", &vec!["virtues", "fondues"]);
         assert_similar_terms_are(
             &dict,
             "program",
             &vec!["programme", "diagram", "polygram", "telegram"],
         );
-        assert!(dict.similar("guava").words.is_empty());
+        assert!(dict.similar("guava").is_empty());
         assert_similar_terms_are(&dict, "apples", &vec!["apple's"]);
     }
 
@@ -1100,9 +3635,78 @@ mod tests {
         assert_similar_terms_are(&dict, "far", &vec!["our" /* (3) */]);
 
         // Case: A word with many variants should return words that are similar to any variant.
-        // "sour" appears twice because both variants rhyme. (At the moment they aren't
-        // distinguishable because the phonemes aren't passed back.)
-        assert_similar_terms_are(&dict, "our", &vec!["far", "sour", "sour"]);
+        // "sour" appears twice because both variants rhyme, once for each of our and our(2).
+        assert_similar_terms_are(&dict, "our", &vec!["sour", "sour", "far"]);
+    }
+
+    #[test]
+    fn test_similar_groups_by_variant() {
+        let values = vec![
+            "wind W IH1 N D",
+            "wind(2) W AY1 N D",
+            "binned B IH1 N D",
+            "grinned G R IH1 N D",
+            "kind K AY1 N D",
+            "mind M AY1 N D",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+
+        let result = dict.similar("wind");
+        assert_eq!(result.groups.len(), 2);
+        assert_eq!(result.groups[0].variant, Some(1));
+        assert_eq!(
+            result.groups[0].phonemes.phonemes,
+            vec!["W", "IH1", "N", "D"]
+        );
+        let words: Vec<&str> = result.groups[0]
+            .words
+            .iter()
+            .map(|w| w.word.as_str())
+            .collect();
+        assert_eq!(words, vec!["binned", "grinned"]);
+        assert_eq!(result.groups[1].variant, Some(2));
+        let words: Vec<&str> = result.groups[1]
+            .words
+            .iter()
+            .map(|w| w.word.as_str())
+            .collect();
+        assert_eq!(words, vec!["kind", "mind"]);
+        assert_eq!(result.num_words(), 4);
+    }
+
+    #[test]
+    fn test_similar_removes_duplicates_across_variants() {
+        // Both pronunciations of "either" end with the same syllable, so every rhyme would be
+        // found twice. Each should only appear once, with the variant where it scores highest.
+        let values = vec![
+            "either IY1 DH ER0",
+            "either(2) AY1 DH ER0",
+            "neither N IY1 DH ER0",
+            "neither(2) N AY1 DH ER0",
+            "breather B R IY1 DH ER0",
+            "tether T EH1 DH ER0",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+
+        let result = dict.similar("either");
+        assert_eq!(result.groups.len(), 2);
+        let first: Vec<(&str, i32)> = result.groups[0]
+            .words
+            .iter()
+            .map(|w| (w.word.as_str(), w.score))
+            .collect();
+        assert_eq!(
+            first,
+            vec![("breather", 100), ("neither", 100), ("tether", 87)]
+        );
+        let second: Vec<(&str, i32)> = result.groups[1]
+            .words
+            .iter()
+            .map(|w| (w.word.as_str(), w.score))
+            .collect();
+        assert_eq!(second, vec![("neither", 100)]);
     }
 
     #[test]
@@ -1115,7 +3719,7 @@ mod tests {
         let mut dict = DictionaryImpl::new();
         dict.insert_all(&values);
 
-        let words = dict.similar("programme").words;
+        let words = &dict.similar("programme").groups[0].words;
         assert_eq!(words.len(), 2);
         assert_eq!(words[0].word, "program");
         assert_eq!(
@@ -1129,10 +3733,784 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stress_pattern() {
+        assert_eq!(Entry::new("a AH0").phonemes.stress_pattern(), "0");
+        assert_eq!(
+            Entry::new("applesauce AE1 P AH0 L S AO2 S")
+                .phonemes
+                .stress_pattern(),
+            "102"
+        );
+        assert_eq!(Entry::new("hmm HH M").phonemes.stress_pattern(), "");
+    }
+
+    #[test]
+    fn test_rhyme_kind() {
+        let program = Entry::new("program P R OW1 G R AE2 M").phonemes;
+        let programme = Entry::new("programme P R OW1 G R AE2 M").phonemes;
+        let cram = Entry::new("cram K R AE1 M").phonemes;
+        let hologram = Entry::new("hologram HH AA1 L AH0 G R AE0 M").phonemes;
+        assert_eq!(program.rhyme_kind(&programme), RhymeKind::Identical);
+        // Stress levels are ignored, so secondary stress still makes a perfect rhyme.
+        assert_eq!(program.rhyme_kind(&cram), RhymeKind::Perfect);
+        assert_eq!(program.rhyme_kind(&hologram), RhymeKind::Partial);
+    }
+
+    #[test]
+    fn test_near_rhymes() {
+        let values = vec![
+            "shape SH EY1 P",
+            "cape K EY1 P",
+            "break B R EY1 K",
+            "grapes G R EY1 P S",
+            "great G R EY1 T",
+            "sleep S L IY1 P",
+            "dog D AO1 G",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let shape = dict.lookup("shape").unwrap()[0].phonemes.clone();
+
+        // "cape" shares the last syllable, so it's only in similar().
+        let result = dict.near_rhymes(&shape, Some("shape"), 10);
+        let words: Vec<(&str, i32)> = result.groups[0]
+            .words
+            .iter()
+            .map(|w| (w.word.as_str(), w.score))
+            .collect();
+        assert_eq!(words, vec![("break", 91), ("great", 91), ("grapes", 80)]);
+        assert!(result.groups[0]
+            .words
+            .iter()
+            .all(|w| w.kind == RhymeKind::Near));
+
+        let result = dict.near_rhymes(&shape, Some("shape"), 1);
+        assert_eq!(first_group_words(&result), vec!["break"]);
+
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let result = shelf.near_rhymes(&shape, Some("shape"), 2);
+        assert_eq!(first_group_words(&result), vec!["break", "great"]);
+
+        // The user's "break" replaces the base one and isn't a near rhyme, but there are still
+        // two others.
+        let mut user = DictionaryImpl::new();
+        user.insert_all(&vec!["break B R EY1 K S"]);
+        shelf.set_merge_policy(MergePolicy::Override);
+        shelf.push_dictionary(Box::new(user));
+        let result = shelf.near_rhymes(&shape, Some("shape"), 2);
+        assert_eq!(first_group_words(&result), vec!["great", "grapes"]);
+
+        // Removing a word takes it out of the rhyme index too.
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        dict.remove("great", 1);
+        let result = dict.near_rhymes(&shape, Some("shape"), 10);
+        assert_eq!(first_group_words(&result), vec!["break", "grapes"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let values = vec![
+            "acrobat AE1 K R AH0 B AE2 T",
+            "button B AH1 T AH0 N",
+            "cat K AE1 T",
+            "cotton K AA1 T AH0 N",
+            "hat HH AE1 T",
+            "retain R IH0 T EY1 N",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let search = |dict: &dyn Dictionary, pattern: &str| -> Vec<String> {
+            dict.search(&pattern.parse().unwrap())
+                .iter()
+                .map(|e| e.dict_key())
+                .collect()
+        };
+        assert_eq!(search(&dict, "* AE1 T"), vec!["cat", "hat"]);
+        assert_eq!(search(&dict, "* AE T"), vec!["acrobat", "cat", "hat"]);
+        assert_eq!(search(&dict, "stress:10 * AH0 N"), vec!["button", "cotton"]);
+        assert_eq!(search(&dict, "stop * nasal"), vec!["button", "cotton"]);
+        assert!(search(&dict, "* ZH").is_empty());
+
+        let mut user = DictionaryImpl::with_name("userdict.dict");
+        user.insert_all(&vec!["cat(2) K AA1 T", "brat B R AE1 T"]);
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        shelf.push_dictionary(Box::new(user));
+        assert_eq!(search(&shelf, "* AE1 T"), vec!["brat", "cat", "hat"]);
+        assert_eq!(search(&shelf, "K ? T"), vec!["cat", "cat(2)"]);
+    }
+
+    #[test]
+    fn test_by_stress() {
+        let values = vec![
+            "banana B AH0 N AE1 N AH0",
+            "cabana K AH0 B AE1 N AH0",
+            "bandana B AE0 N D AE1 N AH0",
+            "tomato T AH0 M EY1 T OW2",
+            "potato P AH0 T EY1 T OW2",
+            "button B AH1 T AH0 N",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let words = |result: &SimilarResult| -> Vec<(String, i32)> {
+            result.groups[0]
+                .words
+                .iter()
+                .map(|w| (w.word.clone(), w.score))
+                .collect()
+        };
+
+        let query = StressQuery::new("010").unwrap();
+        let result = dict.by_stress(&query);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["banana", "bandana", "cabana"]
+        );
+        assert!(result.groups[0]
+            .words
+            .iter()
+            .all(|w| w.kind == RhymeKind::Unrhymed && w.score == 0));
+
+        let query = StressQuery::new("01?").unwrap();
+        assert_eq!(dict.by_stress(&query).num_words(), 5);
+
+        // Rhyming with "banana" and starting with "B", which ignores the stress of vowels.
+        let banana = dict.lookup("banana").unwrap()[0].phonemes.clone();
+        let query = StressQuery {
+            rhyme: Some(banana.clone()),
+            rhyme_word: Some("banana".to_string()),
+            onset: vec!["B".parse().unwrap(), "AE1".parse().unwrap()],
+            ..StressQuery::new("01?").unwrap()
+        };
+        let result = dict.by_stress(&query);
+        assert_eq!(words(&result), vec![("bandana".to_string(), 100)]);
+        assert_eq!(result.groups[0].phonemes, banana);
+        assert_eq!(result.groups[0].words[0].kind, RhymeKind::Perfect);
+
+        // The index follows edits.
+        dict.remove("cabana", 1);
+        dict.insert(Entry::new("savanna S AH0 V AE1 N AH0"));
+        let query = StressQuery::new("010").unwrap();
+        assert_eq!(
+            first_group_words(&dict.by_stress(&query)),
+            vec!["banana", "bandana", "savanna"]
+        );
+
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let query = StressQuery::new("10").unwrap();
+        assert_eq!(first_group_words(&shelf.by_stress(&query)), vec!["button"]);
+
+        let query = StressQuery::new("0??").unwrap();
+        let result = by_stress_rhyming_with(&shelf, &query, Some("potato")).unwrap();
+        assert_eq!(result.groups[0].variant, Some(1));
+        assert_eq!(first_group_words(&result), vec!["tomato"]);
+        assert_eq!(
+            by_stress_rhyming_with(&shelf, &query, Some("zzz")).unwrap_err(),
+            "zzz isn't in the dictionary"
+        );
+
+        assert_eq!(
+            StressQuery::new("da-DUM").unwrap_err(),
+            "invalid stress pattern \"da-DUM\"; use the digits 0, 1 and 2, and ? for any"
+        );
+        assert!(StressQuery::new("").is_err());
+    }
+
+    #[test]
+    fn test_one_edit_apart() {
+        let ph = |s: &str| crate::poet::phoneme::parse_sequence(s).unwrap();
+        assert!(one_edit_apart(&ph("SH IH1 P"), &ph("SH IY1 P")));
+        assert!(one_edit_apart(&ph("SH IH1 P"), &ph("SH IH1 P S")));
+        assert!(one_edit_apart(&ph("SH IY1 P"), &ph("SH IY1")));
+        assert!(one_edit_apart(&ph("AH0"), &ph("AY1")));
+        assert!(!one_edit_apart(&ph("SH IH1 P"), &ph("SH IH1 P")));
+        assert!(!one_edit_apart(&ph("SH IH1 P"), &ph("S L IH1 P")));
+        assert!(!one_edit_apart(&ph("SH IH1 P"), &ph("SH IH1 P M AH0 N T")));
+    }
+
+    #[test]
+    fn test_homophones_and_minimal_pairs() {
+        let values = vec![
+            "pair P EH1 R",
+            "pear P EH1 R",
+            "pare P EH1 R",
+            "par P AA1 R",
+            "ship SH IH1 P",
+            "sheep SH IY1 P",
+            "she SH IY1",
+            "shop SH AA1 P",
+            "ships SH IH1 P S",
+            "lip L IH1 P",
+            "slip S L IH1 P",
+            "shipment SH IH1 P M AH0 N T",
+            "a AH0",
+            "i AY1",
+            "uh AH1",
+            "up AH1 P",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let phonemes = |word: &str| dict.lookup(word).unwrap()[0].phonemes.clone();
+
+        let result = dict.homophones(&phonemes("pair"), Some("pair"));
+        assert_eq!(first_group_words(&result), vec!["pare", "pear"]);
+        assert!(result.groups[0]
+            .words
+            .iter()
+            .all(|w| w.kind == RhymeKind::Identical && w.score == 100));
+        assert!(dict.homophones(&phonemes("par"), Some("par")).is_empty());
+
+        let result = dict.minimal_pairs(&phonemes("ship"), Some("ship"));
+        assert_eq!(
+            first_group_words(&result),
+            vec!["lip", "sheep", "ships", "shop"]
+        );
+        assert_eq!(result.groups[0].words[0].kind, RhymeKind::Unrhymed);
+        assert_eq!(
+            first_group_words(&dict.minimal_pairs(&phonemes("sheep"), Some("sheep"))),
+            vec!["she", "ship", "shop"]
+        );
+        assert_eq!(
+            first_group_words(&dict.minimal_pairs(&phonemes("a"), Some("a"))),
+            vec!["i", "uh"]
+        );
+
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let pair = shelf.lookup("pair").unwrap()[0].phonemes.clone();
+        assert_eq!(
+            first_group_words(&shelf.homophones(&pair, None)),
+            vec!["pair", "pare", "pear"]
+        );
+        assert_eq!(
+            first_group_words(&shelf.minimal_pairs(&pair, None)),
+            vec!["par"]
+        );
+    }
+
+    #[test]
+    fn test_mosaic_rhymes() {
+        let values = vec![
+            "poet P OW1 AH0 T",
+            "know N OW1",
+            "go G OW1",
+            "it IH1 T",
+            "at AE1 T",
+            "but B AH1 T",
+            "orange AO1 R AH0 N JH",
+            "door D AO1 R",
+            "hinge HH IH1 N JH",
+            "cat K AE1 T",
+            "hospital HH AA1 S P IH0 T AH0 L",
+            "boss B AA1 S",
+            "pit P IH1 T",
+            "all AO1 L",
+            "dull D AH1 L",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let mosaics = |word: &str, limit| {
+            let phonemes = &shelf.lookup(word).unwrap()[0].phonemes;
+            mosaic_rhymes(&shelf, phonemes, Some(word), limit)
+        };
+
+        // "it" and "at" are reduced to "AH0 T" after a stressed word.
+        let result = mosaics("poet", 5);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["go at", "go it", "know at", "know it", "go but"]
+        );
+        let know_it = &result.groups[0].words[3];
+        assert_eq!((know_it.score, know_it.kind), (100, RhymeKind::Perfect));
+        assert_eq!(know_it.phonemes.to_string(), "N OW1 AH0 T");
+        assert_eq!(know_it.syllables, 2);
+        assert_eq!(result.groups[0].words[4].kind, RhymeKind::Near);
+
+        assert_eq!(first_group_words(&mosaics("orange", 5)), vec!["door hinge"]);
+        assert_eq!(
+            first_group_words(&mosaics("hospital", 5)),
+            vec!["boss pit dull", "boss pit all"]
+        );
+        // There's nothing after the rhyming vowel of "cat" to make from other words.
+        assert!(mosaics("cat", 5).is_empty());
+    }
+
+    #[test]
+    fn test_alliterations() {
+        let values = vec![
+            "phantom F AE1 N T AH0 M",
+            "fantastic F AE0 N T AE1 S T IH0 K",
+            "photo F OW1 T OW2",
+            "flame F L EY1 M",
+            "panther P AE1 N TH ER0",
+            "apple AE1 P AH0 L",
+            "attic AE1 T IH0 K",
+            "strong S T R AO1 NG",
+            "string S T R IH1 NG",
+            "stone S T OW1 N",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+        let alliterations = |dict: &dyn Dictionary, word: &str, span, limit| {
+            let phonemes = &dict.lookup(word).unwrap()[0].phonemes;
+            dict.alliterations(phonemes, Some(word), span, limit)
+        };
+
+        // "fantastic" shares F AE N T with "phantom", so it comes first.
+        let result = alliterations(&dict, "phantom", Alliteration::Onset, 10);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["fantastic", "flame", "photo"]
+        );
+        assert_eq!(result.groups[0].words[0].score, 4);
+        assert_eq!(result.groups[0].words[0].kind, RhymeKind::Unrhymed);
+        assert_eq!(
+            first_group_words(&alliterations(
+                &dict,
+                "phantom",
+                Alliteration::Phonemes(2),
+                10
+            )),
+            vec!["fantastic"]
+        );
+        assert_eq!(
+            first_group_words(&alliterations(&dict, "strong", Alliteration::Onset, 10)),
+            vec!["string"]
+        );
+        // Words starting with a vowel alliterate with that vowel.
+        assert_eq!(
+            first_group_words(&alliterations(&dict, "apple", Alliteration::Onset, 10)),
+            vec!["attic"]
+        );
+
+        // The index follows edits.
+        dict.remove("fantastic", 1);
+        dict.insert(Entry::new("fanfare F AE1 N F EH2 R"));
+        dict.insert(Entry::new("fuse F Y UW1 Z"));
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        assert_eq!(
+            first_group_words(&alliterations(&shelf, "phantom", Alliteration::Onset, 2)),
+            vec!["fanfare", "flame"]
+        );
+
+        // The user's "fanfare" and "flame" start differently, which leaves two others.
+        let mut user = DictionaryImpl::new();
+        user.insert_all(&vec!["fanfare P AE1 N F EH2 R", "flame P L EY1 M"]);
+        shelf.set_merge_policy(MergePolicy::Override);
+        shelf.push_dictionary(Box::new(user));
+        assert_eq!(
+            first_group_words(&alliterations(&shelf, "phantom", Alliteration::Onset, 2)),
+            vec!["fuse", "photo"]
+        );
+    }
+
+    /// Returns the words in the first group of `result`.
+    fn first_group_words(result: &SimilarResult) -> Vec<&str> {
+        result.groups[0]
+            .words
+            .iter()
+            .map(|w| w.word.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_similar_filter_and_sort() {
+        let values = vec![
+            "apple AE1 P AH0 L",
+            "apel AE1 P AH0 L",
+            "capital K AE1 P IH0 T AH0 L",
+            "chapel CH AE1 P AH0 L",
+            "grapple G R AE1 P AH0 L",
+            "label L EY1 B AH0 L",
+            "multiple M AH1 L T AH0 P AH0 L",
+            "people P IY1 P AH0 L",
+        ];
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&values);
+
+        let all = dict.similar("apple");
+        assert_eq!(
+            first_group_words(&all),
+            vec!["apel", "chapel", "grapple", "label", "people", "capital", "multiple"]
+        );
+
+        let mut result = all.clone();
+        result.filter(&SimilarFilter {
+            min_syllables: Some(3),
+            max_syllables: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(first_group_words(&result), vec!["capital", "multiple"]);
+
+        let mut result = all.clone();
+        result.filter(&SimilarFilter {
+            min_score: Some(100),
+            ..Default::default()
+        });
+        assert_eq!(
+            first_group_words(&result),
+            vec!["apel", "chapel", "grapple"]
+        );
+
+        let mut result = all.clone();
+        result.filter(&SimilarFilter {
+            kind: Some(RhymeKind::Perfect),
+            ..Default::default()
+        });
+        assert_eq!(first_group_words(&result), vec!["chapel", "grapple"]);
+
+        let mut result = all.clone();
+        result.filter(&SimilarFilter {
+            kind: Some(RhymeKind::Identical),
+            ..Default::default()
+        });
+        assert_eq!(first_group_words(&result), vec!["apel"]);
+
+        let mut result = all.clone();
+        result.filter(&SimilarFilter {
+            stress: Some(String::from("100")),
+            ..Default::default()
+        });
+        assert_eq!(first_group_words(&result), vec!["capital", "multiple"]);
+
+        let mut result = all.clone();
+        result.sort_by(SimilarOrder::Word);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["apel", "capital", "chapel", "grapple", "label", "multiple", "people"]
+        );
+        result.sort_by(SimilarOrder::Syllables);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["apel", "chapel", "grapple", "label", "people", "capital", "multiple"]
+        );
+        result.sort_by(SimilarOrder::Score);
+        assert_eq!(first_group_words(&result), first_group_words(&all));
+    }
+
+    #[test]
+    fn test_similar_frequencies() {
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "apple AE1 P AH0 L",
+            "chapel CH AE1 P AH0 L",
+            "grapple G R AE1 P AH0 L",
+            "people P IY1 P AH0 L",
+            "multiple M AH1 L T AH0 P AH0 L",
+        ]);
+        let mut shelf = Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let all = shelf.similar("apple");
+        assert!(all.groups[0].words.iter().all(|w| w.frequency.is_none()));
+        assert_eq!(shelf.frequency("people"), None);
+        assert!(!shelf.has_frequencies());
+
+        // Out of a million words: people 500, multiple 100, chapel 5, and grapple not at all.
+        let text = "people 500\nmultiple 100\nchapel 5\nthe 999395\n";
+        let frequencies = Frequencies::from_reader("test", text.as_bytes(), LoadMode::Strict);
+        shelf.set_frequencies(frequencies.unwrap());
+        assert!(shelf.has_frequencies());
+        assert_eq!(shelf.frequency("people"), Some(500_000));
+        assert_eq!(shelf.frequency("grapple"), Some(0));
+        let all = shelf.similar("apple");
+        let frequencies: Vec<(&str, Option<u64>)> = all.groups[0]
+            .words
+            .iter()
+            .map(|w| (w.word.as_str(), w.frequency))
+            .collect();
+        assert_eq!(
+            frequencies,
+            vec![
+                ("chapel", Some(5_000)),
+                ("grapple", Some(0)),
+                ("people", Some(500_000)),
+                ("multiple", Some(100_000))
+            ]
+        );
+
+        let mut result = all.clone();
+        result.sort_by(SimilarOrder::Frequency);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["people", "multiple", "chapel", "grapple"]
+        );
+
+        let band = |band: FrequencyBand| {
+            let mut result = all.clone();
+            result.filter(&SimilarFilter {
+                frequency: Some(band),
+                ..Default::default()
+            });
+            return first_group_words(&result)
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>();
+        };
+        assert_eq!(band(FrequencyBand::Common), vec!["people", "multiple"]);
+        assert_eq!(band(FrequencyBand::Uncommon), vec!["chapel"]);
+        assert_eq!(band(FrequencyBand::Rare), vec!["grapple"]);
+    }
+
+    #[test]
+    fn test_sidecar_metadata() {
+        let path = temp_path("metadata.dict");
+        let sidecar = metadata::sidecar_path(&path);
+        std::fs::write(
+            &path,
+            "light L AY1 T\nbright B R AY1 T\nwrite R AY1 T\nquite K W AY1 T\n\
+             kite K AY1 T\nlead L EH1 D\nlead(2) L IY1 D\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &sidecar,
+            "light n v adj f:120\nbright adj f:60\nwrite v\nquite adv f:300\nkite n\n\
+             lead v\nlead(2) n\n",
+        )
+        .unwrap();
+        let mut shelf = Shelf::new();
+        shelf.init_dictionary(&path, Format::Cmudict).unwrap();
+        assert!(shelf.has_metadata());
+        assert!(shelf.has_frequencies());
+
+        let lead = shelf.lookup("lead").unwrap();
+        assert_eq!(lead[0].parts_of_speech(), &[PartOfSpeech::Verb]);
+        assert_eq!(lead[1].parts_of_speech(), &[PartOfSpeech::Noun]);
+        assert_eq!(
+            lead[1].to_string(),
+            format!(
+                "\"lead\" (L IY1 D); variant=2, syllables=1; from {}; tagged n",
+                path
+            )
+        );
+        assert_eq!(shelf.frequency("quite"), Some(300_000));
+        assert_eq!(shelf.frequency("kite"), None);
+
+        let all = shelf.similar("light");
+        let kite = all.groups[0].words.iter().find(|w| w.word == "kite");
+        assert_eq!(kite.unwrap().pos, vec![PartOfSpeech::Noun]);
+        let pos = |pos: PartOfSpeech| {
+            let mut result = all.clone();
+            result.filter(&SimilarFilter {
+                pos: Some(pos),
+                ..Default::default()
+            });
+            return first_group_words(&result)
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<String>>();
+        };
+        assert_eq!(pos(PartOfSpeech::Noun), vec!["kite"]);
+        assert_eq!(pos(PartOfSpeech::Adjective), vec!["bright"]);
+        assert_eq!(pos(PartOfSpeech::Verb), vec!["write"]);
+
+        // Without a frequency list, the frequencies come from the metadata.
+        let mut result = all.clone();
+        result.sort_by(SimilarOrder::Frequency);
+        assert_eq!(
+            first_group_words(&result),
+            vec!["quite", "bright", "kite", "write"]
+        );
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+
+    #[test]
+    fn test_dictionary_load_modes() {
+        let path = temp_path("malformed.dict");
+        std::fs::write(
+            &path,
+            "# A comment.\nour AW1 ER0\n\nlonely\nour(x) AW1 R\nfar F AA1 R # ok\n",
+        )
+        .unwrap();
+
+        let dict = DictionaryImpl::new_from_cmudict_file(&path, LoadMode::Lenient).unwrap();
+        assert_eq!(dict.len(), 2);
+        assert!(dict.lookup("far").is_some());
+        let skipped: Vec<String> = dict.skipped_lines().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            skipped,
+            vec![
+                format!("{}:4: no phonemes for \"lonely\"", path),
+                format!("{}:5: invalid term \"our(x)\"", path),
+            ]
+        );
+
+        match DictionaryImpl::new_from_cmudict_file(&path, LoadMode::Strict) {
+            Err(DictionaryError::Malformed { line, .. }) => assert_eq!(line, 4),
+            other => panic!("expected a malformed line error, got {:?}", other.err()),
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        match DictionaryImpl::new_from_cmudict_file(&path, LoadMode::Lenient) {
+            Err(DictionaryError::Io { path: p, .. }) => assert_eq!(p, path),
+            other => panic!("expected an io error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_compiled_index() {
+        let path = temp_path("compiled.dict");
+        let index = index_path(&path);
+        std::fs::write(&path, "our AW1 ER0\nfar F AA1 R\nour(2) AW1 R\nlonely\n").unwrap();
+        let compiled = DictionaryImpl::compile_index(&path, LoadMode::Lenient).unwrap();
+        let source = std::fs::read(&path).unwrap();
+
+        let loaded = DictionaryImpl::from_index(&index, &path, checksum(&source)).unwrap();
+        assert_eq!(loaded.entries, compiled.entries);
+        assert_eq!(loaded.reverse_list, compiled.reverse_list);
+        assert_eq!(loaded.stress_index, compiled.stress_index);
+        assert_eq!(loaded.rhyme_index, compiled.rhyme_index);
+        assert_eq!(loaded.forward_list, compiled.forward_list);
+        assert_eq!(
+            loaded.lookup_variant("our", 2).unwrap().source.as_deref(),
+            Some(path.as_str())
+        );
+        assert_eq!(
+            loaded.skipped_lines()[0].to_string(),
+            format!("{}:4: no phonemes for \"lonely\"", path)
+        );
+        // The skipped line still fails a strict load, even from the index.
+        assert!(DictionaryImpl::load(&path, LoadMode::Strict).is_err());
+
+        // A changed source makes the index stale, and load() falls back to the text.
+        std::fs::write(&path, "our AW1 ER0\nfar F AA1 R\nstar S T AA1 R\n").unwrap();
+        let source = std::fs::read(&path).unwrap();
+        let stale = DictionaryImpl::from_index(&index, &path, checksum(&source));
+        assert!(stale
+            .err()
+            .unwrap()
+            .contains("has changed since it was compiled"));
+        let dict = DictionaryImpl::load(&path, LoadMode::Strict).unwrap();
+        assert!(dict.lookup("star").is_some());
+
+        // So does a corrupt index.
+        DictionaryImpl::compile_index(&path, LoadMode::Strict).unwrap();
+        let mut bytes = std::fs::read(&index).unwrap();
+        bytes[20] ^= 0xff;
+        std::fs::write(&index, &bytes).unwrap();
+        let corrupt = DictionaryImpl::from_index(&index, &path, checksum(&source));
+        assert!(corrupt.err().unwrap().contains("corrupt"));
+        assert!(DictionaryImpl::load(&path, LoadMode::Strict)
+            .unwrap()
+            .lookup("star")
+            .is_some());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&index).unwrap();
+    }
+
+    /// Returns this process's resident memory, in kB, if it's available.
+    fn resident_kb() -> Option<usize> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+        line.split_whitespace().nth(1)?.parse().ok()
+    }
+
+    /// Times loading a whole dictionary and finding rhymes for some of its words. Run with
+    /// `POET_BENCH_DICT=./cmudict.dict cargo test --release bench -- --ignored --nocapture`.
+    #[test]
+    #[ignore] // It's slow, and only prints measurements.
+    fn bench_load_and_similar() {
+        let path = std::env::var("POET_BENCH_DICT").unwrap_or("./cmudict.dict".to_string());
+        let rss_before = resident_kb();
+        let start = std::time::Instant::now();
+        let dict = DictionaryImpl::new_from_cmudict_file(&path, LoadMode::Lenient).unwrap();
+        println!("Loaded {} terms in {:?}", dict.len(), start.elapsed());
+        if let (Some(before), Some(after)) = (rss_before, resident_kb()) {
+            println!("Resident memory grew by {} kB", after - before);
+        }
+
+        DictionaryImpl::compile_index(&path, LoadMode::Lenient).unwrap();
+        let start = std::time::Instant::now();
+        let compiled = DictionaryImpl::load(&path, LoadMode::Lenient).unwrap();
+        println!(
+            "Loaded {} terms from the compiled index in {:?}",
+            compiled.len(),
+            start.elapsed()
+        );
+        std::fs::remove_file(index_path(&path)).unwrap();
+
+        let mut words: Vec<&String> = dict.entries.keys().collect();
+        words.sort();
+        let words: Vec<&String> = words.into_iter().step_by(100).take(1000).collect();
+        let start = std::time::Instant::now();
+        let mut found = 0;
+        for word in &words {
+            found += dict
+                .similar(word)
+                .groups
+                .iter()
+                .map(|g| g.words.len())
+                .sum::<usize>();
+        }
+        println!(
+            "Found {} similar words for {} terms in {:?}",
+            found,
+            words.len(),
+            start.elapsed()
+        );
+
+        let start = std::time::Instant::now();
+        let mut found = 0;
+        for word in words.iter().take(10) {
+            let phonemes = &dict.lookup(word).unwrap()[0].phonemes;
+            found += dict.near_rhymes(phonemes, Some(word), 50).num_words();
+        }
+        println!(
+            "Found {} near rhymes for 10 terms in {:?}",
+            found,
+            start.elapsed()
+        );
+
+        let start = std::time::Instant::now();
+        let mut found = 0;
+        for word in &words {
+            let phonemes = &dict.lookup(word).unwrap()[0].phonemes;
+            found += dict.minimal_pairs(phonemes, Some(word)).num_words();
+        }
+        println!(
+            "Found {} minimal pairs for {} terms in {:?}",
+            found,
+            words.len(),
+            start.elapsed()
+        );
+
+        // The lookup page finds mosaic rhymes while it holds the shelf, so none can be slow.
+        let start = std::time::Instant::now();
+        let mut found = 0;
+        let mut slowest = std::time::Duration::ZERO;
+        for word in &words {
+            let phonemes = &dict.lookup(word).unwrap()[0].phonemes;
+            let word_start = std::time::Instant::now();
+            found += mosaic_rhymes(&dict, phonemes, Some(word), 20).num_words();
+            slowest = slowest.max(word_start.elapsed());
+        }
+        println!(
+            "Found {} mosaic rhymes for {} terms in {:?}, at most {:?} for one",
+            found,
+            words.len(),
+            start.elapsed(),
+            slowest
+        );
+        assert!(
+            slowest < std::time::Duration::from_millis(250),
+            "mosaic rhymes took {:?}",
+            slowest
+        );
+    }
+
     #[test]
     #[ignore] // It's slow.
     fn test_can_read_entire_cmudict() {
-        let _dict = DictionaryImpl::new_from_cmudict_file("./cmudict.dict").unwrap();
+        let _dict =
+            DictionaryImpl::new_from_cmudict_file("./cmudict.dict", LoadMode::Strict).unwrap();
         // The test is successful if it doesn't crash.
     }
 } // mod tests
@@ -0,0 +1,209 @@
+//! Word frequencies, for ranking and filtering suggestions by how common the words are.
+//!
+//! A frequency list is a text file with a word and its count in some corpus on each line,
+//! separated by whitespace, like the unigram counts from Google Books or SUBTLEX:
+//!
+//! ```noformat
+//! the 23135851162
+//! of 13151942776
+//! sonnet 1393447
+//! ```
+//!
+//! Only the relative counts matter, since they're scaled to occurrences per billion words.
+//! Words are matched case-insensitively, and `#` starts a comment.
+use crate::poet::dictionary::{DictionaryError, LoadMode};
+use rocket::serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+
+/// How many times each word occurs per billion words of text.
+#[derive(Debug, Default)]
+pub struct Frequencies {
+    per_billion: HashMap<String, u64>,
+
+    /// Malformed lines skipped while loading in `LoadMode::Lenient`.
+    skipped: Vec<DictionaryError>,
+}
+
+/// Words at least this frequent (10 per million) are `FrequencyBand::Common`.
+const COMMON_PER_BILLION: u64 = 10_000;
+
+/// Words less frequent than this (1 per million) are `FrequencyBand::Rare`.
+const RARE_PER_BILLION: u64 = 1_000;
+
+/// A rough grouping of words by frequency, for filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum FrequencyBand {
+    /// At least 10 per million words, e.g. "flower" or "orange".
+    Common,
+    /// From 1 to 10 per million words, e.g. "sonnet".
+    Uncommon,
+    /// Under 1 per million words, including words that aren't in the list at all, like most
+    /// surnames and acronyms.
+    Rare,
+}
+
+impl FrequencyBand {
+    /// Returns the band for a frequency from `Frequencies::get`.
+    pub fn of(per_billion: u64) -> FrequencyBand {
+        if per_billion >= COMMON_PER_BILLION {
+            FrequencyBand::Common
+        } else if per_billion >= RARE_PER_BILLION {
+            FrequencyBand::Uncommon
+        } else {
+            FrequencyBand::Rare
+        }
+    }
+}
+
+impl std::str::FromStr for FrequencyBand {
+    type Err = String;
+
+    /// Parses the lowercase name of the band, e.g. "common".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "common" => Ok(FrequencyBand::Common),
+            "uncommon" => Ok(FrequencyBand::Uncommon),
+            "rare" => Ok(FrequencyBand::Rare),
+            _ => Err(format!("unknown frequency band: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for FrequencyBand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FrequencyBand::Common => "common",
+            FrequencyBand::Uncommon => "uncommon",
+            FrequencyBand::Rare => "rare",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Frequencies {
+    /// Loads the frequency list at `path`. See the module documentation for the format.
+    pub fn load(path: &str, mode: LoadMode) -> Result<Frequencies, DictionaryError> {
+        let file = std::fs::File::open(path).map_err(|error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        return Frequencies::from_reader(path, std::io::BufReader::new(file), mode);
+    }
+
+    /// Reads a frequency list from `reader`. `path` is only used in errors.
+    pub fn from_reader(
+        path: &str,
+        reader: impl BufRead,
+        mode: LoadMode,
+    ) -> Result<Frequencies, DictionaryError> {
+        let mut out = Frequencies::default();
+        let mut counts: Vec<(String, u64)> = vec![];
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|error| DictionaryError::Io {
+                path: path.to_string(),
+                error,
+            })?;
+            let text = line.split('#').next().unwrap_or("");
+            let fields: Vec<&str> = text.split_whitespace().collect();
+            let count = match fields[..] {
+                [] => continue,
+                [_, count] => count.parse::<u64>().ok(),
+                _ => None,
+            };
+            match count {
+                Some(count) => counts.push((fields[0].to_lowercase(), count)),
+                None => {
+                    let error = DictionaryError::Malformed {
+                        path: path.to_string(),
+                        line: i + 1,
+                        reason: "expected a word and a count".to_string(),
+                    };
+                    if mode == LoadMode::Strict {
+                        return Err(error);
+                    }
+                    out.skipped.push(error);
+                }
+            }
+        }
+        let total: u128 = counts.iter().map(|(_, count)| *count as u128).sum();
+        for (word, count) in counts {
+            let per_billion = (count as u128 * 1_000_000_000 / total.max(1)) as u64;
+            // Lists with several spellings that fold to the same word count them all.
+            *out.per_billion.entry(word).or_default() += per_billion;
+        }
+        return Ok(out);
+    }
+
+    /// Returns how many times `word` occurs per billion words, or 0 if it isn't in the list.
+    pub fn get(&self, word: &str) -> u64 {
+        if let Some(n) = self.per_billion.get(word) {
+            return *n;
+        }
+        return self
+            .per_billion
+            .get(&word.to_lowercase())
+            .copied()
+            .unwrap_or(0);
+    }
+
+    /// Returns the malformed lines that were skipped when loading the list.
+    pub fn skipped_lines(&self) -> &[DictionaryError] {
+        &self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_and_scales_counts() {
+        let text = "# word count\nThe 600\nflower 300\nsonnet 99\nzyzzyva 1\n\nthe 0\n";
+        let freqs = Frequencies::from_reader("test", text.as_bytes(), LoadMode::Strict).unwrap();
+        assert_eq!(freqs.per_billion.len(), 4);
+        assert_eq!(freqs.get("the"), 600_000_000);
+        assert_eq!(freqs.get("Flower"), 300_000_000);
+        assert_eq!(freqs.get("zyzzyva"), 1_000_000);
+        assert_eq!(freqs.get("nonesuch"), 0);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let text = "the 10\nflower\nsonnet many\ndaisy 1 2\n";
+        let freqs = Frequencies::from_reader("test", text.as_bytes(), LoadMode::Lenient).unwrap();
+        assert_eq!(freqs.per_billion.len(), 1);
+        let lines: Vec<String> = freqs
+            .skipped_lines()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "test:2: expected a word and a count",
+                "test:3: expected a word and a count",
+                "test:4: expected a word and a count",
+            ]
+        );
+        let error = Frequencies::from_reader("test", text.as_bytes(), LoadMode::Strict);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "test:2: expected a word and a count"
+        );
+    }
+
+    #[test]
+    fn bands() {
+        assert_eq!(FrequencyBand::of(50_000), FrequencyBand::Common);
+        assert_eq!(FrequencyBand::of(10_000), FrequencyBand::Common);
+        assert_eq!(FrequencyBand::of(9_999), FrequencyBand::Uncommon);
+        assert_eq!(FrequencyBand::of(999), FrequencyBand::Rare);
+        assert_eq!(FrequencyBand::of(0), FrequencyBand::Rare);
+        assert_eq!("rare".parse(), Ok(FrequencyBand::Rare));
+        assert!("often".parse::<FrequencyBand>().is_err());
+        assert_eq!(FrequencyBand::Uncommon.to_string(), "uncommon");
+    }
+}
@@ -0,0 +1,508 @@
+//! Guesses at the pronunciation of words that aren't in the dictionary.
+//!
+//! There are two local sources of guesses:
+//!
+//! * Morphology: strips common suffixes and prefixes (e.g. "-ing", "-s", "un-"), or splits a
+//!   compound into two known words, and builds the pronunciation from the known parts. For
+//!   example, "moonlit" is "moon" + "lit", and "flowering" is "flower" + "-ing".
+//! * Spelling: a crude letter-to-sound guesser, for when nothing better applies.
+//!
+//! Neither is reliable. The candidates are meant as starting points for a person to accept or
+//! edit before adding the word to the user dictionary.
+use rocket::serde::Serialize;
+
+use crate::poet::dictionary::{Dictionary, Entry, Phonemes};
+use crate::poet::phoneme::Stress;
+
+/// Where a candidate pronunciation came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum CandidateSource {
+    /// Built from known words by `morphology_candidates`.
+    Morphology,
+    /// Guessed from the letters by `spelling_candidate`.
+    Spelling,
+    /// Fetched from the Datamuse API.
+    Datamuse,
+    /// Typed in by the user.
+    Edited,
+}
+
+/// A possible pronunciation for an unknown word.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub source: CandidateSource,
+    pub phonemes: Phonemes,
+    /// How the candidate was derived, e.g. "flower + -ing". May be empty.
+    pub note: String,
+}
+
+/// The most candidates returned by `local_candidates`.
+const MAX_CANDIDATES: usize = 5;
+
+/// Returns candidate pronunciations for `word` from morphology and then spelling, without
+/// duplicates.
+pub fn local_candidates(word: &str, dict: &dyn Dictionary) -> Vec<Candidate> {
+    let mut out: Vec<Candidate> = vec![];
+    let mut candidates = morphology_candidates(word, dict);
+    candidates.extend(spelling_candidate(word));
+    for candidate in candidates {
+        if out.len() >= MAX_CANDIDATES {
+            break;
+        }
+        if !out.iter().any(|c| c.phonemes == candidate.phonemes) {
+            out.push(candidate);
+        }
+    }
+    return out;
+}
+
+/// How deep `from_known_parts` will go, e.g. "un-" + "kind" + "-ness" needs two levels.
+const MAX_MORPHOLOGY_DEPTH: usize = 2;
+
+/// Returns pronunciations of `word` that are built from words in the dictionary.
+pub fn morphology_candidates(word: &str, dict: &dyn Dictionary) -> Vec<Candidate> {
+    let mut out = vec![];
+    for (phonemes, note) in from_known_parts(word, dict, MAX_MORPHOLOGY_DEPTH) {
+        out.push(Candidate {
+            source: CandidateSource::Morphology,
+            phonemes: to_phonemes(&phonemes),
+            note,
+        });
+    }
+    for (first, second) in compound_splits(word, dict) {
+        // The second half of a compound usually has secondary stress, e.g. "moonlight".
+        let mut phonemes = first.phonemes.phonemes.clone();
+        phonemes.extend(second.phonemes.phonemes.iter().map(|p| {
+            if p.stress() == Some(Stress::Primary) {
+                p.with_stress(Stress::Secondary)
+            } else {
+                *p
+            }
+        }));
+        out.push(Candidate {
+            source: CandidateSource::Morphology,
+            phonemes: Phonemes::from_phonemes(phonemes),
+            note: format!("{} + {}", first.dict_key(), second.dict_key()),
+        });
+    }
+    return out;
+}
+
+/// The sound a suffix adds to the stem.
+enum SuffixSound {
+    Fixed(&'static str),
+    /// "-s", which is "S", "Z" or "IH0 Z" depending on the end of the stem.
+    Plural,
+    /// "-ed", which is "T", "D" or "IH0 D" depending on the end of the stem.
+    Past,
+}
+
+const SUFFIXES: &[(&str, SuffixSound)] = &[
+    ("'s", SuffixSound::Plural),
+    ("s", SuffixSound::Plural),
+    ("es", SuffixSound::Plural),
+    ("ed", SuffixSound::Past),
+    ("ing", SuffixSound::Fixed("IH0 NG")),
+    ("er", SuffixSound::Fixed("ER0")),
+    ("est", SuffixSound::Fixed("AH0 S T")),
+    ("ly", SuffixSound::Fixed("L IY0")),
+    ("y", SuffixSound::Fixed("IY0")),
+    ("ness", SuffixSound::Fixed("N AH0 S")),
+    ("less", SuffixSound::Fixed("L AH0 S")),
+    ("ful", SuffixSound::Fixed("F AH0 L")),
+    ("ment", SuffixSound::Fixed("M AH0 N T")),
+    ("able", SuffixSound::Fixed("AH0 B AH0 L")),
+];
+
+const PREFIXES: &[(&str, &str)] = &[
+    ("un", "AH0 N"),
+    ("re", "R IY0"),
+    ("dis", "D IH0 S"),
+    ("mis", "M IH0 S"),
+    ("pre", "P R IY0"),
+    ("non", "N AA1 N"),
+    ("over", "OW2 V ER0"),
+    ("out", "AW2 T"),
+];
+
+/// Returns (phonemes, note) pairs for `word`, either from the dictionary or by stripping
+/// affixes and recursing on the stem, up to `depth` times.
+fn from_known_parts(word: &str, dict: &dyn Dictionary, depth: usize) -> Vec<(Vec<String>, String)> {
+    let mut out = vec![];
+    if let Some(entries) = dict.lookup(word) {
+        for entry in entries {
+            let phonemes = entry.phonemes.phonemes.iter().map(|p| p.to_string());
+            out.push((phonemes.collect(), entry.dict_key()));
+        }
+        return out;
+    }
+    if depth == 0 {
+        return out;
+    }
+
+    for (suffix, sound) in SUFFIXES {
+        for stem in suffix_stems(word, suffix) {
+            for (mut phonemes, note) in from_known_parts(&stem, dict, depth - 1) {
+                let last = phonemes.last().map(|p| p.as_str()).unwrap_or("");
+                let added = match sound {
+                    SuffixSound::Fixed(s) => *s,
+                    SuffixSound::Plural => plural_sound(last),
+                    SuffixSound::Past => past_sound(last),
+                };
+                phonemes.extend(added.split_whitespace().map(String::from));
+                out.push((phonemes, format!("{} + -{}", note, suffix)));
+            }
+        }
+    }
+    for (prefix, sound) in PREFIXES {
+        let stem = match word.strip_prefix(prefix) {
+            Some(stem) if stem.len() >= 3 => stem,
+            _ => continue,
+        };
+        for (phonemes, note) in from_known_parts(stem, dict, depth - 1) {
+            let mut with_prefix: Vec<String> = sound.split_whitespace().map(String::from).collect();
+            with_prefix.extend(phonemes);
+            out.push((with_prefix, format!("{}- + {}", prefix, note)));
+        }
+    }
+    return out;
+}
+
+/// Returns the possible spellings of the stem of `word` without `suffix`, e.g. "hoping" could
+/// be "hop", "hope", and "running" could be "run".
+fn suffix_stems(word: &str, suffix: &str) -> Vec<String> {
+    let stem = match word.strip_suffix(suffix) {
+        Some(stem) if stem.len() >= 2 => stem,
+        _ => return vec![],
+    };
+    let mut stems = vec![stem.to_string()];
+    if !suffix.starts_with('\'') && !stem.ends_with('e') {
+        // "hoping" -> "hope", "baker" -> "bake".
+        stems.push(format!("{}e", stem));
+    }
+    let bytes = stem.as_bytes();
+    let last = bytes[bytes.len() - 1];
+    if last.is_ascii_alphabetic() && last == bytes[bytes.len() - 2] && !b"aeiousl".contains(&last) {
+        // "running" -> "run", but not "seeing" -> "se" or "kissed" -> "kis".
+        stems.push(stem[..stem.len() - 1].to_string());
+    }
+    if let Some(s) = stem.strip_suffix('i') {
+        // "tried" -> "try", "happily" -> "happy".
+        stems.push(format!("{}y", s));
+    }
+    return stems;
+}
+
+/// Returns the sound of a plural "-s" after a word ending in `last`.
+fn plural_sound(last: &str) -> &'static str {
+    match last {
+        "S" | "Z" | "SH" | "ZH" | "CH" | "JH" => "IH0 Z",
+        "P" | "T" | "K" | "F" | "TH" => "S",
+        _ => "Z",
+    }
+}
+
+/// Returns the sound of a past tense "-ed" after a word ending in `last`.
+fn past_sound(last: &str) -> &'static str {
+    match last {
+        "T" | "D" => "IH0 D",
+        "P" | "K" | "F" | "TH" | "S" | "SH" | "CH" => "T",
+        _ => "D",
+    }
+}
+
+/// Returns the ways of splitting `word` into two dictionary words, e.g. "moon" + "light".
+fn compound_splits<'a>(word: &str, dict: &'a dyn Dictionary) -> Vec<(&'a Entry, &'a Entry)> {
+    let mut out = vec![];
+    for (i, _) in word.char_indices().skip(3) {
+        if word.len() - i < 3 {
+            break;
+        }
+        if let (Some(first), Some(second)) = (
+            dict.lookup_variant(&word[..i], 1),
+            dict.lookup_variant(&word[i..], 1),
+        ) {
+            out.push((first, second));
+        }
+    }
+    return out;
+}
+
+fn to_phonemes(phonemes: &[String]) -> Phonemes {
+    // These come from dictionary entries and `GRAPHEMES`, so they're all ARPABET.
+    return Entry::from_parts("", &phonemes.join(" "))
+        .expect("guessed phonemes are valid")
+        .phonemes;
+}
+
+/// Letter sequences and their usual sounds, checked in order before single letters.
+///
+/// Vowel sounds have no stress here; `spelling_candidate` adds it.
+const GRAPHEMES: &[(&str, &str)] = &[
+    ("tion", "SH AH N"),
+    ("sion", "ZH AH N"),
+    ("ough", "AO"),
+    ("augh", "AO"),
+    ("eigh", "EY"),
+    ("igh", "AY"),
+    ("tch", "CH"),
+    ("dge", "JH"),
+    ("sch", "S K"),
+    ("ch", "CH"),
+    ("sh", "SH"),
+    ("th", "TH"),
+    ("ph", "F"),
+    ("ck", "K"),
+    ("ng", "NG"),
+    ("qu", "K W"),
+    ("wh", "W"),
+    ("wr", "R"),
+    ("kn", "N"),
+    ("gh", ""),
+    ("ee", "IY"),
+    ("ea", "IY"),
+    ("oo", "UW"),
+    ("ou", "AW"),
+    ("ow", "OW"),
+    ("oi", "OY"),
+    ("oy", "OY"),
+    ("ai", "EY"),
+    ("ay", "EY"),
+    ("au", "AO"),
+    ("aw", "AO"),
+    ("ew", "UW"),
+    ("ie", "IY"),
+    ("ei", "IY"),
+    ("oa", "OW"),
+    ("ue", "UW"),
+    ("ar", "AA R"),
+    ("er", "ER"),
+    ("ir", "ER"),
+    ("ur", "ER"),
+    ("or", "AO R"),
+];
+
+const VOWEL_SOUNDS: &[&str] = &[
+    "AA", "AE", "AH", "AO", "AW", "AY", "EH", "ER", "EY", "IH", "IY", "OW", "OY", "UH", "UW",
+];
+
+fn is_vowel_letter(c: u8) -> bool {
+    return b"aeiou".contains(&c);
+}
+
+/// Guesses the pronunciation of `word` from its spelling, with primary stress on the first
+/// vowel. Returns None if the word has no letters.
+pub fn spelling_candidate(word: &str) -> Option<Candidate> {
+    let letters: Vec<u8> = word
+        .bytes()
+        .filter(|b| b.is_ascii_alphabetic())
+        .map(|b| b.to_ascii_lowercase())
+        .collect();
+    if letters.is_empty() {
+        return None;
+    }
+    let text = std::str::from_utf8(&letters).unwrap();
+    let n = letters.len();
+
+    // A final "e" is silent if there's another vowel, e.g. "make", and a vowel before a single
+    // consonant and the silent "e" is long, e.g. "make" vs. "mack".
+    let silent_e = n >= 3
+        && letters[n - 1] == b'e'
+        && letters[..n - 1]
+            .iter()
+            .any(|&c| is_vowel_letter(c) || c == b'y');
+    let long_vowel_at = if silent_e
+        && !is_vowel_letter(letters[n - 2])
+        && is_vowel_letter(letters[n - 3])
+        && (n == 3 || !is_vowel_letter(letters[n - 4]))
+    {
+        Some(n - 3)
+    } else {
+        None
+    };
+    let end = if silent_e { n - 1 } else { n };
+
+    let mut sounds: Vec<&str> = vec![];
+    let mut i = 0;
+    'letters: while i < end {
+        if Some(i) != long_vowel_at {
+            for (spelling, sound) in GRAPHEMES {
+                if text[i..end].starts_with(spelling) {
+                    sounds.extend(sound.split_whitespace());
+                    i += spelling.len();
+                    continue 'letters;
+                }
+            }
+        }
+        let c = letters[i];
+        let next = letters.get(i + 1).copied();
+        let sound = match c {
+            b'a' if Some(i) == long_vowel_at => "EY",
+            b'e' if Some(i) == long_vowel_at => "IY",
+            b'i' if Some(i) == long_vowel_at => "AY",
+            b'o' if Some(i) == long_vowel_at => "OW",
+            b'u' if Some(i) == long_vowel_at => "UW",
+            b'a' => "AE",
+            b'e' => "EH",
+            b'i' => "IH",
+            b'o' => "AA",
+            b'u' => "AH",
+            b'y' if i == 0 => "Y",
+            b'y' if i + 1 == end && sounds.iter().any(|s| VOWEL_SOUNDS.contains(s)) => "IY",
+            b'y' if i + 1 == end => "AY",
+            b'y' => "IH",
+            b'c' if matches!(next, Some(b'e') | Some(b'i') | Some(b'y')) => "S",
+            b'c' | b'k' | b'q' => "K",
+            b'x' => "K S",
+            b'b' => "B",
+            b'd' => "D",
+            b'f' => "F",
+            b'g' => "G",
+            b'h' => "HH",
+            b'j' => "JH",
+            b'l' => "L",
+            b'm' => "M",
+            b'n' => "N",
+            b'p' => "P",
+            b'r' => "R",
+            b's' => "S",
+            b't' => "T",
+            b'v' => "V",
+            b'w' => "W",
+            b'z' => "Z",
+            _ => "",
+        };
+        sounds.extend(sound.split_whitespace());
+        // Double consonants make one sound, e.g. "ll" in "ball".
+        if next == Some(c) && !is_vowel_letter(c) {
+            i += 1;
+        }
+        i += 1;
+    }
+
+    let mut stressed = false;
+    let phonemes: Vec<String> = sounds
+        .iter()
+        .map(|s| {
+            if !VOWEL_SOUNDS.contains(s) {
+                return s.to_string();
+            }
+            let stress = if stressed { '0' } else { '1' };
+            stressed = true;
+            format!("{}{}", s, stress)
+        })
+        .collect();
+    if phonemes.is_empty() {
+        return None;
+    }
+    return Some(Candidate {
+        source: CandidateSource::Spelling,
+        phonemes: to_phonemes(&phonemes),
+        note: String::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::DictionaryImpl;
+
+    fn test_dictionary() -> DictionaryImpl {
+        let mut dict = DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "flower F L AW1 ER0",
+            "hope HH OW1 P",
+            "run R AH1 N",
+            "kind K AY1 N D",
+            "try T R AY1",
+            "box B AA1 K S",
+            "cat K AE1 T",
+            "moon M UW1 N",
+            "light L AY1 T",
+            "want W AA1 N T",
+        ]);
+        dict
+    }
+
+    /// Returns the phonemes of the morphology candidates for `word`.
+    fn morphology(word: &str) -> Vec<String> {
+        let dict = test_dictionary();
+        return morphology_candidates(word, &dict)
+            .iter()
+            .map(|c| c.phonemes.to_string())
+            .collect();
+    }
+
+    #[test]
+    fn test_suffixes() {
+        assert!(morphology("flowers").contains(&"F L AW1 ER0 Z".to_string()));
+        assert!(morphology("cats").contains(&"K AE1 T S".to_string()));
+        assert!(morphology("boxes").contains(&"B AA1 K S IH0 Z".to_string()));
+        assert!(morphology("wanted").contains(&"W AA1 N T IH0 D".to_string()));
+        assert!(morphology("hoped").contains(&"HH OW1 P T".to_string()));
+        assert!(morphology("hoping").contains(&"HH OW1 P IH0 NG".to_string()));
+        assert!(morphology("running").contains(&"R AH1 N IH0 NG".to_string()));
+        assert!(morphology("tried").contains(&"T R AY1 D".to_string()));
+    }
+
+    #[test]
+    fn test_prefixes_and_stacked_affixes() {
+        assert!(morphology("unkind").contains(&"AH0 N K AY1 N D".to_string()));
+        assert!(morphology("unkindness").contains(&"AH0 N K AY1 N D N AH0 S".to_string()));
+        assert!(morphology("flowering").contains(&"F L AW1 ER0 IH0 NG".to_string()));
+    }
+
+    #[test]
+    fn test_compounds() {
+        let dict = test_dictionary();
+        let candidates = morphology_candidates("moonlight", &dict);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].phonemes.to_string(), "M UW1 N L AY2 T");
+        assert_eq!(candidates[0].note, "moon + light");
+    }
+
+    #[test]
+    fn test_notes() {
+        let dict = test_dictionary();
+        let candidates = morphology_candidates("flowering", &dict);
+        assert_eq!(candidates[0].note, "flower + -ing");
+        assert_eq!(candidates[0].source, CandidateSource::Morphology);
+        assert!(morphology_candidates("zzyzx", &dict).is_empty());
+    }
+
+    fn spelling(word: &str) -> String {
+        return spelling_candidate(word).unwrap().phonemes.to_string();
+    }
+
+    #[test]
+    fn test_spelling_candidate() {
+        assert_eq!(spelling("cat"), "K AE1 T");
+        assert_eq!(spelling("make"), "M EY1 K");
+        assert_eq!(spelling("night"), "N AY1 T");
+        assert_eq!(spelling("ship"), "SH IH1 P");
+        assert_eq!(spelling("city"), "S IH1 T IY0");
+        assert_eq!(spelling("my"), "M AY1");
+        assert_eq!(spelling("station"), "S T AE1 SH AH0 N");
+        assert_eq!(spelling_candidate("'").map(|c| c.phonemes), None);
+        assert_eq!(
+            spelling_candidate("cat").unwrap().phonemes.num_syllables(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_local_candidates_are_deduplicated() {
+        let dict = test_dictionary();
+        let candidates = local_candidates("cats", &dict);
+        assert_eq!(candidates[0].source, CandidateSource::Morphology);
+        assert_eq!(candidates[0].phonemes.to_string(), "K AE1 T S");
+        // The spelling guess is the same, so it's dropped.
+        assert_eq!(candidates.len(), 1);
+
+        let candidates = local_candidates("zorp", &dict);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].source, CandidateSource::Spelling);
+    }
+}
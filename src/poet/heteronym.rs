@@ -0,0 +1,254 @@
+//! Guesses which pronunciation of a heteronym is meant, from the words around it.
+//!
+//! Words like "read", "lead", "wind", "live" and "tear" have pronunciations that depend on
+//! what they mean, e.g. "R IY1 D" in "I will read" but "R EH1 D" in "I have read". cmudict
+//! lists both, so without some help the analysis would pick whichever fits the form.
+//!
+//! This is a lightweight pass, not a parser. The word before (and sometimes after) a heteronym
+//! is a cue for how it's used, e.g. "to" before a verb or "the" before a noun, and each reading
+//! scores by how well it fits the cues:
+//!
+//! ```
+//! let scores = likelihoods(&["the", "wind", "blows"], 1, entries);
+//! ```
+//!
+//! Readings come from a small table of common heteronyms, or else from the parts of speech in
+//! the entries' metadata, if any.
+use crate::poet::dictionary::Entry;
+use crate::poet::metadata::PartOfSpeech;
+
+/// How a reading of a word is used, as far as the cues can tell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Usage {
+    /// A verb in the base or present tense, e.g. "to read".
+    Verb,
+    /// A verb in the past tense or as a past participle, e.g. "have read".
+    PastVerb,
+    Noun,
+    Adjective,
+}
+
+/// The usages of the pronunciations of some common heteronyms, as (word, phonemes, usage).
+const READINGS: &[(&str, &str, Usage)] = &[
+    ("bow", "B AW1", Usage::Verb),
+    ("bow", "B OW1", Usage::Noun),
+    ("close", "K L OW1 Z", Usage::Verb),
+    ("close", "K L OW1 S", Usage::Adjective),
+    ("dove", "D OW1 V", Usage::PastVerb),
+    ("dove", "D AH1 V", Usage::Noun),
+    ("lead", "L IY1 D", Usage::Verb),
+    ("lead", "L EH1 D", Usage::Noun),
+    ("live", "L IH1 V", Usage::Verb),
+    ("live", "L AY1 V", Usage::Adjective),
+    ("read", "R IY1 D", Usage::Verb),
+    ("read", "R EH1 D", Usage::PastVerb),
+    ("tear", "T EH1 R", Usage::Verb),
+    ("tear", "T IH1 R", Usage::Noun),
+    ("use", "Y UW1 Z", Usage::Verb),
+    ("use", "Y UW1 S", Usage::Noun),
+    ("wind", "W AY1 N D", Usage::Verb),
+    ("wind", "W IH1 N D", Usage::Noun),
+    ("wound", "W AW1 N D", Usage::PastVerb),
+    ("wound", "W UW1 N D", Usage::Noun),
+];
+
+/// Words that come before a verb in its base form, e.g. "to" or "will".
+const BEFORE_VERB: &[&str] = &[
+    "to", "will", "would", "can", "could", "shall", "should", "may", "might", "must", "do", "does",
+    "did", "don't", "doesn't", "didn't", "won't", "can't", "cannot", "let's", "i'll", "you'll",
+    "we'll", "they'll", "he'll", "she'll",
+];
+
+/// Words that come before a past participle, e.g. "have read".
+const BEFORE_PARTICIPLE: &[&str] = &[
+    "have", "has", "had", "having", "i've", "you've", "we've", "they've", "been", "was", "were",
+];
+
+/// Forms of "to be", which come before either a participle or an adjective.
+const BEFORE_PARTICIPLE_OR_ADJECTIVE: &[&str] = &["is", "are", "am", "be", "being", "it's"];
+
+/// Subjects that take a verb ending in -s in the present tense, so a bare verb after them is
+/// probably in the past tense, e.g. "she read".
+const THIRD_PERSON: &[&str] = &["he", "she", "it", "who"];
+
+/// Other subjects, after which a verb can be in either tense.
+const SUBJECTS: &[&str] = &["i", "you", "we", "they"];
+
+/// Words that come before a noun or an adjective, e.g. "the" or "her".
+const DETERMINERS: &[&str] = &[
+    "a", "an", "the", "this", "that", "these", "those", "my", "your", "his", "her", "its", "our",
+    "their", "no", "each", "every", "some", "any",
+];
+
+/// Words that come before an adjective, e.g. "so close".
+const BEFORE_ADJECTIVE: &[&str] = &["so", "too", "very", "more", "most", "as", "quite"];
+
+/// Words that come after a verb that takes an object, e.g. "wind the clock".
+const OBJECTS: &[&str] = &[
+    "a", "an", "the", "this", "that", "my", "your", "his", "her", "our", "their", "me", "him",
+    "us", "them", "it",
+];
+
+/// Returns what the neighbouring words suggest about how the `index`-th word is used, with a
+/// weight for each usage.
+fn cues(words: &[&str], index: usize) -> Vec<(Usage, i32)> {
+    let mut out = vec![];
+    if index > 0 {
+        let prev = words[index - 1];
+        if BEFORE_VERB.contains(&prev) {
+            out.push((Usage::Verb, 2));
+        } else if BEFORE_PARTICIPLE.contains(&prev) {
+            out.push((Usage::PastVerb, 2));
+        } else if BEFORE_PARTICIPLE_OR_ADJECTIVE.contains(&prev) {
+            out.push((Usage::PastVerb, 1));
+            out.push((Usage::Adjective, 1));
+        } else if THIRD_PERSON.contains(&prev) {
+            out.push((Usage::PastVerb, 1));
+        } else if SUBJECTS.contains(&prev) {
+            out.push((Usage::Verb, 1));
+            out.push((Usage::PastVerb, 1));
+        } else if DETERMINERS.contains(&prev) {
+            out.push((Usage::Noun, 2));
+            out.push((Usage::Adjective, 1));
+        } else if BEFORE_ADJECTIVE.contains(&prev) {
+            out.push((Usage::Adjective, 2));
+        }
+    }
+    if let Some(next) = words.get(index + 1) {
+        if OBJECTS.contains(next) {
+            out.push((Usage::Verb, 1));
+            out.push((Usage::PastVerb, 1));
+        } else if *next == "of" {
+            out.push((Usage::Noun, 1));
+        }
+    }
+    return out;
+}
+
+/// Returns the usages of `entry`, from `READINGS` or its metadata.
+fn usages(entry: &Entry) -> Vec<Usage> {
+    let phonemes = entry.phonemes.to_string();
+    let known: Vec<Usage> = READINGS
+        .iter()
+        .filter(|(word, p, _)| *word == entry.word && *p == phonemes)
+        .map(|(_, _, usage)| *usage)
+        .collect();
+    if !known.is_empty() {
+        return known;
+    }
+    return entry
+        .parts_of_speech()
+        .iter()
+        .filter_map(|pos| match pos {
+            PartOfSpeech::Noun => Some(Usage::Noun),
+            PartOfSpeech::Verb => Some(Usage::Verb),
+            PartOfSpeech::Adjective => Some(Usage::Adjective),
+            PartOfSpeech::Adverb => None,
+        })
+        .collect();
+}
+
+/// Scores each of `entries`, the pronunciations of the `index`-th of `words`, by how likely it
+/// is given the words around it. Higher is more likely, and all zeros means there's no telling.
+///
+/// `words` should be normalized, as in `snippet::normalize_for_lookup`.
+pub fn likelihoods(words: &[&str], index: usize, entries: &[Entry]) -> Vec<i32> {
+    if entries.len() < 2 {
+        return vec![0; entries.len()];
+    }
+    let cues = cues(words, index);
+    return entries
+        .iter()
+        .map(|entry| {
+            let usages = usages(entry);
+            cues.iter()
+                .filter(|(usage, _)| usages.contains(usage))
+                .map(|(_, weight)| weight)
+                .sum()
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::metadata::EntryMetadata;
+    use std::sync::Arc;
+
+    /// Returns the phonemes of the most likely of `entries` for the `index`-th word of `text`,
+    /// or None if it's a tie.
+    fn most_likely(text: &str, index: usize, entries: &[Entry]) -> Option<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let scores = likelihoods(&words, index, entries);
+        let best = *scores.iter().max().unwrap();
+        if scores.iter().filter(|s| **s == best).count() > 1 {
+            return None;
+        }
+        let i = scores.iter().position(|s| *s == best).unwrap();
+        return Some(entries[i].phonemes.to_string());
+    }
+
+    #[test]
+    fn ranks_known_heteronyms() {
+        let read = vec![Entry::new("read R IY1 D"), Entry::new("read(2) R EH1 D")];
+        assert_eq!(most_likely("i will read", 2, &read).unwrap(), "R IY1 D");
+        assert_eq!(most_likely("i have read", 2, &read).unwrap(), "R EH1 D");
+        assert_eq!(most_likely("she read it", 1, &read).unwrap(), "R EH1 D");
+        assert_eq!(most_likely("read", 0, &read), None);
+        assert_eq!(most_likely("we read it", 1, &read), None);
+
+        let wind = vec![
+            Entry::new("wind W IH1 N D"),
+            Entry::new("wind(2) W AY1 N D"),
+        ];
+        assert_eq!(
+            most_likely("the wind blows", 1, &wind).unwrap(),
+            "W IH1 N D"
+        );
+        assert_eq!(
+            most_likely("to wind the clock", 1, &wind).unwrap(),
+            "W AY1 N D"
+        );
+
+        let tear = vec![Entry::new("tear T EH1 R"), Entry::new("tear(2) T IH1 R")];
+        assert_eq!(most_likely("a tear of joy", 1, &tear).unwrap(), "T IH1 R");
+        assert_eq!(most_likely("don't tear it", 1, &tear).unwrap(), "T EH1 R");
+
+        let live = vec![Entry::new("live L IH1 V"), Entry::new("live(2) L AY1 V")];
+        assert_eq!(
+            most_likely("the show is live", 3, &live).unwrap(),
+            "L AY1 V"
+        );
+        assert_eq!(most_likely("where they live", 2, &live).unwrap(), "L IH1 V");
+    }
+
+    #[test]
+    fn ranks_by_metadata() {
+        let tagged = |line: &str, pos: PartOfSpeech| {
+            let mut entry = Entry::new(line);
+            entry.metadata = Some(Arc::new(EntryMetadata {
+                pos: vec![pos],
+                frequency: None,
+            }));
+            entry
+        };
+        let record = vec![
+            tagged("record R EH1 K ER0 D", PartOfSpeech::Noun),
+            tagged("record(2) R IH0 K AO1 R D", PartOfSpeech::Verb),
+        ];
+        assert_eq!(
+            most_likely("the record", 1, &record).unwrap(),
+            "R EH1 K ER0 D"
+        );
+        assert_eq!(
+            most_likely("to record", 1, &record).unwrap(),
+            "R IH0 K AO1 R D"
+        );
+
+        // Words with one pronunciation don't need ranking.
+        assert_eq!(
+            likelihoods(&["the", "cat"], 1, &[Entry::new("cat K AE1 T")]),
+            vec![0]
+        );
+    }
+}
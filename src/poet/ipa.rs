@@ -0,0 +1,431 @@
+//! Converts pronunciations between ARPABET and the International Phonetic Alphabet.
+//!
+//! Most people who know any phonetic notation know IPA, so poet can show pronunciations in it
+//! and read them back, e.g. for looking up rhymes for a made-up word:
+//!
+//! ```
+//! let hello = Entry::new("hello HH AH0 L OW1").phonemes;
+//! assert_eq!(hello.to_ipa(), "həˈloʊ");
+//! assert_eq!(Phonemes::from_ipa("/həˈloʊ/")?, hello);
+//! ```
+//!
+//! The symbols are those of General American, like cmudict's. Stress marks go before the
+//! syllable they stress, as split up by `syllable` ("ˈæk.ʃən", not "ˈækʃ.ən"). Words of one
+//! syllable aren't marked.
+//!
+//! When reading IPA, some other common symbols are accepted too, like "ɒ", "r" and "ː", but
+//! they're folded into the nearest ARPABET phoneme. Without stress marks, the first full vowel
+//! (not "ə" or "ɚ") is taken to be stressed.
+//!
+//! Related references:
+//!
+//! * <https://en.wikipedia.org/wiki/ARPABET>
+//! * <https://en.wikipedia.org/wiki/Help:IPA/English>
+use crate::poet::dictionary::Phonemes;
+use crate::poet::phoneme::{self, Phoneme, Stress, Symbol};
+use crate::poet::syllable;
+
+/// How to write out pronunciations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Notation {
+    /// cmudict's own notation, e.g. "HH AH0 L OW1".
+    #[default]
+    Arpabet,
+    /// IPA, e.g. "həˈloʊ".
+    Ipa,
+}
+
+impl Notation {
+    /// Writes `phonemes` in this notation.
+    pub fn format(&self, phonemes: &Phonemes) -> String {
+        match self {
+            Notation::Arpabet => phonemes.to_string(),
+            Notation::Ipa => to_ipa(&phonemes.phonemes),
+        }
+    }
+
+    /// Writes `phonemes` in this notation with the syllables set apart, e.g. "HH AH0 · L OW1"
+    /// or "ˈpoʊ.ə.tɹi". This is for reading, and can't always be parsed back.
+    pub fn format_syllables(&self, phonemes: &Phonemes) -> String {
+        match self {
+            Notation::Arpabet => phonemes.to_syllabified_string(),
+            Notation::Ipa => to_syllabified_ipa(&phonemes.phonemes),
+        }
+    }
+}
+
+impl std::str::FromStr for Notation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arpabet" => Ok(Notation::Arpabet),
+            "ipa" => Ok(Notation::Ipa),
+            _ => Err(format!("unknown notation: {}", s)),
+        }
+    }
+}
+
+/// Returns the IPA for a symbol, given whether it's stressed.
+fn symbol_to_ipa(symbol: Symbol, stressed: bool) -> &'static str {
+    match symbol {
+        Symbol::AA => "ɑ",
+        Symbol::AE => "æ",
+        Symbol::AH if stressed => "ʌ",
+        Symbol::AH => "ə",
+        Symbol::AO => "ɔ",
+        Symbol::AW => "aʊ",
+        Symbol::AY => "aɪ",
+        Symbol::B => "b",
+        Symbol::CH => "tʃ",
+        Symbol::D => "d",
+        Symbol::DH => "ð",
+        Symbol::EH => "ɛ",
+        Symbol::ER if stressed => "ɝ",
+        Symbol::ER => "ɚ",
+        Symbol::EY => "eɪ",
+        Symbol::F => "f",
+        Symbol::G => "ɡ",
+        Symbol::HH => "h",
+        Symbol::IH => "ɪ",
+        Symbol::IY => "i",
+        Symbol::JH => "dʒ",
+        Symbol::K => "k",
+        Symbol::L => "l",
+        Symbol::M => "m",
+        Symbol::N => "n",
+        Symbol::NG => "ŋ",
+        Symbol::OW => "oʊ",
+        Symbol::OY => "ɔɪ",
+        Symbol::P => "p",
+        Symbol::R => "ɹ",
+        Symbol::S => "s",
+        Symbol::SH => "ʃ",
+        Symbol::T => "t",
+        Symbol::TH => "θ",
+        Symbol::UH => "ʊ",
+        Symbol::UW => "u",
+        Symbol::V => "v",
+        Symbol::W => "w",
+        Symbol::Y => "j",
+        Symbol::Z => "z",
+        Symbol::ZH => "ʒ",
+    }
+}
+
+/// The IPA symbols that `from_ipa` reads, with their ARPABET symbols. Where one is a prefix of
+/// another, e.g. "a" and "aɪ", the longer one comes first.
+const IPA_SYMBOLS: &[(&str, Symbol)] = &[
+    ("aɪ", Symbol::AY),
+    ("aʊ", Symbol::AW),
+    ("eɪ", Symbol::EY),
+    ("oʊ", Symbol::OW),
+    ("əʊ", Symbol::OW),
+    ("ɔɪ", Symbol::OY),
+    // British "near", "square" and "cure", which cmudict spells with an R instead.
+    ("ɪə", Symbol::IH),
+    ("eə", Symbol::EH),
+    ("ɛə", Symbol::EH),
+    ("ʊə", Symbol::UH),
+    ("tʃ", Symbol::CH),
+    ("dʒ", Symbol::JH),
+    ("ʧ", Symbol::CH),
+    ("ʤ", Symbol::JH),
+    ("ɑ", Symbol::AA),
+    ("ɒ", Symbol::AA),
+    ("a", Symbol::AA),
+    ("æ", Symbol::AE),
+    ("ʌ", Symbol::AH),
+    ("ə", Symbol::AH),
+    ("ɐ", Symbol::AH),
+    ("ɔ", Symbol::AO),
+    ("ɛ", Symbol::EH),
+    ("e", Symbol::EH),
+    ("ɝ", Symbol::ER),
+    ("ɜ", Symbol::ER),
+    ("ɚ", Symbol::ER),
+    ("ɪ", Symbol::IH),
+    ("i", Symbol::IY),
+    ("o", Symbol::OW),
+    ("ʊ", Symbol::UH),
+    ("u", Symbol::UW),
+    ("b", Symbol::B),
+    ("d", Symbol::D),
+    ("ð", Symbol::DH),
+    ("f", Symbol::F),
+    ("ɡ", Symbol::G),
+    ("g", Symbol::G),
+    ("h", Symbol::HH),
+    ("k", Symbol::K),
+    ("l", Symbol::L),
+    ("ɫ", Symbol::L),
+    ("m", Symbol::M),
+    ("n", Symbol::N),
+    ("ŋ", Symbol::NG),
+    ("p", Symbol::P),
+    ("ɹ", Symbol::R),
+    ("r", Symbol::R),
+    ("s", Symbol::S),
+    ("ʃ", Symbol::SH),
+    ("t", Symbol::T),
+    ("ɾ", Symbol::T),
+    ("θ", Symbol::TH),
+    ("v", Symbol::V),
+    ("w", Symbol::W),
+    ("j", Symbol::Y),
+    ("z", Symbol::Z),
+    ("ʒ", Symbol::ZH),
+];
+
+/// The IPA vowels that are always unstressed.
+const REDUCED: &[&str] = &["ə", "ɚ", "ɐ"];
+
+/// Marks for primary and secondary stress. "'" is accepted for typing convenience.
+const PRIMARY_STRESS: &[char] = &['ˈ', '\''];
+const SECONDARY_STRESS: &[char] = &['ˌ'];
+
+/// Characters that `from_ipa` skips: length marks, syllable breaks, spaces and the slashes and
+/// brackets that IPA transcriptions are often written in.
+const IGNORED: &[char] = &['ː', 'ˑ', '.', ' ', '/', '[', ']'];
+
+/// The mark under a syllabic consonant, as in "n̩" for the end of "button".
+const SYLLABIC: char = '\u{329}';
+
+/// Writes `phonemes` in IPA, with stress marks if there's more than one syllable.
+pub fn to_ipa(phonemes: &[Phoneme]) -> String {
+    return write_ipa(phonemes, false);
+}
+
+/// Like `to_ipa`, but with a "." between syllables where there's no stress mark, e.g.
+/// "ˈpoʊ.ə.tɹi".
+pub fn to_syllabified_ipa(phonemes: &[Phoneme]) -> String {
+    return write_ipa(phonemes, true);
+}
+
+/// Writes `phonemes` in IPA, marking the start of each syllable (see `syllable`) with its
+/// stress, or with a "." if `breaks` is set.
+fn write_ipa(phonemes: &[Phoneme], breaks: bool) -> String {
+    let mut out = String::with_capacity(phonemes.len() * 2);
+    let syllables = syllable::syllabify(phonemes);
+    if syllables.len() < 2 {
+        for ph in phonemes {
+            out.push_str(symbol_to_ipa(ph.symbol(), ph.is_stressed()));
+        }
+        return out;
+    }
+    for (i, syllable) in syllables.iter().enumerate() {
+        match syllable.stress() {
+            Stress::Primary => out.push('ˈ'),
+            Stress::Secondary => out.push('ˌ'),
+            Stress::Unstressed if breaks && i > 0 => out.push('.'),
+            Stress::Unstressed => {}
+        }
+        for ph in syllable.phonemes() {
+            out.push_str(symbol_to_ipa(ph.symbol(), ph.is_stressed()));
+        }
+    }
+    return out;
+}
+
+/// Reads a pronunciation in IPA, e.g. "/həˈloʊ/". See the module documentation.
+pub fn from_ipa(text: &str) -> Result<Phonemes, String> {
+    // The symbols with their stress marks, if any, and whether they're reduced vowels.
+    let mut symbols: Vec<(Symbol, Option<Stress>, bool)> = vec![];
+    let mut pending: Option<Stress> = None;
+    let mut any_marks = false;
+    let mut rest = text.trim();
+    while let Some(c) = rest.chars().next() {
+        if IGNORED.contains(&c) {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        if PRIMARY_STRESS.contains(&c) || SECONDARY_STRESS.contains(&c) {
+            pending = Some(if PRIMARY_STRESS.contains(&c) {
+                Stress::Primary
+            } else {
+                Stress::Secondary
+            });
+            any_marks = true;
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        if c == SYLLABIC {
+            // A syllabic consonant has a schwa before it, e.g. "button" is "B AH1 T AH0 N".
+            let consonant = match symbols.pop() {
+                Some((symbol, _, _)) if !symbol.is_vowel() => symbol,
+                _ => return Err(format!("\"{}\" needs a consonant before it", c)),
+            };
+            symbols.push((Symbol::AH, pending.take(), true));
+            symbols.push((consonant, None, false));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (ipa, symbol) = match IPA_SYMBOLS.iter().find(|(ipa, _)| rest.starts_with(ipa)) {
+            Some(found) => *found,
+            None => return Err(format!("unknown IPA symbol \"{}\"", c)),
+        };
+        if symbol.is_vowel() {
+            symbols.push((symbol, pending.take(), REDUCED.contains(&ipa)));
+        } else {
+            symbols.push((symbol, None, false));
+        }
+        rest = &rest[ipa.len()..];
+    }
+
+    // Without any marks, stress the first full vowel, or else the first vowel.
+    let mut stressed = None;
+    if !any_marks {
+        let vowels: Vec<usize> = (0..symbols.len())
+            .filter(|i| symbols[*i].0.is_vowel())
+            .collect();
+        stressed = vowels
+            .iter()
+            .find(|i| !symbols[**i].2)
+            .or_else(|| vowels.first())
+            .copied();
+    }
+
+    let mut out = Vec::with_capacity(symbols.len());
+    for (i, (symbol, stress, _)) in symbols.into_iter().enumerate() {
+        let text = if symbol.is_vowel() {
+            let stress = match stress {
+                Some(stress) => stress,
+                None if stressed == Some(i) => Stress::Primary,
+                None => Stress::Unstressed,
+            };
+            format!("{}{}", symbol.as_str(), stress as u8)
+        } else {
+            symbol.as_str().to_string()
+        };
+        out.push(text.parse::<Phoneme>()?);
+    }
+    return Ok(Phonemes::from_phonemes(out));
+}
+
+/// Reads a pronunciation typed as a query, in either notation: IPA if it's in slashes or
+/// brackets or has any non-ASCII characters, e.g. "/ˈpoʊ.ɪt/" or "ˈpoʊɪt", and ARPABET
+/// otherwise, e.g. "P OW1 IH0 T". ARPABET may be split into syllables with "·", as
+/// `Notation::format_syllables` writes it. It must have at least one vowel.
+pub fn parse_pronunciation(text: &str) -> Result<Phonemes, String> {
+    let text = text.trim();
+    let arpabet = text.replace('·', " ");
+    let is_ipa = text.starts_with('/') || text.starts_with('[') || !arpabet.is_ascii();
+    let phonemes = if is_ipa {
+        Phonemes::from_ipa(text)?
+    } else {
+        Phonemes::from_phonemes(phoneme::parse_sequence(&arpabet)?)
+    };
+    if phonemes.num_syllables() == 0 {
+        return Err(format!("no vowels in \"{}\"", text));
+    }
+    return Ok(phonemes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Entry;
+
+    fn ipa(line: &str) -> String {
+        Entry::new(line).phonemes.to_ipa()
+    }
+
+    fn arpabet(ipa: &str) -> String {
+        Phonemes::from_ipa(ipa).unwrap().to_string()
+    }
+
+    #[test]
+    fn writes_ipa() {
+        assert_eq!(ipa("hello HH AH0 L OW1"), "həˈloʊ");
+        assert_eq!(ipa("cat K AE1 T"), "kæt");
+        assert_eq!(ipa("but B AH1 T"), "bʌt");
+        assert_eq!(ipa("a AH0"), "ə");
+        assert_eq!(ipa("action AE1 K SH AH0 N"), "ˈækʃən");
+        assert_eq!(ipa("extra EH1 K S T R AH0"), "ˈɛkstɹə");
+        assert_eq!(ipa("poetry P OW1 AH0 T R IY0"), "ˈpoʊətɹi");
+        assert_eq!(ipa("misspell M IH0 S S P EH1 L"), "mɪsˈspɛl");
+        assert_eq!(ipa("understand AH2 N D ER0 S T AE1 N D"), "ˌʌndɚˈstænd");
+        assert_eq!(ipa("singer S IH1 NG ER0"), "ˈsɪŋɚ");
+        assert_eq!(ipa("church CH ER1 CH"), "tʃɝtʃ");
+    }
+
+    #[test]
+    fn writes_syllables() {
+        let format =
+            |notation: Notation, line: &str| notation.format_syllables(&Entry::new(line).phonemes);
+        let poetry = "poetry P OW1 AH0 T R IY0";
+        assert_eq!(format(Notation::Arpabet, poetry), "P OW1 · AH0 · T R IY0");
+        assert_eq!(format(Notation::Ipa, poetry), "ˈpoʊ.ə.tɹi");
+        assert_eq!(
+            format(Notation::Ipa, "understand AH2 N D ER0 S T AE1 N D"),
+            "ˌʌn.dɚˈstænd"
+        );
+        assert_eq!(format(Notation::Arpabet, "cat K AE1 T"), "K AE1 T");
+        assert_eq!(format(Notation::Arpabet, "hmm HH M"), "HH M");
+        assert_eq!(format(Notation::Ipa, "cat K AE1 T"), "kæt");
+    }
+
+    #[test]
+    fn reads_ipa() {
+        assert_eq!(arpabet("/həˈloʊ/"), "HH AH0 L OW1");
+        assert_eq!(arpabet("kæt"), "K AE1 T");
+        assert_eq!(arpabet("[ˈbʌtn̩]"), "B AH1 T AH0 N");
+        assert_eq!(arpabet("ˌʌndɚˈstænd"), "AH2 N D ER0 S T AE1 N D");
+        assert_eq!(arpabet("ˈpoʊ.ɪt"), "P OW1 IH0 T");
+        assert_eq!(arpabet("bəˈnɑːnə"), "B AH0 N AA1 N AH0");
+        assert_eq!(arpabet("bənænə"), "B AH0 N AE1 N AH0");
+        assert_eq!(arpabet("ɡrɒt"), "G R AA1 T");
+        assert_eq!(arpabet("'tʃɝtʃ"), "CH ER1 CH");
+        assert_eq!(
+            Phonemes::from_ipa("ʀat").unwrap_err(),
+            "unknown IPA symbol \"ʀ\""
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        for line in [
+            "hello HH AH0 L OW1",
+            "orange AO1 R AH0 N JH",
+            "understand AH2 N D ER0 S T AE1 N D",
+            "measure M EH1 ZH ER0",
+            "thoughtful TH AO1 T F AH0 L",
+            "boyhood B OY1 HH UH2 D",
+            "cat K AE1 T",
+        ] {
+            let phonemes = Entry::new(line).phonemes;
+            assert_eq!(
+                Phonemes::from_ipa(&phonemes.to_ipa()),
+                Ok(phonemes),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn parses_either_notation() {
+        let poet = "P OW1 AH0 T";
+        assert_eq!(parse_pronunciation(poet).unwrap().to_string(), poet);
+        assert_eq!(
+            parse_pronunciation("p ow1 ah0 t").unwrap().to_string(),
+            poet
+        );
+        assert_eq!(parse_pronunciation("/ˈpoʊət/").unwrap().to_string(), poet);
+        assert_eq!(parse_pronunciation("ˈpoʊət").unwrap().to_string(), poet);
+        assert_eq!(
+            parse_pronunciation("P OW1 · AH0 T").unwrap().to_string(),
+            poet
+        );
+        assert!(parse_pronunciation("poet").is_err());
+        assert_eq!(
+            parse_pronunciation("S T").unwrap_err(),
+            "no vowels in \"S T\""
+        );
+        assert_eq!(
+            Notation::Ipa.format(&Entry::new("poet P OW1 AH0 T").phonemes),
+            "ˈpoʊət"
+        );
+        assert_eq!("ipa".parse(), Ok(Notation::Ipa));
+    }
+}
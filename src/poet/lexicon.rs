@@ -0,0 +1,403 @@
+//! Loaders for pronunciation dictionaries, in cmudict's format and a few others.
+//!
+//! cmudict is General American, so other dialects and specialist vocabularies come from other
+//! lexicons, which are loaded onto the `Shelf` alongside it. Each `Format` is converted into
+//! `Entry`s on loading:
+//!
+//! * `cmudict`: "word W ER1 D" lines, as in `dictionary`.
+//! * `cmudict-nostress`: the same, but vowels may lack stress digits, e.g. "hello HH AH L OW".
+//!   The first full vowel (not AH or ER) of each word is stressed, like in `ipa::from_ipa`.
+//! * `ipa-tsv`: a word, a tab, then its pronunciations in IPA, separated by commas, as in the
+//!   ipa-dict files, e.g. "tomato\t/təˈmɑːtəʊ/, /təˈmeɪtəʊ/".
+//! * `json`: an object from each word to a pronunciation or a list of them, in IPA or ARPABET
+//!   (see `ipa::parse_pronunciation`), e.g. `{"tomato": ["/təˈmɑːtəʊ/", "T AH0 M EY1 T OW2"]}`.
+//!   The ipa-dict JSON files, which wrap such objects in `{"en_UK": [...]}`, work too.
+//!
+//! Words in the IPA and JSON lexicons are lowercased, and a word's pronunciations become its
+//! variants, in the order they're listed.
+//!
+//! Related references:
+//!
+//! * <https://github.com/open-dict-data/ipa-dict>
+use crate::poet::dictionary::{DictionaryError, DictionaryImpl, Entry, LoadMode, Phonemes};
+use crate::poet::ipa;
+use crate::poet::phoneme::Symbol;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The format of a dictionary file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Cmudict,
+    CmudictNoStress,
+    IpaTsv,
+    Json,
+}
+
+impl Format {
+    /// Every format, in the order they're documented.
+    pub const ALL: [Format; 4] = [
+        Format::Cmudict,
+        Format::CmudictNoStress,
+        Format::IpaTsv,
+        Format::Json,
+    ];
+
+    /// Returns the name of the format, as used on the command line, e.g. "ipa-tsv".
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Cmudict => "cmudict",
+            Format::CmudictNoStress => "cmudict-nostress",
+            Format::IpaTsv => "ipa-tsv",
+            Format::Json => "json",
+        }
+    }
+
+    /// Guesses the format of the file at `path` from its extension: ".tsv" for `IpaTsv`,
+    /// ".json" for `Json`, and `Cmudict` for anything else.
+    pub fn from_path(path: &str) -> Format {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        match extension.as_deref() {
+            Some("tsv") => Format::IpaTsv,
+            Some("json") => Format::Json,
+            _ => Format::Cmudict,
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Format::ALL.iter().find(|f| f.name() == s) {
+            Some(format) => Ok(*format),
+            None => Err(format!("unknown dictionary format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A dictionary file to load, as given on the command line: a path, optionally after its
+/// format and a colon, e.g. "ipa-tsv:en_UK.txt". Without a format, it's guessed from the path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Source {
+    pub path: String,
+    pub format: Format,
+}
+
+impl std::str::FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((prefix, path)) = s.split_once(':') {
+            if let Ok(format) = prefix.parse() {
+                return Ok(Source {
+                    path: path.to_string(),
+                    format,
+                });
+            }
+        }
+        if s.is_empty() {
+            return Err("empty dictionary path".to_string());
+        }
+        return Ok(Source {
+            path: s.to_string(),
+            format: Format::from_path(s),
+        });
+    }
+}
+
+/// Loads the dictionary at `path` in the given format.
+///
+/// cmudict files are loaded with `DictionaryImpl::load()`, so they can use a compiled index.
+/// Malformed entries in the others are handled as `mode` says, like malformed cmudict lines.
+pub fn load(path: &str, format: Format, mode: LoadMode) -> Result<DictionaryImpl, DictionaryError> {
+    if format == Format::Cmudict {
+        return DictionaryImpl::load(path, mode);
+    }
+    let text = std::fs::read_to_string(path).map_err(|error| DictionaryError::Io {
+        path: path.to_string(),
+        error,
+    })?;
+    return from_text(path, &text, format, mode);
+}
+
+/// Reads a dictionary in the given format from `text`. `path` names the dictionary and is used
+/// in errors.
+pub fn from_text(
+    path: &str,
+    text: &str,
+    format: Format,
+    mode: LoadMode,
+) -> Result<DictionaryImpl, DictionaryError> {
+    let mut entries = vec![];
+    let mut skipped = vec![];
+    let mut skip = |error: DictionaryError| {
+        if mode == LoadMode::Strict {
+            return Err(error);
+        }
+        skipped.push(error);
+        Ok(())
+    };
+
+    if format == Format::Json {
+        let lexicon = read_json(text).map_err(|reason| DictionaryError::Malformed {
+            path: path.to_string(),
+            line: reason.0,
+            reason: reason.1,
+        })?;
+        let mut variants = Variants::default();
+        for (word, value) in lexicon {
+            match json_pronunciations(value) {
+                Ok(pronunciations) => {
+                    entries.extend(variants.entries(&word, pronunciations));
+                }
+                Err(reason) => skip(DictionaryError::MalformedEntry {
+                    path: path.to_string(),
+                    word,
+                    reason,
+                })?,
+            }
+        }
+        return Ok(DictionaryImpl::from_entries(path, entries, skipped));
+    }
+
+    let mut variants = Variants::default();
+    for (i, line) in text.lines().enumerate() {
+        let content = line.split('#').next().unwrap_or("");
+        if content.trim().is_empty() {
+            continue;
+        }
+        let parsed = match format {
+            Format::CmudictNoStress => parse_unstressed_line(content).map(|e| vec![e]),
+            _ => parse_tsv_line(content)
+                .map(|(word, pronunciations)| variants.entries(&word, pronunciations)),
+        };
+        match parsed {
+            Ok(parsed) => entries.extend(parsed),
+            Err(reason) => skip(DictionaryError::Malformed {
+                path: path.to_string(),
+                line: i + 1,
+                reason,
+            })?,
+        }
+    }
+    return Ok(DictionaryImpl::from_entries(path, entries, skipped));
+}
+
+/// Numbers the pronunciations of each word as variants, across all of the lines they're on.
+#[derive(Default)]
+struct Variants {
+    last: HashMap<String, i32>,
+}
+
+impl Variants {
+    /// Returns an entry for each of the pronunciations of `word`, numbered after any earlier ones.
+    fn entries(&mut self, word: &str, pronunciations: Vec<Phonemes>) -> Vec<Entry> {
+        let last = self.last.entry(word.to_string()).or_insert(0);
+        let mut out = Vec::with_capacity(pronunciations.len());
+        for phonemes in pronunciations {
+            *last += 1;
+            out.push(Entry {
+                word: word.to_string(),
+                phonemes,
+                variant: *last,
+                source: None,
+                metadata: None,
+            });
+        }
+        return out;
+    }
+}
+
+/// Parses a cmudict line whose vowels may lack stress digits, e.g. "hello HH AH L OW".
+///
+/// If no vowel has primary stress, the first full vowel gets it, or else the first vowel. The
+/// other unmarked vowels are unstressed.
+fn parse_unstressed_line(line: &str) -> Result<Entry, String> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let is_vowel = |token: &str| Symbol::from_name(token).is_some_and(|s| s.is_vowel());
+    let has_primary = tokens.iter().skip(1).any(|t| t.ends_with('1'));
+    let vowels: Vec<usize> = (1..tokens.len()).filter(|i| is_vowel(tokens[*i])).collect();
+    let stressed = if has_primary {
+        None
+    } else {
+        vowels
+            .iter()
+            .find(|i| !matches!(tokens[**i], "AH" | "ER"))
+            .or_else(|| vowels.first())
+            .copied()
+    };
+
+    let mut text = String::with_capacity(line.len() + vowels.len());
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        text.push_str(token);
+        if vowels.contains(&i) {
+            text.push(if stressed == Some(i) { '1' } else { '0' });
+        }
+    }
+    return Entry::parse(&text);
+}
+
+/// Parses an ipa-dict line, e.g. "tomato\t/təˈmɑːtəʊ/, /təˈmeɪtəʊ/", into the lowercased word
+/// and its pronunciations.
+fn parse_tsv_line(line: &str) -> Result<(String, Vec<Phonemes>), String> {
+    let (word, pronunciations) = match line.split_once('\t') {
+        Some((word, pronunciations)) => (word.trim(), pronunciations),
+        None => return Err("expected a word, a tab and its pronunciations".to_string()),
+    };
+    if word.is_empty() {
+        return Err("empty word".to_string());
+    }
+    let pronunciations = pronunciations
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(Phonemes::from_ipa)
+        .collect::<Result<Vec<Phonemes>, String>>()?;
+    if pronunciations.is_empty() {
+        return Err(format!("no pronunciations for \"{}\"", word));
+    }
+    return Ok((word.to_lowercase(), pronunciations));
+}
+
+/// Reads a JSON lexicon into its words and their pronunciations, in the order they're listed,
+/// unwrapping ipa-dict's `{"en_UK": [...]}`. Errors have the line number and the reason.
+fn read_json(text: &str) -> Result<Vec<(String, Value)>, (usize, String)> {
+    let value: Value = serde_json::from_str(text).map_err(|e| (e.line(), e.to_string()))?;
+    let object = match value {
+        Value::Object(object) => object,
+        _ => return Err((1, "expected an object of words".to_string())),
+    };
+    let mut out = vec![];
+    for (key, value) in object {
+        match value {
+            // ipa-dict wraps the words in a language code and a list.
+            Value::Array(items) if items.iter().all(|v| v.is_object()) && !items.is_empty() => {
+                for item in items {
+                    if let Value::Object(words) = item {
+                        out.extend(words.into_iter().map(|(w, v)| (w.to_lowercase(), v)));
+                    }
+                }
+            }
+            value => out.push((key.to_lowercase(), value)),
+        }
+    }
+    return Ok(out);
+}
+
+/// Returns the pronunciations in a JSON lexicon's value for a word, which can be a string of
+/// them separated by commas, or a list of strings.
+fn json_pronunciations(value: Value) -> Result<Vec<Phonemes>, String> {
+    let texts: Vec<String> = match value {
+        Value::String(text) => text.split(',').map(String::from).collect(),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(text) => Ok(text),
+                other => Err(format!("expected a pronunciation, not {}", other)),
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        other => return Err(format!("expected a pronunciation, not {}", other)),
+    };
+    let pronunciations = texts
+        .iter()
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| ipa::parse_pronunciation(text))
+        .collect::<Result<Vec<Phonemes>, String>>()?;
+    if pronunciations.is_empty() {
+        return Err("no pronunciations".to_string());
+    }
+    return Ok(pronunciations);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Dictionary;
+
+    fn pronunciations(dict: &DictionaryImpl, word: &str) -> Vec<String> {
+        match dict.lookup(word) {
+            Some(entries) => entries.iter().map(|e| e.phonemes.to_string()).collect(),
+            None => vec![],
+        }
+    }
+
+    #[test]
+    fn parses_sources() {
+        let source = |s: &str| s.parse::<Source>().unwrap();
+        assert_eq!(source("ipa-tsv:en_UK.txt").format, Format::IpaTsv);
+        assert_eq!(source("ipa-tsv:en_UK.txt").path, "en_UK.txt");
+        assert_eq!(source("words.json").format, Format::Json);
+        assert_eq!(source("./cmudict.dict").format, Format::Cmudict);
+        assert_eq!(source("C:\\cmudict.dict").path, "C:\\cmudict.dict");
+        assert!("".parse::<Source>().is_err());
+        for format in Format::ALL {
+            assert_eq!(format.name().parse(), Ok(format));
+        }
+    }
+
+    #[test]
+    fn reads_ipa_tsv() {
+        let text = "tomato\t/təˈmɑːtəʊ/, /təˈmeɪtəʊ/\nCat\t/kæt/\n# comment\n\nbad line\n";
+        let dict = from_text("en.tsv", text, Format::IpaTsv, LoadMode::Lenient).unwrap();
+        assert_eq!(
+            pronunciations(&dict, "tomato"),
+            vec!["T AH0 M AA1 T OW0", "T AH0 M EY1 T OW0"]
+        );
+        assert_eq!(dict.lookup("tomato").unwrap()[1].variant, 2);
+        assert_eq!(pronunciations(&dict, "cat"), vec!["K AE1 T"]);
+        assert_eq!(
+            dict.skipped_lines()[0].to_string(),
+            "en.tsv:5: expected a word, a tab and its pronunciations"
+        );
+
+        let strict = from_text("en.tsv", text, Format::IpaTsv, LoadMode::Strict);
+        assert!(strict.is_err());
+    }
+
+    #[test]
+    fn reads_cmudict_without_stress() {
+        let text = "hello HH AH L OW\nbutter B AH T ER\nreset R IY0 S EH1 T\nsong(2) S AO NG\n";
+        let dict = from_text("x", text, Format::CmudictNoStress, LoadMode::Strict).unwrap();
+        assert_eq!(pronunciations(&dict, "hello"), vec!["HH AH0 L OW1"]);
+        assert_eq!(pronunciations(&dict, "butter"), vec!["B AH1 T ER0"]);
+        assert_eq!(pronunciations(&dict, "reset"), vec!["R IY0 S EH1 T"]);
+        assert_eq!(dict.lookup("song").unwrap()[0].variant, 2);
+    }
+
+    #[test]
+    fn reads_json() {
+        let text = r#"{"Tomato": ["/təˈmɑːtəʊ/", "T AH0 M EY1 T OW2"], "cat": "/kæt/", "dog": 3}"#;
+        let dict = from_text("x.json", text, Format::Json, LoadMode::Lenient).unwrap();
+        assert_eq!(
+            pronunciations(&dict, "tomato"),
+            vec!["T AH0 M AA1 T OW0", "T AH0 M EY1 T OW2"]
+        );
+        assert_eq!(pronunciations(&dict, "cat"), vec!["K AE1 T"]);
+        assert_eq!(
+            dict.skipped_lines()[0].to_string(),
+            "x.json: \"dog\": expected a pronunciation, not 3"
+        );
+
+        // ipa-dict's layout.
+        let text = r#"{"en_UK": [{"a": "/ə/, /ˈeɪ/", "cat": "/kˈat/"}]}"#;
+        let dict = from_text("x.json", text, Format::Json, LoadMode::Strict).unwrap();
+        assert_eq!(pronunciations(&dict, "a"), vec!["AH1", "EY1"]);
+        assert_eq!(pronunciations(&dict, "cat"), vec!["K AA1 T"]);
+
+        assert!(from_text("x.json", "[1, 2]", Format::Json, LoadMode::Lenient).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Optional metadata about dictionary entries, like parts of speech, for narrowing down
+//! suggestions, e.g. to "a noun that rhymes with light".
+//!
+//! cmudict only has pronunciations, so the metadata comes from a sidecar file next to the
+//! dictionary (`cmudict.dict.meta` for `cmudict.dict`; see `sidecar_path`), or from Datamuse.
+//! Each line of a sidecar file has a term and its tags, which are the same as Datamuse's:
+//!
+//! ```noformat
+//! light n v adj f:120.5
+//! lead(2) n
+//! ```
+//!
+//! The tags are `n`, `v`, `adj` and `adv` for the parts of speech, `u` for an unknown part of
+//! speech, and `f:` for the number of times the word occurs per million words. A term without
+//! a variant number applies to all of its pronunciations. `#` starts a comment.
+use crate::poet::dictionary::{DictionaryError, LoadMode};
+use rocket::serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+use std::sync::Arc;
+
+/// A part of speech, as tagged by Datamuse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum PartOfSpeech {
+    Noun,
+    Verb,
+    Adjective,
+    Adverb,
+}
+
+impl PartOfSpeech {
+    /// Returns the part of speech for a tag like "adj", if it is one.
+    pub fn from_tag(tag: &str) -> Option<PartOfSpeech> {
+        match tag {
+            "n" => Some(PartOfSpeech::Noun),
+            "v" => Some(PartOfSpeech::Verb),
+            "adj" => Some(PartOfSpeech::Adjective),
+            "adv" => Some(PartOfSpeech::Adverb),
+            _ => None,
+        }
+    }
+
+    /// Returns the tag for the part of speech, e.g. "adj".
+    pub fn tag(&self) -> &'static str {
+        match self {
+            PartOfSpeech::Noun => "n",
+            PartOfSpeech::Verb => "v",
+            PartOfSpeech::Adjective => "adj",
+            PartOfSpeech::Adverb => "adv",
+        }
+    }
+}
+
+impl std::str::FromStr for PartOfSpeech {
+    type Err = String;
+
+    /// Parses either the name, e.g. "adjective", or the tag, e.g. "adj".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(pos) = PartOfSpeech::from_tag(s) {
+            return Ok(pos);
+        }
+        match s {
+            "noun" => Ok(PartOfSpeech::Noun),
+            "verb" => Ok(PartOfSpeech::Verb),
+            "adjective" => Ok(PartOfSpeech::Adjective),
+            "adverb" => Ok(PartOfSpeech::Adverb),
+            _ => Err(format!("unknown part of speech: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for PartOfSpeech {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PartOfSpeech::Noun => "noun",
+            PartOfSpeech::Verb => "verb",
+            PartOfSpeech::Adjective => "adjective",
+            PartOfSpeech::Adverb => "adverb",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What's known about an `Entry` besides its pronunciation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntryMetadata {
+    /// The parts of speech the word can be, in the order they were tagged.
+    pub pos: Vec<PartOfSpeech>,
+    /// How many times the word occurs per billion words, if known.
+    pub frequency: Option<u64>,
+}
+
+impl EntryMetadata {
+    /// Adds what `tag` says about the word, and returns whether it was a metadata tag. Other
+    /// tags, like Datamuse's `pron:`, are left for the caller.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        if let Some(pos) = PartOfSpeech::from_tag(tag) {
+            if !self.pos.contains(&pos) {
+                self.pos.push(pos);
+            }
+            return true;
+        }
+        if tag == "u" {
+            return true;
+        }
+        if let Some(per_million) = tag.strip_prefix("f:") {
+            return match per_million.parse::<f64>() {
+                Ok(f) if f >= 0.0 => {
+                    self.frequency = Some((f * 1000.0).round() as u64);
+                    true
+                }
+                _ => false,
+            };
+        }
+        return false;
+    }
+
+    /// Returns whether there's no metadata at all.
+    pub fn is_empty(&self) -> bool {
+        self.pos.is_empty() && self.frequency.is_none()
+    }
+}
+
+impl fmt::Display for EntryMetadata {
+    /// Writes the metadata as tags, e.g. "n v f:120.5", like a line of a sidecar file.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut tags: Vec<String> = self.pos.iter().map(|p| p.tag().to_string()).collect();
+        if let Some(per_billion) = self.frequency {
+            tags.push(format!("f:{}", per_billion as f64 / 1000.0));
+        }
+        write!(f, "{}", tags.join(" "))
+    }
+}
+
+/// Returns the path of the sidecar metadata file for the dictionary at `dict_path`.
+pub fn sidecar_path(dict_path: &str) -> String {
+    format!("{}.meta", dict_path)
+}
+
+/// The contents of a sidecar metadata file. See the module documentation for the format.
+#[derive(Debug, Default)]
+pub struct MetadataFile {
+    /// The metadata for each term as written, e.g. "lead(2)" or "light".
+    by_term: HashMap<String, Arc<EntryMetadata>>,
+
+    /// Malformed lines skipped while loading in `LoadMode::Lenient`.
+    skipped: Vec<DictionaryError>,
+}
+
+impl MetadataFile {
+    /// Loads the sidecar file at `path`.
+    pub fn load(path: &str, mode: LoadMode) -> Result<MetadataFile, DictionaryError> {
+        let file = std::fs::File::open(path).map_err(|error| DictionaryError::Io {
+            path: path.to_string(),
+            error,
+        })?;
+        return MetadataFile::from_reader(path, std::io::BufReader::new(file), mode);
+    }
+
+    /// Reads a sidecar file from `reader`. `path` is only used in errors.
+    pub fn from_reader(
+        path: &str,
+        reader: impl BufRead,
+        mode: LoadMode,
+    ) -> Result<MetadataFile, DictionaryError> {
+        let mut out = MetadataFile::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|error| DictionaryError::Io {
+                path: path.to_string(),
+                error,
+            })?;
+            let text = line.split('#').next().unwrap_or("");
+            let mut tokens = text.split_whitespace();
+            let term = match tokens.next() {
+                Some(term) => term,
+                None => continue,
+            };
+            let tags: Vec<&str> = tokens.collect();
+            let mut metadata = EntryMetadata::default();
+            let reason = if tags.is_empty() {
+                format!("no tags for \"{}\"", term)
+            } else if let Some(tag) = tags.iter().find(|tag| !metadata.add_tag(tag)) {
+                format!("unknown tag \"{}\"", tag)
+            } else {
+                // A word tagged only "u" has nothing to record.
+                if !metadata.is_empty() {
+                    out.by_term.insert(term.to_lowercase(), Arc::new(metadata));
+                }
+                continue;
+            };
+            let error = DictionaryError::Malformed {
+                path: path.to_string(),
+                line: i + 1,
+                reason,
+            };
+            if mode == LoadMode::Strict {
+                return Err(error);
+            }
+            out.skipped.push(error);
+        }
+        return Ok(out);
+    }
+
+    /// Returns the metadata for the given pronunciation of `word`: that of its own line, e.g.
+    /// "lead(2)", if there is one, or else that of the word.
+    pub fn get(&self, word: &str, variant: i32) -> Option<&Arc<EntryMetadata>> {
+        let word = word.to_lowercase();
+        if variant != 1 {
+            if let Some(metadata) = self.by_term.get(&format!("{}({})", word, variant)) {
+                return Some(metadata);
+            }
+        }
+        return self.by_term.get(&word);
+    }
+
+    /// Returns whether any term was tagged with a frequency.
+    pub fn has_frequencies(&self) -> bool {
+        self.by_term.values().any(|m| m.frequency.is_some())
+    }
+
+    /// Returns the malformed lines that were skipped when loading the file.
+    pub fn skipped_lines(&self) -> &[DictionaryError] {
+        &self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tags() {
+        let text =
+            "# term tags\nlight n v adj f:120.5\nlead v f:80\nlead(2) n\nquickly adv u\nhmm u\n";
+        let file = MetadataFile::from_reader("test", text.as_bytes(), LoadMode::Strict).unwrap();
+        let light = file.get("light", 1).unwrap();
+        assert_eq!(
+            light.pos,
+            vec![
+                PartOfSpeech::Noun,
+                PartOfSpeech::Verb,
+                PartOfSpeech::Adjective
+            ]
+        );
+        assert_eq!(light.frequency, Some(120_500));
+        assert_eq!(light.to_string(), "n v adj f:120.5");
+        assert_eq!(file.get("lead", 1).unwrap().pos, vec![PartOfSpeech::Verb]);
+        assert_eq!(file.get("lead", 2).unwrap().pos, vec![PartOfSpeech::Noun]);
+        assert_eq!(file.get("Light", 3), Some(light));
+        assert_eq!(file.get("quickly", 1).unwrap().to_string(), "adv");
+        assert_eq!(file.get("hmm", 1), None);
+        assert_eq!(file.get("dark", 1), None);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let text = "light n\ndark noun\nblue\nred adj f:often\n";
+        let file = MetadataFile::from_reader("test", text.as_bytes(), LoadMode::Lenient).unwrap();
+        assert!(file.get("light", 1).is_some());
+        let lines: Vec<String> = file.skipped_lines().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "test:2: unknown tag \"noun\"",
+                "test:3: no tags for \"blue\"",
+                "test:4: unknown tag \"f:often\"",
+            ]
+        );
+        let error = MetadataFile::from_reader("test", text.as_bytes(), LoadMode::Strict);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "test:2: unknown tag \"noun\""
+        );
+    }
+
+    #[test]
+    fn parses_parts_of_speech() {
+        assert_eq!("adj".parse(), Ok(PartOfSpeech::Adjective));
+        assert_eq!("adverb".parse(), Ok(PartOfSpeech::Adverb));
+        assert!("pronoun".parse::<PartOfSpeech>().is_err());
+        assert_eq!(PartOfSpeech::Noun.to_string(), "noun");
+    }
+}
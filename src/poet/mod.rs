@@ -1,4 +1,16 @@
+pub mod check;
 pub mod client;
+pub mod dialect;
 pub mod dictionary;
+pub mod frequency;
+pub mod guess;
+pub mod heteronym;
+pub mod ipa;
+pub mod lexicon;
+pub mod metadata;
+pub mod pattern;
+pub mod phoneme;
 pub mod server;
 pub mod snippet;
+pub mod spelling;
+pub mod syllable;
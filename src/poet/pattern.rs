@@ -0,0 +1,352 @@
+//! A small query language for finding words by their phonemes.
+//!
+//! A pattern is a list of whitespace-separated terms, which must match the whole pronunciation
+//! in order:
+//!
+//! * `AE1`, `T`: exactly that phoneme. A vowel without a digit, e.g. `AE`, has any stress.
+//! * `?`: any one phoneme, and `*`: any run of phonemes, including none.
+//! * `vowel`, `consonant`, or a manner of articulation: `stop`, `affricate`, `fricative`,
+//!   `aspirate`, `nasal`, `liquid` or `semivowel`. A digit after `vowel` sets its stress,
+//!   e.g. `vowel1` for a vowel with primary stress.
+//!
+//! A few terms constrain the word as a whole instead, and can go anywhere in the pattern:
+//!
+//! * `syllables:2`, or a range like `syllables:2-3`, `syllables:2-` or `syllables:-3`.
+//! * `stress:10`, the stress digits of the vowels in order (see `Phonemes::stress_pattern`),
+//!   where `?` allows any stress, e.g. `stress:1?`.
+//!
+//! ```
+//! // Two-syllable words, stressed on the first, ending in "AH0 N", e.g. "button".
+//! let pattern: Pattern = "stress:10 * AH0 N".parse()?;
+//! // Anything ending in "AE1 T", e.g. "cat" and "acrobat".
+//! let pattern: Pattern = "* AE1 T".parse()?;
+//! // Words that start with a stop and have three syllables.
+//! let pattern: Pattern = "stop * syllables:3".parse()?;
+//! ```
+use crate::poet::dictionary::Phonemes;
+use crate::poet::phoneme::{Articulation, Manner, Phoneme, Stress, Symbol};
+use std::fmt;
+
+/// A group of phonemes that a pattern term can match, e.g. `nasal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Class {
+    Vowel,
+    Consonant,
+    Manner(Manner),
+}
+
+impl Class {
+    fn from_name(name: &str) -> Option<Class> {
+        let class = match name {
+            "vowel" => Class::Vowel,
+            "consonant" => Class::Consonant,
+            "stop" => Class::Manner(Manner::Stop),
+            "affricate" => Class::Manner(Manner::Affricate),
+            "fricative" => Class::Manner(Manner::Fricative),
+            "aspirate" => Class::Manner(Manner::Aspirate),
+            "nasal" => Class::Manner(Manner::Nasal),
+            "liquid" => Class::Manner(Manner::Liquid),
+            "semivowel" => Class::Manner(Manner::Semivowel),
+            _ => return None,
+        };
+        return Some(class);
+    }
+
+    fn contains(&self, phoneme: &Phoneme) -> bool {
+        match (self, phoneme.articulation()) {
+            (Class::Vowel, Articulation::Vowel { .. }) => true,
+            (Class::Consonant, Articulation::Consonant { .. }) => true,
+            (Class::Manner(m), Articulation::Consonant { manner, .. }) => *m == manner,
+            _ => false,
+        }
+    }
+}
+
+/// One term of a `Pattern`, matching phonemes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Term {
+    /// `?`
+    Any,
+    /// `*`
+    AnySequence,
+    /// e.g. `AE1` or `T`.
+    Phoneme(Phoneme),
+    /// A vowel with any stress, e.g. `AE`.
+    Symbol(Symbol),
+    /// e.g. `nasal` or `vowel1`.
+    Class(Class, Option<Stress>),
+}
+
+impl Term {
+    /// Returns whether this term matches `phoneme`. `AnySequence` is handled by the caller.
+    fn matches(&self, phoneme: &Phoneme) -> bool {
+        match self {
+            Term::Any | Term::AnySequence => true,
+            Term::Phoneme(ph) => ph == phoneme,
+            Term::Symbol(symbol) => *symbol == phoneme.symbol(),
+            Term::Class(class, stress) => {
+                class.contains(phoneme) && (stress.is_none() || *stress == phoneme.stress())
+            }
+        }
+    }
+}
+
+/// A parsed phonetic search pattern. See the module documentation for the syntax.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    terms: Vec<Term>,
+    min_syllables: Option<i32>,
+    max_syllables: Option<i32>,
+    /// The stress of each vowel in order, with None for `?`.
+    stress: Option<Vec<Option<Stress>>>,
+    /// The original text, for display.
+    text: String,
+}
+
+impl Pattern {
+    /// Returns whether the whole of `phonemes` matches the pattern.
+    pub fn matches(&self, phonemes: &Phonemes) -> bool {
+        let syllables = phonemes.num_syllables();
+        if matches!(self.min_syllables, Some(n) if syllables < n)
+            || matches!(self.max_syllables, Some(n) if syllables > n)
+        {
+            return false;
+        }
+        if let Some(stress) = &self.stress {
+            let mut vowels = phonemes.phonemes.iter().filter(|ph| ph.is_vowel());
+            let mut expected = stress.iter();
+            loop {
+                match (vowels.next(), expected.next()) {
+                    (None, None) => break,
+                    (Some(ph), Some(s)) if s.is_none() || *s == ph.stress() => continue,
+                    _ => return false,
+                }
+            }
+        }
+        return matches_terms(&self.terms, &phonemes.phonemes);
+    }
+
+    /// Returns the exact phonemes that every match ends with, e.g. "AE1 T" for "* AE1 T".
+    ///
+    /// Dictionaries use this to narrow the search with their index of word endings.
+    pub fn literal_suffix(&self) -> Vec<Phoneme> {
+        let mut suffix: Vec<Phoneme> = self
+            .terms
+            .iter()
+            .rev()
+            .map_while(|term| match term {
+                Term::Phoneme(ph) => Some(*ph),
+                _ => None,
+            })
+            .collect();
+        suffix.reverse();
+        return suffix;
+    }
+}
+
+/// Matches phonemes against terms, where `*` can stand for any run of phonemes.
+///
+/// This is the usual wildcard matcher: it remembers the most recent `*` and, on a mismatch,
+/// retries with that `*` covering one more phoneme.
+fn matches_terms(terms: &[Term], phonemes: &[Phoneme]) -> bool {
+    let (mut t, mut p) = (0, 0);
+    // The term after the most recent `*`, and the first phoneme it hasn't yet covered.
+    let mut retry: Option<(usize, usize)> = None;
+    while p < phonemes.len() {
+        if t < terms.len() && terms[t] == Term::AnySequence {
+            t += 1;
+            retry = Some((t, p));
+        } else if t < terms.len() && terms[t].matches(&phonemes[p]) {
+            t += 1;
+            p += 1;
+        } else if let Some((retry_t, retry_p)) = retry {
+            t = retry_t;
+            p = retry_p + 1;
+            retry = Some((retry_t, p));
+        } else {
+            return false;
+        }
+    }
+    return terms[t..].iter().all(|term| *term == Term::AnySequence);
+}
+
+/// Parses e.g. "2", "2-3", "2-" or "-3" into inclusive bounds.
+fn parse_range(value: &str) -> Option<(Option<i32>, Option<i32>)> {
+    let bound = |s: &str| -> Option<Option<i32>> {
+        match s {
+            "" => Some(None),
+            _ => s.parse().ok().map(Some),
+        }
+    };
+    match value.split_once('-') {
+        Some((min, max)) => Some((bound(min)?, bound(max)?)),
+        None => {
+            let n = value.parse().ok()?;
+            Some((Some(n), Some(n)))
+        }
+    }
+}
+
+fn parse_stress(value: &str) -> Option<Vec<Option<Stress>>> {
+    value
+        .chars()
+        .map(|c| match c {
+            '0' => Some(Some(Stress::Unstressed)),
+            '1' => Some(Some(Stress::Primary)),
+            '2' => Some(Some(Stress::Secondary)),
+            '?' => Some(None),
+            _ => None,
+        })
+        .collect()
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pattern = Pattern {
+            terms: vec![],
+            min_syllables: None,
+            max_syllables: None,
+            stress: None,
+            text: s.split_whitespace().collect::<Vec<_>>().join(" "),
+        };
+        if pattern.text.is_empty() {
+            return Err("empty pattern".to_string());
+        }
+        for token in s.split_whitespace() {
+            if let Some((name, value)) = token.split_once(':') {
+                match name {
+                    "syllables" => {
+                        let (min, max) = parse_range(value)
+                            .ok_or_else(|| format!("invalid syllable count \"{}\"", value))?;
+                        pattern.min_syllables = min;
+                        pattern.max_syllables = max;
+                    }
+                    "stress" => {
+                        pattern.stress = Some(
+                            parse_stress(value)
+                                .ok_or_else(|| format!("invalid stress pattern \"{}\"", value))?,
+                        );
+                    }
+                    _ => return Err(format!("unknown constraint \"{}\"", token)),
+                }
+                continue;
+            }
+            let term = match token {
+                "?" => Term::Any,
+                "*" => Term::AnySequence,
+                _ if token.starts_with(|c: char| c.is_ascii_lowercase()) => {
+                    let name = token.trim_end_matches(|c: char| c.is_ascii_digit());
+                    let class = Class::from_name(name)
+                        .ok_or_else(|| format!("unknown class \"{}\"", name))?;
+                    let stress = match &token[name.len()..] {
+                        "" => None,
+                        _ if class != Class::Vowel => {
+                            return Err(format!("only vowels have stress: \"{}\"", token))
+                        }
+                        "0" => Some(Stress::Unstressed),
+                        "1" => Some(Stress::Primary),
+                        "2" => Some(Stress::Secondary),
+                        _ => return Err(format!("unknown stress in \"{}\"", token)),
+                    };
+                    Term::Class(class, stress)
+                }
+                _ => match Symbol::from_name(token) {
+                    Some(symbol) if symbol.is_vowel() => Term::Symbol(symbol),
+                    _ => Term::Phoneme(token.parse()?),
+                },
+            };
+            pattern.terms.push(term);
+        }
+        if pattern.terms.is_empty() {
+            // Only constraints, e.g. "syllables:3", so any phonemes will do.
+            pattern.terms.push(Term::AnySequence);
+        }
+        return Ok(pattern);
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Entry;
+
+    fn matches(pattern: &str, entry: &str) -> bool {
+        let pattern: Pattern = pattern.parse().unwrap();
+        pattern.matches(&Entry::from_parts("word", entry).unwrap().phonemes)
+    }
+
+    #[test]
+    fn matches_phonemes_and_wildcards() {
+        assert!(matches("* AE1 T", "K AE1 T"));
+        assert!(!matches("* AE1 T", "AE1 K R AH0 B AE2 T"));
+        assert!(matches("* AE T", "AE1 K R AH0 B AE2 T"));
+        assert!(matches("K ? T", "K AE1 T"));
+        assert!(!matches("K ? T", "K AE1 S T"));
+        assert!(matches("K * T", "K AE1 S T"));
+        assert!(matches("* S * T *", "K AE1 S T"));
+        assert!(matches("*", "K AE1 T"));
+        assert!(!matches("AE1 T", "K AE1 T"));
+    }
+
+    #[test]
+    fn matches_classes() {
+        assert!(matches("stop vowel nasal", "K AE1 N"));
+        assert!(!matches("stop vowel nasal", "K AE1 T"));
+        assert!(matches("consonant vowel1 *", "K AE1 N"));
+        assert!(!matches("consonant vowel0 *", "K AE1 N"));
+        assert!(matches("* fricative", "B AH1 Z"));
+        assert!(matches("semivowel *", "W IH1 N D"));
+    }
+
+    #[test]
+    fn matches_syllables_and_stress() {
+        let button = "B AH1 T AH0 N";
+        assert!(matches("stress:10 * AH0 N", button));
+        assert!(matches("syllables:2 * AH0 N", button));
+        assert!(matches("syllables:1-2", button));
+        assert!(matches("syllables:2-", button));
+        assert!(!matches("syllables:-1", button));
+        assert!(matches("stress:1?", button));
+        assert!(!matches("stress:01", button));
+        assert!(!matches("stress:1", button));
+    }
+
+    #[test]
+    fn literal_suffix() {
+        let suffix = |p: &str| {
+            let pattern: Pattern = p.parse().unwrap();
+            pattern
+                .literal_suffix()
+                .iter()
+                .map(|ph| ph.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(suffix("* AE1 T"), "AE1 T");
+        assert_eq!(suffix("* AE T"), "T");
+        assert_eq!(suffix("K AE1 T *"), "");
+        assert_eq!(suffix("syllables:2"), "");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |p: &str| p.parse::<Pattern>().unwrap_err();
+        assert_eq!(err("  "), "empty pattern");
+        assert_eq!(err("* XX"), "unknown phoneme \"XX\"");
+        assert_eq!(err("* AE3"), "unknown stress in \"AE3\"");
+        assert_eq!(err("plosive *"), "unknown class \"plosive\"");
+        assert_eq!(err("nasal1"), "only vowels have stress: \"nasal1\"");
+        assert_eq!(err("vowel3"), "unknown stress in \"vowel3\"");
+        assert_eq!(err("syllables:x"), "invalid syllable count \"x\"");
+        assert_eq!(err("stress:1x"), "invalid stress pattern \"1x\"");
+        assert_eq!(err("rhyme:cat"), "unknown constraint \"rhyme:cat\"");
+    }
+}
@@ -0,0 +1,682 @@
+//! Typed ARPABET phonemes.
+//!
+//! cmudict spells each sound as one of 39 ARPABET symbols, and vowels also carry a stress
+//! digit, e.g. "AH0", "EY1" or "SH". A `Phoneme` stores the symbol and stress in two bytes,
+//! rejects anything that isn't in the inventory, and knows how the sound is made (its
+//! articulatory features), so that rhyme and similarity measures don't need to re-parse strings.
+//!
+//! ```
+//! let ph: Phoneme = "EY1".parse()?;
+//! assert!(ph.is_vowel());
+//! assert_eq!(ph.stress(), Some(Stress::Primary));
+//! ```
+//!
+//! Related references:
+//!
+//! * <https://en.wikipedia.org/wiki/ARPABET>
+//! * <https://github.com/cmusphinx/cmudict/blob/master/cmudict.phones>
+use rocket::serde::{Serialize, Serializer};
+use std::fmt;
+
+/// The 39 ARPABET symbols used by cmudict, without stress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Symbol {
+    AA,
+    AE,
+    AH,
+    AO,
+    AW,
+    AY,
+    B,
+    CH,
+    D,
+    DH,
+    EH,
+    ER,
+    EY,
+    F,
+    G,
+    HH,
+    IH,
+    IY,
+    JH,
+    K,
+    L,
+    M,
+    N,
+    NG,
+    OW,
+    OY,
+    P,
+    R,
+    S,
+    SH,
+    T,
+    TH,
+    UH,
+    UW,
+    V,
+    W,
+    Y,
+    Z,
+    ZH,
+}
+
+/// The stress of a vowel, written as the digit after it in cmudict.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Stress {
+    Unstressed = 0,
+    Primary = 1,
+    Secondary = 2,
+}
+
+/// How a consonant is made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Manner {
+    Stop,
+    Affricate,
+    Fricative,
+    Aspirate,
+    Nasal,
+    Liquid,
+    Semivowel,
+}
+
+/// Where in the mouth a consonant is made, from the lips back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Place {
+    Bilabial,
+    Labiodental,
+    Dental,
+    Alveolar,
+    Postalveolar,
+    Palatal,
+    Velar,
+    Glottal,
+}
+
+/// How high the tongue is for a vowel (for diphthongs, where it starts).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Height {
+    High,
+    Mid,
+    Low,
+}
+
+/// How far back the tongue is for a vowel (for diphthongs, where it starts).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Backness {
+    Front,
+    Central,
+    Back,
+}
+
+/// The articulatory features of a symbol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Articulation {
+    Vowel {
+        height: Height,
+        backness: Backness,
+        rounded: bool,
+        diphthong: bool,
+    },
+    Consonant {
+        manner: Manner,
+        place: Place,
+        voiced: bool,
+    },
+}
+
+impl Symbol {
+    /// Every symbol, in order.
+    pub const ALL: [Symbol; 39] = [
+        Symbol::AA,
+        Symbol::AE,
+        Symbol::AH,
+        Symbol::AO,
+        Symbol::AW,
+        Symbol::AY,
+        Symbol::B,
+        Symbol::CH,
+        Symbol::D,
+        Symbol::DH,
+        Symbol::EH,
+        Symbol::ER,
+        Symbol::EY,
+        Symbol::F,
+        Symbol::G,
+        Symbol::HH,
+        Symbol::IH,
+        Symbol::IY,
+        Symbol::JH,
+        Symbol::K,
+        Symbol::L,
+        Symbol::M,
+        Symbol::N,
+        Symbol::NG,
+        Symbol::OW,
+        Symbol::OY,
+        Symbol::P,
+        Symbol::R,
+        Symbol::S,
+        Symbol::SH,
+        Symbol::T,
+        Symbol::TH,
+        Symbol::UH,
+        Symbol::UW,
+        Symbol::V,
+        Symbol::W,
+        Symbol::Y,
+        Symbol::Z,
+        Symbol::ZH,
+    ];
+
+    /// Returns the symbol for e.g. "SH", or None if it isn't an ARPABET symbol.
+    pub fn from_name(s: &str) -> Option<Symbol> {
+        let symbol = match s {
+            "AA" => Symbol::AA,
+            "AE" => Symbol::AE,
+            "AH" => Symbol::AH,
+            "AO" => Symbol::AO,
+            "AW" => Symbol::AW,
+            "AY" => Symbol::AY,
+            "B" => Symbol::B,
+            "CH" => Symbol::CH,
+            "D" => Symbol::D,
+            "DH" => Symbol::DH,
+            "EH" => Symbol::EH,
+            "ER" => Symbol::ER,
+            "EY" => Symbol::EY,
+            "F" => Symbol::F,
+            "G" => Symbol::G,
+            "HH" => Symbol::HH,
+            "IH" => Symbol::IH,
+            "IY" => Symbol::IY,
+            "JH" => Symbol::JH,
+            "K" => Symbol::K,
+            "L" => Symbol::L,
+            "M" => Symbol::M,
+            "N" => Symbol::N,
+            "NG" => Symbol::NG,
+            "OW" => Symbol::OW,
+            "OY" => Symbol::OY,
+            "P" => Symbol::P,
+            "R" => Symbol::R,
+            "S" => Symbol::S,
+            "SH" => Symbol::SH,
+            "T" => Symbol::T,
+            "TH" => Symbol::TH,
+            "UH" => Symbol::UH,
+            "UW" => Symbol::UW,
+            "V" => Symbol::V,
+            "W" => Symbol::W,
+            "Y" => Symbol::Y,
+            "Z" => Symbol::Z,
+            "ZH" => Symbol::ZH,
+            _ => return None,
+        };
+        return Some(symbol);
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Symbol::AA => "AA",
+            Symbol::AE => "AE",
+            Symbol::AH => "AH",
+            Symbol::AO => "AO",
+            Symbol::AW => "AW",
+            Symbol::AY => "AY",
+            Symbol::B => "B",
+            Symbol::CH => "CH",
+            Symbol::D => "D",
+            Symbol::DH => "DH",
+            Symbol::EH => "EH",
+            Symbol::ER => "ER",
+            Symbol::EY => "EY",
+            Symbol::F => "F",
+            Symbol::G => "G",
+            Symbol::HH => "HH",
+            Symbol::IH => "IH",
+            Symbol::IY => "IY",
+            Symbol::JH => "JH",
+            Symbol::K => "K",
+            Symbol::L => "L",
+            Symbol::M => "M",
+            Symbol::N => "N",
+            Symbol::NG => "NG",
+            Symbol::OW => "OW",
+            Symbol::OY => "OY",
+            Symbol::P => "P",
+            Symbol::R => "R",
+            Symbol::S => "S",
+            Symbol::SH => "SH",
+            Symbol::T => "T",
+            Symbol::TH => "TH",
+            Symbol::UH => "UH",
+            Symbol::UW => "UW",
+            Symbol::V => "V",
+            Symbol::W => "W",
+            Symbol::Y => "Y",
+            Symbol::Z => "Z",
+            Symbol::ZH => "ZH",
+        }
+    }
+
+    /// Returns how the sound is made. The classes follow cmudict.phones.
+    pub fn articulation(&self) -> Articulation {
+        use Backness::*;
+        use Height::*;
+        use Manner::*;
+        use Place::*;
+        let vowel = |height, backness, rounded, diphthong| Articulation::Vowel {
+            height,
+            backness,
+            rounded,
+            diphthong,
+        };
+        let consonant = |manner, place, voiced| Articulation::Consonant {
+            manner,
+            place,
+            voiced,
+        };
+        match self {
+            Symbol::AA => vowel(Low, Back, false, false),
+            Symbol::AE => vowel(Low, Front, false, false),
+            Symbol::AH => vowel(Mid, Central, false, false),
+            Symbol::AO => vowel(Mid, Back, true, false),
+            Symbol::AW => vowel(Low, Central, false, true),
+            Symbol::AY => vowel(Low, Central, false, true),
+            Symbol::EH => vowel(Mid, Front, false, false),
+            Symbol::ER => vowel(Mid, Central, false, false),
+            Symbol::EY => vowel(Mid, Front, false, true),
+            Symbol::IH => vowel(High, Front, false, false),
+            Symbol::IY => vowel(High, Front, false, false),
+            Symbol::OW => vowel(Mid, Back, true, true),
+            Symbol::OY => vowel(Mid, Back, true, true),
+            Symbol::UH => vowel(High, Back, true, false),
+            Symbol::UW => vowel(High, Back, true, false),
+            Symbol::B => consonant(Stop, Bilabial, true),
+            Symbol::CH => consonant(Affricate, Postalveolar, false),
+            Symbol::D => consonant(Stop, Alveolar, true),
+            Symbol::DH => consonant(Fricative, Dental, true),
+            Symbol::F => consonant(Fricative, Labiodental, false),
+            Symbol::G => consonant(Stop, Velar, true),
+            Symbol::HH => consonant(Aspirate, Glottal, false),
+            Symbol::JH => consonant(Affricate, Postalveolar, true),
+            Symbol::K => consonant(Stop, Velar, false),
+            Symbol::L => consonant(Liquid, Alveolar, true),
+            Symbol::M => consonant(Nasal, Bilabial, true),
+            Symbol::N => consonant(Nasal, Alveolar, true),
+            Symbol::NG => consonant(Nasal, Velar, true),
+            Symbol::P => consonant(Stop, Bilabial, false),
+            Symbol::R => consonant(Liquid, Alveolar, true),
+            Symbol::S => consonant(Fricative, Alveolar, false),
+            Symbol::SH => consonant(Fricative, Postalveolar, false),
+            Symbol::T => consonant(Stop, Alveolar, false),
+            Symbol::TH => consonant(Fricative, Dental, false),
+            Symbol::V => consonant(Fricative, Labiodental, true),
+            Symbol::W => consonant(Semivowel, Bilabial, true),
+            Symbol::Y => consonant(Semivowel, Palatal, true),
+            Symbol::Z => consonant(Fricative, Alveolar, true),
+            Symbol::ZH => consonant(Fricative, Postalveolar, true),
+        }
+    }
+
+    pub fn is_vowel(&self) -> bool {
+        matches!(self.articulation(), Articulation::Vowel { .. })
+    }
+
+    /// Returns how different two sounds are, from 0 for the same symbol up to `MAX_DISTANCE`.
+    ///
+    /// The cost adds up the features that differ. Vowels differ by height and backness (two per
+    /// step), rounding and gliding; consonants by voicing (2), manner (4) and place (one per step
+    /// from the lips back, up to 3). So "P" and "K" are 3 apart, "P" and "B" 2, and "P" and "S"
+    /// 7. Distinct symbols with the same features, e.g. "IH" and "IY", are 1 apart, and a vowel
+    /// and a consonant are as far apart as possible.
+    pub fn distance(&self, other: &Symbol) -> u32 {
+        if self == other {
+            return 0;
+        }
+        let cost = match (self.articulation(), other.articulation()) {
+            (
+                Articulation::Vowel {
+                    height: h1,
+                    backness: b1,
+                    rounded: r1,
+                    diphthong: d1,
+                },
+                Articulation::Vowel {
+                    height: h2,
+                    backness: b2,
+                    rounded: r2,
+                    diphthong: d2,
+                },
+            ) => {
+                2 * (h1 as u32).abs_diff(h2 as u32)
+                    + 2 * (b1 as u32).abs_diff(b2 as u32)
+                    + (r1 != r2) as u32
+                    + (d1 != d2) as u32
+            }
+            (
+                Articulation::Consonant {
+                    manner: m1,
+                    place: p1,
+                    voiced: v1,
+                },
+                Articulation::Consonant {
+                    manner: m2,
+                    place: p2,
+                    voiced: v2,
+                },
+            ) => {
+                2 * (v1 != v2) as u32
+                    + 4 * (m1 != m2) as u32
+                    + (p1 as u32).abs_diff(p2 as u32).min(3)
+            }
+            _ => MAX_DISTANCE,
+        };
+        return cost.max(1);
+    }
+}
+
+/// The largest `Symbol::distance`, between a vowel and a consonant.
+pub const MAX_DISTANCE: u32 = 10;
+
+/// One sound in a pronunciation, e.g. "AH0" or "SH".
+///
+/// Vowels always have a stress and consonants never do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Phoneme {
+    symbol: Symbol,
+    stress: Option<Stress>,
+}
+
+impl Phoneme {
+    pub fn symbol(&self) -> Symbol {
+        self.symbol
+    }
+
+    /// Returns the stress of a vowel, or None for a consonant.
+    pub fn stress(&self) -> Option<Stress> {
+        self.stress
+    }
+
+    pub fn is_vowel(&self) -> bool {
+        self.stress.is_some()
+    }
+
+    /// Returns whether this is a vowel with primary or secondary stress.
+    pub fn is_stressed(&self) -> bool {
+        matches!(self.stress, Some(Stress::Primary) | Some(Stress::Secondary))
+    }
+
+    pub fn articulation(&self) -> Articulation {
+        self.symbol.articulation()
+    }
+
+    /// Returns a copy with the given stress. Consonants are returned unchanged.
+    pub fn with_stress(&self, stress: Stress) -> Phoneme {
+        if !self.is_vowel() {
+            return *self;
+        }
+        Phoneme {
+            symbol: self.symbol,
+            stress: Some(stress),
+        }
+    }
+
+    /// Returns a copy of a vowel with another vowel's symbol, keeping the stress, e.g. "AA1"
+    /// for "AE1". Consonants are returned unchanged, as is a vowel given a consonant.
+    pub fn with_symbol(&self, symbol: Symbol) -> Phoneme {
+        if !self.is_vowel() || !symbol.is_vowel() {
+            return *self;
+        }
+        Phoneme {
+            symbol,
+            stress: self.stress,
+        }
+    }
+
+    /// Returns the phoneme as it sounds in a quick, unstressed syllable, e.g. "it" in "know it":
+    /// the short vowels IH, EH, AE, UH and AH become a schwa (AH0), and other vowels lose
+    /// their stress. Consonants are returned unchanged.
+    pub fn reduced(&self) -> Phoneme {
+        match self.symbol {
+            Symbol::IH | Symbol::EH | Symbol::AE | Symbol::UH | Symbol::AH => Phoneme {
+                symbol: Symbol::AH,
+                stress: Some(Stress::Unstressed),
+            },
+            _ => self.with_stress(Stress::Unstressed),
+        }
+    }
+
+    /// Returns a one-byte code for the phoneme, used by the compiled dictionary index. The
+    /// symbol is in the low six bits and the stress (none, 0, 1 or 2) in the top two.
+    pub fn to_byte(&self) -> u8 {
+        let stress = match self.stress {
+            None => 0,
+            Some(stress) => stress as u8 + 1,
+        };
+        return (self.symbol as u8) | (stress << 6);
+    }
+
+    /// The inverse of `to_byte()`, or None if the byte isn't a valid phoneme.
+    pub fn from_byte(byte: u8) -> Option<Phoneme> {
+        let symbol = *Symbol::ALL.get((byte & 0x3f) as usize)?;
+        let stress = match byte >> 6 {
+            0 => None,
+            1 => Some(Stress::Unstressed),
+            2 => Some(Stress::Primary),
+            _ => Some(Stress::Secondary),
+        };
+        if symbol.is_vowel() != stress.is_some() {
+            return None;
+        }
+        return Some(Phoneme { symbol, stress });
+    }
+}
+
+impl std::str::FromStr for Phoneme {
+    type Err = String;
+
+    /// Parses a cmudict phoneme like "AH0" or "SH".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = &s[name.len()..];
+        let symbol = match Symbol::from_name(name) {
+            Some(symbol) => symbol,
+            None => return Err(format!("unknown phoneme \"{}\"", s)),
+        };
+        let stress = match digits {
+            "" => None,
+            "0" => Some(Stress::Unstressed),
+            "1" => Some(Stress::Primary),
+            "2" => Some(Stress::Secondary),
+            _ => return Err(format!("unknown stress in \"{}\"", s)),
+        };
+        match (symbol.is_vowel(), stress) {
+            (true, None) => Err(format!("vowel \"{}\" needs a stress digit (0, 1 or 2)", s)),
+            (false, Some(_)) => Err(format!("consonant \"{}\" can't have a stress digit", s)),
+            _ => Ok(Phoneme { symbol, stress }),
+        }
+    }
+}
+
+impl fmt::Display for Phoneme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.stress {
+            Some(stress) => write!(f, "{}{}", self.symbol.as_str(), stress as u8),
+            None => write!(f, "{}", self.symbol.as_str()),
+        }
+    }
+}
+
+/// Parses whitespace-separated phonemes typed by a person, e.g. "k r" or "AE1 T".
+pub fn parse_sequence(text: &str) -> Result<Vec<Phoneme>, String> {
+    text.to_uppercase()
+        .split_whitespace()
+        .map(|ph| ph.parse())
+        .collect()
+}
+
+/// Allows comparisons like `phoneme == "AH0"`, mostly for tests.
+impl PartialEq<&str> for Phoneme {
+    fn eq(&self, other: &&str) -> bool {
+        match other.parse::<Phoneme>() {
+            Ok(ph) => *self == ph,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Serialized as the cmudict text, e.g. "AH0".
+impl Serialize for Phoneme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for symbol in Symbol::ALL {
+            let text = if symbol.is_vowel() {
+                format!("{}1", symbol.as_str())
+            } else {
+                symbol.as_str().to_string()
+            };
+            let ph: Phoneme = text.parse().unwrap();
+            assert_eq!(ph.symbol(), symbol);
+            assert_eq!(ph.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_symbols_and_stress() {
+        assert_eq!(
+            "PP".parse::<Phoneme>().unwrap_err(),
+            "unknown phoneme \"PP\""
+        );
+        assert_eq!(
+            "ah0".parse::<Phoneme>().unwrap_err(),
+            "unknown phoneme \"ah0\""
+        );
+        assert_eq!(
+            "AH3".parse::<Phoneme>().unwrap_err(),
+            "unknown stress in \"AH3\""
+        );
+        assert_eq!(
+            "EH".parse::<Phoneme>().unwrap_err(),
+            "vowel \"EH\" needs a stress digit (0, 1 or 2)"
+        );
+        assert_eq!(
+            "NG1".parse::<Phoneme>().unwrap_err(),
+            "consonant \"NG1\" can't have a stress digit"
+        );
+    }
+
+    #[test]
+    fn inventory_matches_cmudict() {
+        let vowels = Symbol::ALL.iter().filter(|s| s.is_vowel()).count();
+        assert_eq!(vowels, 15);
+        assert_eq!(Symbol::ALL.len() - vowels, 24);
+        for (i, symbol) in Symbol::ALL.iter().enumerate() {
+            assert_eq!(*symbol as usize, i);
+            assert_eq!(Symbol::from_name(symbol.as_str()), Some(*symbol));
+        }
+    }
+
+    #[test]
+    fn features() {
+        let ph = |s: &str| s.parse::<Phoneme>().unwrap();
+        let voiced = |s: &str| match ph(s).articulation() {
+            Articulation::Vowel { .. } => true,
+            Articulation::Consonant { voiced, .. } => voiced,
+        };
+        assert!(voiced("Z"));
+        assert!(!voiced("S"));
+        assert!(voiced("AH0"));
+        assert!(ph("EY1").is_stressed());
+        assert!(!ph("AH0").is_stressed());
+        assert_eq!(
+            ph("SH").articulation(),
+            Articulation::Consonant {
+                manner: Manner::Fricative,
+                place: Place::Postalveolar,
+                voiced: false,
+            }
+        );
+        assert!(matches!(
+            ph("OY1").articulation(),
+            Articulation::Vowel {
+                diphthong: true,
+                rounded: true,
+                ..
+            }
+        ));
+        assert_eq!(ph("AE1").with_stress(Stress::Secondary), ph("AE2"));
+        assert_eq!(ph("T").with_stress(Stress::Primary), ph("T"));
+        assert_eq!(ph("AE1").with_symbol(Symbol::AA), ph("AA1"));
+        assert_eq!(ph("AE1").with_symbol(Symbol::T), ph("AE1"));
+        assert_eq!(ph("IH1").reduced(), ph("AH0"));
+        assert_eq!(ph("OW1").reduced(), ph("OW0"));
+        assert_eq!(ph("T").reduced(), ph("T"));
+        assert_eq!(std::mem::size_of::<Phoneme>(), 2);
+    }
+
+    #[test]
+    fn distance_follows_features() {
+        let d = |a: &str, b: &str| {
+            let a = Symbol::from_name(a).unwrap();
+            let b = Symbol::from_name(b).unwrap();
+            assert_eq!(a.distance(&b), b.distance(&a));
+            a.distance(&b)
+        };
+        assert_eq!(d("P", "P"), 0);
+        assert_eq!(d("IH", "IY"), 1);
+        assert_eq!(d("P", "B"), 2);
+        assert_eq!(d("P", "K"), 3);
+        assert_eq!(d("P", "S"), 7);
+        assert_eq!(d("AE", "AA"), 4);
+        assert_eq!(d("AE", "UW"), 9);
+        assert_eq!(d("AE", "T"), MAX_DISTANCE);
+        for a in Symbol::ALL {
+            for b in Symbol::ALL {
+                assert!(a.distance(&b) <= MAX_DISTANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_codes_round_trip() {
+        for text in ["AA0", "AE1", "ZH", "UW2", "B"] {
+            let ph: Phoneme = text.parse().unwrap();
+            assert_eq!(Phoneme::from_byte(ph.to_byte()), Some(ph));
+        }
+        // A consonant with stress, a vowel without, and a symbol past the end.
+        assert_eq!(Phoneme::from_byte(Symbol::B as u8 | (1 << 6)), None);
+        assert_eq!(Phoneme::from_byte(Symbol::AA as u8), None);
+        assert_eq!(Phoneme::from_byte(39), None);
+    }
+
+    #[test]
+    fn parses_sequences() {
+        let sequence = parse_sequence(" k  ae1 T").unwrap();
+        assert_eq!(sequence, vec!["K", "AE1", "T"]);
+        assert!(parse_sequence("").unwrap().is_empty());
+        assert_eq!(parse_sequence("K X").unwrap_err(), "unknown phoneme \"X\"");
+    }
+
+    #[test]
+    fn compares_with_text() {
+        let ph: Phoneme = "AH0".parse().unwrap();
+        assert!(ph == "AH0");
+        assert!(ph != "AH1");
+        assert!(ph != "bogus");
+    }
+}
@@ -1,17 +1,58 @@
 //! HTTP server components for poet.
 
 use rocket::form::Form;
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::content::RawHtml;
 use rocket::serde::Serialize;
 use rocket::State;
+use rocket_dyn_templates::tera::escape_html;
 use rocket_dyn_templates::Template;
 use std::collections::HashMap;
 use std::sync::Mutex;
 
+use crate::poet::dialect::Dialect;
+use crate::poet::frequency::FrequencyBand;
+use crate::poet::ipa::Notation;
+use crate::poet::metadata::PartOfSpeech;
 use crate::poet::*;
 
 /// A container for data owned by web server that's available for all requests.
 struct ServerState {
     shelf: Mutex<dictionary::Shelf>,
+    /// The bearer token required to edit the user dictionary. Editing is disabled if None.
+    edit_token: Option<String>,
+    /// The dialect to analyze text in, unless the request picks another.
+    dialect: Dialect,
+}
+
+impl ServerState {
+    /// Returns the dialect named in a request, or the default if it's missing or unknown.
+    fn dialect_or_default(&self, name: Option<&str>) -> Dialect {
+        name.and_then(|d| d.parse().ok()).unwrap_or(self.dialect)
+    }
+
+    /// Checks that `provided` matches the edit token, returning the status and reason if not.
+    fn check_edit_token(&self, provided: Option<&str>) -> Result<(), (Status, &'static str)> {
+        match &self.edit_token {
+            None => Err((Status::Forbidden, "editing is disabled")),
+            Some(expected) if tokens_match(provided.unwrap_or(""), expected) => Ok(()),
+            Some(_) => Err((Status::Unauthorized, "missing or invalid token")),
+        }
+    }
+}
+
+/// Compares two tokens in time that depends only on their lengths, so that a guess can't be
+/// refined one byte at a time.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    if provided.len() != expected.len() || expected.is_empty() {
+        return false;
+    }
+    let diff = provided
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    return diff == 0;
 }
 
 /// A template-oriented version of SimilarWord.
@@ -27,8 +68,189 @@ struct SimilarWordTemplateData {
     /// Larger scores represent higher similarity.
     score: i32,
 
-    /// Pre-serialized phoneme sequence, e.g. "HH AH0 L OW1".
+    /// Pre-serialized phoneme sequence, by syllable, e.g. "HH AH0 · L OW1" or "həˈloʊ".
     phonemes: String,
+
+    /// How the word rhymes with the query, e.g. "perfect".
+    kind: dictionary::RhymeKind,
+
+    /// How common the word is, if there's a frequency list.
+    frequency: Option<FrequencyBand>,
+
+    /// Pre-serialized parts of speech, e.g. "noun, verb". Empty if they aren't known.
+    pos: String,
+}
+
+impl SimilarWordTemplateData {
+    /// Converts `word`, writing its phonemes in `notation` with the syllables set apart.
+    fn new(word: dictionary::SimilarWord, notation: Notation) -> Self {
+        SimilarWordTemplateData {
+            word: word.word,
+            syllables: word.syllables,
+            score: word.score,
+            phonemes: notation.format_syllables(&word.phonemes),
+            kind: word.kind,
+            frequency: word.frequency.map(FrequencyBand::of),
+            pos: join_parts_of_speech(&word.pos),
+        }
+    }
+}
+
+/// Returns the parts of speech as a list for display, e.g. "noun, verb".
+fn join_parts_of_speech(pos: &[PartOfSpeech]) -> String {
+    let names: Vec<String> = pos.iter().map(|p| p.to_string()).collect();
+    return names.join(", ");
+}
+
+/// Query parameters for filtering, sorting and paging the similar words on `/lookup` and
+/// `/api/lookup`. All of them are optional.
+///
+/// ```raw
+/// /lookup?term=orange&min_syllables=2&kind=perfect&sort=word&offset=100&limit=50
+/// ```
+#[derive(Debug, Default, FromForm, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct LookupOptions {
+    /// The minimum number of syllables, inclusive.
+    min_syllables: Option<i32>,
+
+    /// The maximum number of syllables, inclusive.
+    max_syllables: Option<i32>,
+
+    /// The minimum similarity score, inclusive.
+    min_score: Option<i32>,
+
+    /// One of "identical", "perfect" or "partial". See `dictionary::RhymeKind`.
+    kind: Option<String>,
+
+    /// A stress pattern like "10" that the words must match exactly.
+    stress: Option<String>,
+
+    /// One of "common", "uncommon" or "rare". See `frequency::FrequencyBand`.
+    frequency: Option<String>,
+
+    /// A part of speech like "noun" or "adj". See `metadata::PartOfSpeech`.
+    pos: Option<String>,
+
+    /// One of "score" (the default), "word", "syllables" or "frequency".
+    sort: Option<String>,
+
+    /// The number of words to skip in each group, for paging.
+    offset: Option<usize>,
+
+    /// The maximum number of words to return in each group.
+    limit: Option<usize>,
+
+    /// The original name of `limit`, still accepted for old links.
+    num: Option<usize>,
+
+    /// The number of phonemes the alliterating words must share with the start of the word.
+    /// By default, it's the consonants before the first vowel.
+    alliterate: Option<usize>,
+
+    /// How to write pronunciations: "arpabet" (the default) or "ipa".
+    notation: Option<String>,
+}
+
+impl LookupOptions {
+    /// Returns the filtering criteria. Empty and unparseable values are ignored.
+    fn to_filter(&self) -> dictionary::SimilarFilter {
+        dictionary::SimilarFilter {
+            min_syllables: self.min_syllables,
+            max_syllables: self.max_syllables,
+            min_score: self.min_score,
+            kind: self.kind.as_ref().and_then(|k| k.parse().ok()),
+            stress: self.stress.clone().filter(|s| !s.is_empty()),
+            frequency: self.frequency.as_ref().and_then(|f| f.parse().ok()),
+            pos: self.pos.as_ref().and_then(|p| p.parse().ok()),
+        }
+    }
+
+    /// Returns the requested sort order, or the default.
+    fn order(&self) -> dictionary::SimilarOrder {
+        self.sort
+            .as_ref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns the requested notation, or the default.
+    fn notation(&self) -> Notation {
+        self.notation
+            .as_ref()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns how much of the word the alliterating words must share.
+    fn alliteration(&self) -> dictionary::Alliteration {
+        match self.alliterate {
+            Some(n) if n > 0 => dictionary::Alliteration::Phonemes(n),
+            _ => dictionary::Alliteration::Onset,
+        }
+    }
+
+    /// Returns `limit`, falling back to `num` and then to `default`. It's at least 1, so that
+    /// paging always advances.
+    fn limit_or(&self, default: usize) -> usize {
+        self.limit.or(self.num).unwrap_or(default).max(1)
+    }
+
+    /// Builds a `/lookup` URL for `term` with these options, replacing the sort and offset.
+    fn to_url(&self, term: &str, sort: &str, offset: usize) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("term", term);
+        let numbers = [
+            ("min_syllables", self.min_syllables),
+            ("max_syllables", self.max_syllables),
+            ("min_score", self.min_score),
+        ];
+        for (name, value) in numbers {
+            if let Some(n) = value {
+                query.append_pair(name, &n.to_string());
+            }
+        }
+        for (name, value) in [
+            ("kind", &self.kind),
+            ("stress", &self.stress),
+            ("frequency", &self.frequency),
+            ("pos", &self.pos),
+            ("notation", &self.notation),
+        ] {
+            if let Some(v) = value {
+                query.append_pair(name, v);
+            }
+        }
+        if let Some(n) = self.alliterate {
+            query.append_pair("alliterate", &n.to_string());
+        }
+        query.append_pair("sort", sort);
+        query.append_pair("offset", &offset.to_string());
+        query.append_pair("limit", &self.limit_or(DEFAULT_LOOKUP_LIMIT).to_string());
+        format!("/lookup?{}", query.finish())
+    }
+}
+
+/// The number of similar words to show per pronunciation on the lookup page, by default.
+const DEFAULT_LOOKUP_LIMIT: usize = 500;
+
+/// The number of near rhymes to show per pronunciation on the lookup page.
+const NEAR_RHYME_LIMIT: usize = 50;
+
+/// The number of alliterating words to show per pronunciation on the lookup page.
+const ALLITERATION_LIMIT: usize = 50;
+
+/// The number of mosaic rhymes to show per pronunciation on the lookup page.
+const MOSAIC_RHYME_LIMIT: usize = 20;
+
+/// Links for sorting the lookup results by each of the columns.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct SortUrls {
+    score: String,
+    word: String,
+    syllables: String,
+    frequency: String,
 }
 
 /// A data container for populating the lookup template.
@@ -38,415 +260,1460 @@ struct LookupTemplateData<'a> {
     /// The word being looked up.
     query: &'a str,
 
-    /// Metadata about the query word;
-    entry_info: Option<String>,
+    /// Whether the word was found in the dictionary, or read as a pronunciation.
+    found: bool,
+
+    /// Whether pronunciations are written in IPA.
+    ipa: bool,
 
-    /// The total number of results found.
+    /// Each pronunciation of the word, with its similar words.
+    variants: Vec<VariantTemplateData>,
+
+    /// The options used for the query, to pre-fill the form.
+    options: &'a LookupOptions,
+
+    /// The name of the sort order in use.
+    sort: &'a str,
+
+    /// Links to re-sort the results, starting from the first page.
+    sort_urls: SortUrls,
+
+    /// Whether there's a frequency list, for sorting and filtering by frequency.
+    has_frequencies: bool,
+
+    /// Whether there's entry metadata, for filtering by part of speech.
+    has_metadata: bool,
+
+    /// A link to the previous page of results, if any.
+    prev_url: Option<String>,
+
+    /// A link to the next page of results, if any.
+    next_url: Option<String>,
+}
+
+/// One pronunciation of the query word on the lookup page.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct VariantTemplateData {
+    /// The term as it appears in the dictionary, e.g. "wind(2)".
+    dict_key: String,
+
+    /// Pre-serialized phoneme sequence, by syllable, e.g. "W IH1 N · D OW0" or "ˈwɪn.doʊ".
+    phonemes: String,
+
+    /// The number of syllables in this pronunciation.
+    syllables: i32,
+
+    /// The dictionary the pronunciation came from, e.g. "./userdict.dict", if known.
+    source: Option<String>,
+
+    /// Pre-serialized parts of speech, e.g. "noun, verb". Empty if they aren't known.
+    pos: String,
+
+    /// The total number of results that matched the filters.
     num_found: usize,
 
     /// The number of results returned.
     num_returned: usize,
 
-    /// All of the words, sorted in decreasing order of similarity.
+    /// The 1-based position of the first returned result.
+    first: usize,
+
+    /// The words on this page, in the requested order.
     similar_words: Vec<SimilarWordTemplateData>,
+
+    /// The best near rhymes (see `Dictionary::near_rhymes`), which ignore the filters.
+    near_rhymes: Vec<SimilarWordTemplateData>,
+
+    /// The words starting with the same consonants (see `Dictionary::alliterations`), which
+    /// also ignore the filters.
+    alliterations: Vec<SimilarWordTemplateData>,
+
+    /// The words with exactly the same phonemes (see `Dictionary::homophones`).
+    homophones: Vec<SimilarWordTemplateData>,
+
+    /// The words one phoneme away (see `Dictionary::minimal_pairs`).
+    minimal_pairs: Vec<SimilarWordTemplateData>,
+
+    /// The phrases that rhyme (see `dictionary::mosaic_rhymes`), e.g. "know it" for "poet".
+    mosaic_rhymes: Vec<SimilarWordTemplateData>,
 }
 
 /// Handler for querying the dictionary for a single term.
-#[get("/lookup?<term>&<num>")]
-fn lookup(state: &State<ServerState>, term: &str, num: Option<usize>) -> Template {
+///
+/// See `LookupOptions` for the optional query parameters.
+#[get("/lookup?<term>&<options..>")]
+fn lookup(state: &State<ServerState>, term: &str, options: LookupOptions) -> Template {
+    let sort = options
+        .sort
+        .clone()
+        .unwrap_or_else(|| String::from("score"));
+    let offset = options.offset.unwrap_or(0);
+    let limit = options.limit_or(DEFAULT_LOOKUP_LIMIT);
+    let notation = options.notation();
     let mut data = LookupTemplateData {
         query: term,
-        entry_info: None,
-        num_found: 0,
-        num_returned: 0,
-        similar_words: vec![],
+        found: false,
+        ipa: notation == Notation::Ipa,
+        variants: vec![],
+        options: &options,
+        sort: &sort,
+        sort_urls: SortUrls {
+            score: options.to_url(term, "score", 0),
+            word: options.to_url(term, "word", 0),
+            syllables: options.to_url(term, "syllables", 0),
+            frequency: options.to_url(term, "frequency", 0),
+        },
+        has_frequencies: false,
+        has_metadata: false,
+        prev_url: None,
+        next_url: None,
     };
-    let max_results = num.unwrap_or(500);
 
     let shelf = state.shelf.lock().unwrap();
     let dict = shelf.over_all();
+    data.has_frequencies = shelf.has_frequencies();
+    data.has_metadata = shelf.has_metadata();
 
-    if let Some(v) = dict.lookup(term) {
-        if v.len() > 1 {
-            // FIXME: Ignoring this case.
-            println!(
-                "Warning: {} has multiple results and only rendering the first.",
-                term
+    if let Some((v, mut similar_result)) = lookup_term_or_pronunciation(dict, term) {
+        data.found = true;
+        similar_result.filter(&options.to_filter());
+        similar_result.sort_by(options.order());
+        let convert = |words: Vec<dictionary::SimilarWord>| -> Vec<SimilarWordTemplateData> {
+            words
+                .into_iter()
+                .map(|w| SimilarWordTemplateData::new(w, notation))
+                .collect()
+        };
+
+        // There is one group per entry, in the same order.
+        let mut has_more = false;
+        for (entry, group) in v.iter().zip(similar_result.groups) {
+            let mut variant = VariantTemplateData {
+                dict_key: entry.dict_key(),
+                phonemes: notation.format_syllables(&entry.phonemes),
+                syllables: entry.num_syllables(),
+                source: entry.source.as_deref().map(String::from),
+                pos: join_parts_of_speech(entry.parts_of_speech()),
+                num_found: group.words.len(),
+                num_returned: 0,
+                first: offset + 1,
+                similar_words: vec![],
+                near_rhymes: vec![],
+                alliterations: vec![],
+                homophones: vec![],
+                minimal_pairs: vec![],
+                mosaic_rhymes: vec![],
+            };
+            has_more |= group.words.len() > offset + limit;
+            let words = group.words.into_iter().skip(offset).take(limit).collect();
+            variant.similar_words = convert(words);
+            variant.num_returned = variant.similar_words.len();
+            let mut near = dict.near_rhymes(&entry.phonemes, Some(term), NEAR_RHYME_LIMIT);
+            if let Some(group) = near.groups.pop() {
+                variant.near_rhymes = convert(group.words);
+            }
+            let mut alliterations = dict.alliterations(
+                &entry.phonemes,
+                Some(term),
+                options.alliteration(),
+                ALLITERATION_LIMIT,
             );
+            if let Some(group) = alliterations.groups.pop() {
+                variant.alliterations = convert(group.words);
+            }
+            if let Some(group) = dict.homophones(&entry.phonemes, Some(term)).groups.pop() {
+                variant.homophones = convert(group.words);
+            }
+            if let Some(group) = dict.minimal_pairs(&entry.phonemes, Some(term)).groups.pop() {
+                variant.minimal_pairs = convert(group.words);
+            }
+            let mut mosaics =
+                dictionary::mosaic_rhymes(dict, &entry.phonemes, Some(term), MOSAIC_RHYME_LIMIT);
+            if let Some(group) = mosaics.groups.pop() {
+                variant.mosaic_rhymes = convert(group.words);
+            }
+            data.variants.push(variant);
         }
-        let entry = &v[0];
-        data.entry_info = Some(format!("{:?}", entry));
-        let similar_result = dict.similar(term);
-        data.num_found = similar_result.words.len();
-        for word in similar_result.words.into_iter().take(max_results) {
-            let word_for_template = SimilarWordTemplateData {
-                word: word.word,
-                syllables: word.syllables,
-                score: word.score,
-                phonemes: format!("{}", &word.phonemes),
-            };
-            data.similar_words.push(word_for_template);
+        if offset > 0 {
+            data.prev_url = Some(options.to_url(term, &sort, offset.saturating_sub(limit)));
+        }
+        if has_more {
+            data.next_url = Some(options.to_url(term, &sort, offset + limit));
         }
-        data.num_returned = data.similar_words.len();
     }
     return Template::render("lookup", data);
 }
 
+/// Looks up `term` and the words similar to each of its pronunciations.
+///
+/// If `term` isn't in the dictionary, it can be a pronunciation instead, in IPA or ARPABET (see
+/// `ipa::parse_pronunciation`), e.g. to find rhymes for a made-up word. Then there's just the
+/// one "entry", with `term` as its word.
+fn lookup_term_or_pronunciation(
+    dict: &dyn dictionary::Dictionary,
+    term: &str,
+) -> Option<(Vec<dictionary::Entry>, dictionary::SimilarResult)> {
+    if let Some(v) = dict.lookup(term) {
+        return Some((v.clone(), dict.similar(term)));
+    }
+    let phonemes = ipa::parse_pronunciation(term).ok()?;
+    let similar_result = dict.similar_to_phonemes(&phonemes, None);
+    let entry = dictionary::Entry {
+        word: term.trim().to_string(),
+        phonemes,
+        variant: 1,
+        source: None,
+        metadata: None,
+    };
+    return Some((vec![entry], similar_result));
+}
+
 /// Handler for AJAX lookup of a term (`/api/lookup?term=<query>`).
 ///
 /// The templating is all done server-side at the moment, so this endpoint returns HTML to
-/// be inserted into the page.
-#[get("/api/lookup?<term>")]
-fn api_lookup(state: &State<ServerState>, term: &str) -> String {
+/// be inserted into the page. All user-provided text is escaped.
+///
+/// There is one line in the output for each pronunciation of the term. The same
+/// `LookupOptions` as `/lookup` are accepted, with `limit` setting the number of examples.
+#[get("/api/lookup?<term>&<options..>")]
+fn api_lookup(state: &State<ServerState>, term: &str, options: LookupOptions) -> RawHtml<String> {
     let shelf = state.shelf.lock().unwrap();
 
     let dict = shelf.over_all();
 
-    if let Some(v) = dict.lookup(term) {
-        if v.len() > 1 {
-            // FIXME: Ignoring this case.
-            println!(
-                "Warning: {} has multiple results and only returning one.",
-                term
-            );
-        }
-        let entry = &v[0];
-        let similar_info = dict.similar(term);
-        let num_similar = similar_info.words.len();
+    if let Some((v, mut similar_result)) = lookup_term_or_pronunciation(dict, term) {
+        similar_result.filter(&options.to_filter());
+        similar_result.sort_by(options.order());
 
-        let mut examples = String::with_capacity(1024); // Arbitrary.
         const NUM_WORDS_TO_SHOW: usize = 8;
-        for (i, word_info) in similar_info.words.iter().enumerate() {
-            if i > NUM_WORDS_TO_SHOW {
-                examples.push_str("...");
-                break;
-            } else {
-                examples.push_str(&format!("<b>{}</b>, ", &word_info.word));
+        let offset = options.offset.unwrap_or(0);
+        let limit = options.limit_or(NUM_WORDS_TO_SHOW);
+
+        let mut out = String::with_capacity(1024); // Arbitrary.
+        for (entry, group) in v.iter().zip(similar_result.groups.iter()) {
+            let mut examples = String::with_capacity(1024); // Arbitrary.
+            for (i, word_info) in group.words.iter().skip(offset).enumerate() {
+                if i >= limit {
+                    examples.push_str("...");
+                    break;
+                } else {
+                    examples.push_str(&format!("<b>{}</b>, ", escape_html(&word_info.word)));
+                }
             }
-        }
 
-        return format!(
-            "{} (<code>{}</code>) [{} syllables] with {} similar words like:<br>{}",
-            term,
-            entry.phonemes,
-            entry.num_syllables(),
-            num_similar,
-            examples
-        );
+            out.push_str(&format!(
+                "{} (<code>{}</code>) [{} syllables] with {} similar words like:<br>{}<br>",
+                escape_html(&entry.dict_key()),
+                escape_html(&options.notation().format(&entry.phonemes)),
+                entry.num_syllables(),
+                group.words.len(),
+                examples
+            ));
+        }
+        return RawHtml(out);
     } else {
-        return format!("<em>{}</em> not found.", term);
+        return RawHtml(format!("<em>{}</em> not found.", escape_html(term)));
     }
 }
 
-/// A `serde` template for `/datamuse` to provide a list of words from an input form.
-#[derive(FromForm)]
-struct DatamusePostInput<'a> {
-    /// A list of whitespace-separated words to look up.
-    words: &'a str,
-}
-
-/// Handles a POST request to /datamuse to look up all the words in the input.
-#[post("/datamuse", data = "<req>")]
-async fn datamuse(_state: &State<ServerState>, req: Form<DatamusePostInput<'_>>) -> String {
-    let datamuse = client::datamuse::Client::new();
+/// Handler for AJAX lookup of the homophones and minimal pairs of a term
+/// (`/api/soundalikes?term=<query>`).
+///
+/// Like `/api/lookup`, this returns an HTML snippet with one line for each pronunciation of the
+/// term, listing up to `limit` words of each kind.
+#[get("/api/soundalikes?<term>&<limit>")]
+fn api_soundalikes(
+    state: &State<ServerState>,
+    term: &str,
+    limit: Option<usize>,
+) -> RawHtml<String> {
+    let shelf = state.shelf.lock().unwrap();
+    let dict = shelf.over_all();
 
-    let mut out = String::with_capacity(1000);
-    out.push_str("# Found these entries. You can add them to 'userdict.dict'.\n");
-    for word in req.words.split_whitespace().take(100) {
-        match datamuse.get_phonemes(&word).await {
-            Ok(Some(entry)) => {
-                out.push_str(&format!("{} {}\n", &entry.word, &entry.phonemes));
-            }
-            Ok(None) => {
-                out.push_str(&format!("# fetch of {} returned nothing\n", &word));
-            }
-            Err(e) => {
-                out.push_str(&format!(
-                    "# fetch of {} failed with error: {:?}\n",
-                    &word, &e
-                ));
-            }
+    let variants = match dict.lookup(term) {
+        Some(v) => v,
+        None => return RawHtml(format!("<em>{}</em> not found.", escape_html(term))),
+    };
+    const NUM_WORDS_TO_SHOW: usize = 8;
+    let limit = limit.unwrap_or(NUM_WORDS_TO_SHOW);
+    let list = |kind: &str, result: dictionary::SimilarResult| -> String {
+        let words = &result.groups[0].words;
+        let mut out: Vec<String> = words
+            .iter()
+            .take(limit)
+            .map(|w| format!("<b>{}</b>", escape_html(&w.word)))
+            .collect();
+        if words.len() > limit {
+            out.push("...".to_string());
         }
+        return format!("{} {}: {}", words.len(), kind, out.join(", "));
+    };
+
+    let mut out = String::with_capacity(1024); // Arbitrary.
+    for entry in variants {
+        out.push_str(&format!(
+            "{} (<code>{}</code>) has {}; {}<br>",
+            escape_html(&entry.dict_key()),
+            escape_html(&entry.phonemes.to_string()),
+            list("homophones", dict.homophones(&entry.phonemes, Some(term))),
+            list(
+                "minimal pairs",
+                dict.minimal_pairs(&entry.phonemes, Some(term))
+            ),
+        ));
     }
-    return out;
+    return RawHtml(out);
 }
 
-/// Describes the parameters and types for /analyze POST requests.
+/// Handler for AJAX lookup of the phrases that rhyme with a term (`/api/mosaic?term=<query>`).
 ///
-/// This is used by Rocket to validate incoming requests and to pass the values to
-/// the `analyze` handler.
-#[derive(FromForm)]
-struct AnalyzeRequest<'a> {
-    /// The text snippet to analyze.
-    text: &'a str,
+/// Like `/api/lookup`, this returns an HTML snippet with one line for each pronunciation of the
+/// term, listing up to `limit` phrases. See `dictionary::mosaic_rhymes`.
+#[get("/api/mosaic?<term>&<limit>")]
+fn api_mosaic(state: &State<ServerState>, term: &str, limit: Option<usize>) -> RawHtml<String> {
+    let shelf = state.shelf.lock().unwrap();
+    let dict = shelf.over_all();
+
+    let variants = match dict.lookup(term) {
+        Some(v) => v,
+        None => return RawHtml(format!("<em>{}</em> not found.", escape_html(term))),
+    };
+    const NUM_PHRASES_TO_SHOW: usize = 8;
+    let limit = limit.unwrap_or(NUM_PHRASES_TO_SHOW);
+    let mut out = String::with_capacity(1024); // Arbitrary.
+    for entry in variants {
+        let result = dictionary::mosaic_rhymes(dict, &entry.phonemes, Some(term), limit);
+        let phrases: Vec<String> = result.groups[0]
+            .words
+            .iter()
+            .map(|w| {
+                format!(
+                    "<b>{}</b> (<code>{}</code>)",
+                    escape_html(&w.word),
+                    w.phonemes
+                )
+            })
+            .collect();
+        out.push_str(&format!(
+            "{} (<code>{}</code>) rhymes with {} phrases like:<br>{}<br>",
+            escape_html(&entry.dict_key()),
+            escape_html(&entry.phonemes.to_string()),
+            phrases.len(),
+            phrases.join(", ")
+        ));
+    }
+    return RawHtml(out);
 }
 
-/// Handler for a POST form to analyze a block of prose / snippet.
-#[post("/analyze", data = "<req>")]
-fn analyze(state: &State<ServerState>, req: Form<AnalyzeRequest>) -> Template {
-    let mut context = rocket_dyn_templates::tera::Context::new();
+/// The number of matching words to show on the search page, by default.
+const DEFAULT_SEARCH_LIMIT: usize = 200;
 
-    // Copy the user input to the output to pre-fill the form box.
-    context.insert("user_input", &req.text);
+/// One matching entry on the search page.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct SearchWordTemplateData {
+    /// The word, e.g. "wind", for linking to its lookup page.
+    word: String,
 
-    let shelf = state.shelf.lock().unwrap();
-    let dict = shelf.over_all();
+    /// The term as it appears in the dictionary, e.g. "wind(2)".
+    dict_key: String,
 
-    // Parse the input and break it into one or more stanzas.
-    let stanzas = snippet::get_stanzas_from_text(&req.text, dict);
+    /// Pre-serialized phoneme sequence, e.g. "W AY1 N D".
+    phonemes: String,
+
+    /// The number of syllables in this pronunciation.
+    syllables: i32,
+}
 
-    // Hack: Most of the page is rendered with raw HTML and strings, not with templates.
-    // TODO: Figure out how to do sub-templates with Rocket, etc. and fix this.
-    let mut html = String::with_capacity(32768); // An arbitrary, "biggish" starting point.
+/// A data container for populating the search template.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct SearchTemplateData<'a> {
+    /// The pattern as it was entered, or "" before the first search.
+    pattern: &'a str,
 
-    let mut unknown_words = vec![];
+    /// Why the pattern couldn't be parsed, if it couldn't.
+    error: Option<String>,
 
-    for stanza in &stanzas {
-        unknown_words.append(&mut stanza.unknown_words());
+    /// The total number of entries that matched.
+    num_found: usize,
+
+    /// The 1-based position of the first returned entry.
+    first: usize,
 
-        let best_interpretation = stanza.analyze();
+    /// The number of entries returned.
+    num_returned: usize,
 
-        // This prints the original text of the stanza, the phonemes of each word, the
-        // classification, and any errors/warnings from the analysis.
-        best_interpretation.append_html_to(&mut html);
+    /// The entries on this page, sorted by word.
+    words: Vec<SearchWordTemplateData>,
 
-        // This prints original text of the stanza, along with every word variant and their
-        // phonemes, so that users can see where the analysis may have been incorrect.
-        stanza.append_html_to(&mut html);
-    }
+    /// A link to the previous page of results, if any.
+    prev_url: Option<String>,
 
-    context.insert("prose_html", &html);
-    context.insert("unknown_words", &unknown_words.join("\n"));
-    return Template::render("analyze", context.into_json());
+    /// A link to the next page of results, if any.
+    next_url: Option<String>,
 }
 
-/// A trait like `Display` to render various structures as HTML.
-///
-/// All of these should be done in templates, but this was all written pretty fast and that
-/// refactoring hasn't happened yet. So instead, this trait is an experiment to try and write
-/// something that's somewhat readable and not also copying big strings all the time.
+/// Builds a `/search` URL for `pattern` starting at `offset`.
+fn search_url(pattern: &str, offset: usize, limit: usize) -> String {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("pattern", pattern)
+        .append_pair("offset", &offset.to_string())
+        .append_pair("limit", &limit.to_string())
+        .finish();
+    format!("/search?{}", query)
+}
+
+/// Handler for finding words by a phonetic pattern, e.g. `/search?pattern=*+AE1+T`.
 ///
-/// It would be cool to use a Formatter to write directly to, but I haven't figured out how to make
-/// one yet.
-pub trait ToHtml {
-    /// Outputs `self` as an HTML string, appended to `out`.
-    fn append_html_to(&self, out: &mut String);
-
-    /// Returns an HTML snippet representing `self`.
-    ///
-    /// This variant is for ease of use when rendering smaller objects inside a `format!` macro.
-    /// The default implementation allocates a small `String` to render into.
-    fn to_html(&self) -> String {
-        let mut out = String::with_capacity(100); // Assumed small-ish if inline.
-        self.append_html_to(&mut out);
-        out
-    }
-}
-
-impl ToHtml for snippet::BestInterpretation<'_> {
-    /// Renders the interpretation to HTML.
-    ///
-    /// This includes the `StanzaView` of the best interpretation, the classification, and
-    /// any warnings/errors from the analysis.
-    fn append_html_to(&self, out: &mut String) {
-        let view: &snippet::StanzaView = self.best.as_ref().unwrap();
-
-        out.push_str("<pre>");
-        view.append_html_to(out);
-        out.push('\n');
-
-        if self.errors.is_empty() {
-            out.push_str(&format!("<b>What a great {}!</b>\n", &self.validator));
-        } else {
-            out.push_str(&format!(
-                "This looks like a {}, except for these ...\n",
-                &self.validator
-            ));
-            out.push_str("<span class=\"error_header\">Errors and warnings:</span>\n");
-            for e in &self.errors {
-                use snippet::ClassifyError::*;
-                match &e {
-                    StanzaError(_) => {
-                        out.push_str(&format!("<span class=\"stanza_warning\">{}</span>\n", &e))
-                    }
-                    LineError(_, _) => {
-                        out.push_str(&format!("<span class=\"line_warning\">{}</span>\n", &e))
-                    }
-                }
-            }
-        }
-        out.push_str("\n</pre>");
-    }
-}
-
-impl ToHtml for snippet::Stanza<'_> {
-    /// Renders the `Stanza` to HTML.
-    ///
-    /// This shows the words in the `Stanza` with the corresponding dictionary entries, to aid
-    /// in checking the dictionaries have the correct words and interpretations available.
-    ///
-    /// ```
-    /// // https://www.mcsweeneys.net/articles/haiku-a-bitter-duck-might-write
-    ///
-    /// Humans envy my
-    ///         humans: "humans" (HH Y UW1 M AH0 N Z); variant=1, syllables=2
-    ///               : "humans" (Y UW1 M AH0 N Z); variant=2, syllables=2
-    ///         envy: "envy" (EH1 N V IY0); variant=1, syllables=2
-    ///         my: "my" (M AY1); variant=1, syllables=1
-    ///
-    /// swimming ability. But
-    ///         swimming: "swimming" (S W IH1 M IH0 NG); variant=1, syllables=2
-    ///         ability: "ability" (AH0 B IH1 L AH0 T IY2); variant=1, syllables=4
-    ///         but: "but" (B AH1 T); variant=1, syllables=1
-    ///
-    /// I wish I could read.
-    ///         i: "i" (AY1); variant=1, syllables=1
-    ///         wish: "wish" (W IH1 SH); variant=1, syllables=1
-    ///         i: "i" (AY1); variant=1, syllables=1
-    ///         could: "could" (K UH1 D); variant=1, syllables=1
-    ///         read: "read" (R EH1 D); variant=1, syllables=1
-    ///             : "read" (R IY1 D); variant=2, syllables=1
-    /// ```
-    ///
-    fn append_html_to(&self, out: &mut String) {
-        out.push_str("<pre>");
-        if let Some(title) = &self.title {
-            out.push_str(&format!("TITLE: {}\n", &title));
+/// See `pattern` for the syntax. Without a pattern, this just shows the search form.
+#[get("/search?<pattern>&<offset>&<limit>")]
+fn search(
+    state: &State<ServerState>,
+    pattern: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Template {
+    let offset = offset.unwrap_or(0);
+    // At least 1, so that paging always advances.
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT).max(1);
+    let mut data = SearchTemplateData {
+        pattern: pattern.unwrap_or(""),
+        error: None,
+        num_found: 0,
+        first: offset + 1,
+        num_returned: 0,
+        words: vec![],
+        prev_url: None,
+        next_url: None,
+    };
+    if data.pattern.trim().is_empty() {
+        return Template::render("search", data);
+    }
+    let parsed: pattern::Pattern = match data.pattern.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            data.error = Some(e);
+            return Template::render("search", data);
         }
+    };
 
-        for line in &self.lines {
-            out.push_str(&line.raw_text);
-            out.push('\n');
-            for token in &line.tokens {
-                if let Some(entries) = &token.entry {
-                    for (i, entry) in entries.iter().enumerate() {
-                        if i == 0 {
-                            out.push_str(&format!("\t{}: {}\n", &token.text, entry.to_html(),));
-                        } else {
-                            out.push_str(&format!(
-                                "\t{}: {}\n",
-                                &" ".repeat(token.text.len()),
-                                entry.to_html(),
-                            ));
-                        }
-                    }
-                } else {
-                    out.push_str(&format!(
-                        "\t<span class=\"missing\">{}: not found.</span>\n",
-                        &token.text
-                    ));
-                }
-            }
-            // TODO: Re-introduce the line summary with the number of syllables.
-            out.push('\n');
-        }
-        if self.has_unknown_words() {
-            out.push_str(&format!(
-                "Warning: The text has some unknown words. Analysis may suffer.\n"
-            ));
-        }
-        out.push_str("\n</pre>");
+    let shelf = state.shelf.lock().unwrap();
+    let found = shelf.over_all().search(&parsed);
+    data.num_found = found.len();
+    for entry in found.iter().skip(offset).take(limit) {
+        data.words.push(SearchWordTemplateData {
+            word: entry.word.clone(),
+            dict_key: entry.dict_key(),
+            phonemes: entry.phonemes.to_string(),
+            syllables: entry.num_syllables(),
+        });
+    }
+    data.num_returned = data.words.len();
+    if offset > 0 {
+        data.prev_url = Some(search_url(
+            data.pattern,
+            offset.saturating_sub(limit),
+            limit,
+        ));
+    }
+    if found.len() > offset + limit {
+        data.next_url = Some(search_url(data.pattern, offset + limit, limit));
     }
+    return Template::render("search", data);
 }
 
-impl ToHtml for snippet::StanzaView<'_> {
-    fn append_html_to(&self, out: &mut String) {
-        for line in &self.lines {
-            line.append_html_to(out);
-            out.push('\n');
+/// Handler for AJAX searches by a phonetic pattern (`/api/search?pattern=<pattern>`).
+///
+/// Like `/api/lookup`, this returns escaped HTML to be inserted into the page: the number of
+/// matching words and the first `limit` of them (8 by default), or why the pattern is invalid.
+#[get("/api/search?<pattern>&<limit>")]
+fn api_search(state: &State<ServerState>, pattern: &str, limit: Option<usize>) -> RawHtml<String> {
+    let parsed: pattern::Pattern = match pattern.parse() {
+        Ok(parsed) => parsed,
+        Err(e) => return RawHtml(format!("<em>Invalid pattern:</em> {}", escape_html(&e))),
+    };
+    let shelf = state.shelf.lock().unwrap();
+    let found = shelf.over_all().search(&parsed);
+
+    const NUM_WORDS_TO_SHOW: usize = 8;
+    let limit = limit.unwrap_or(NUM_WORDS_TO_SHOW);
+    let mut examples = String::with_capacity(1024); // Arbitrary.
+    for (i, entry) in found.iter().enumerate() {
+        if i >= limit {
+            examples.push_str("...");
+            break;
         }
+        examples.push_str(&format!(
+            "<b>{}</b> (<code>{}</code>), ",
+            escape_html(&entry.dict_key()),
+            escape_html(&entry.phonemes.to_string())
+        ));
     }
+    return RawHtml(format!(
+        "{} words match <code>{}</code>:<br>{}",
+        found.len(),
+        escape_html(&parsed.to_string()),
+        examples
+    ));
 }
 
-impl ToHtml for snippet::LineView<'_> {
-    /// Renders a `LineView` to explain which words were selected.
-    ///
-    /// ```
-    /// 01. Humans envy my
-    ///   . humans............  envy.......  my...  
-    ///   . HH Y UW1 M AH0 N Z  EH1 N V IY0  M AY1  
-    /// ```
-    fn append_html_to(&self, out: &mut String) {
-        out.push_str(&format!(
-            "{:02} {:2}. {}\n",
-            self.num(),
-            self.index() + 1,
-            self.raw_text(),
-        ));
-        // Start with just blasting everything there, and then make it pretty / evenly spaced.
-        let num_tokens = self.num_words();
-
-        let mut dict_keys: Vec<String> = Vec::with_capacity(num_tokens);
-        let mut phoneme_strs: Vec<String> = Vec::with_capacity(num_tokens);
-
-        // If a word is in the dictionary, then it must have phonemes.
-        // The phonemes will always be longer than the word, often significantly.
-        //
-        // Thus, the widths for known words are always computed from the phonemes.
-        // (And, until there is alignment with the raw strings, they are used in all cases.)
-        let mut widths: Vec<usize> = Vec::with_capacity(num_tokens);
-        for i in 0..num_tokens {
-            match self.get_entry(i) {
-                Some(e) => {
-                    dict_keys.push(e.dict_key());
-                    phoneme_strs.push(format!("{}", e.phonemes));
-                }
-                None => {
-                    let token_text = self.get_text(i);
-                    dict_keys.push(format!("<span class=\"missing\">{}</span>", token_text));
-                    phoneme_strs.push(format!("{: ^1$}", "?", token_text.len()));
-                    // Centers the ?.
-                }
-            }
-            widths.push(phoneme_strs.last().unwrap().len());
-        }
+/// The number of words to show per pronunciation of the rhyme on the meter page, by default.
+const DEFAULT_METER_LIMIT: usize = 200;
 
-        // Start the line by shifting over by the line number prefix (assumed "NN. ").
-        out.push_str("     . ");
-        for i in 0..num_tokens {
-            // "Make the minimum field width the value of the '1'st argument (widths[i]), by
-            // left-justifying the string ('<'), and filling the rest with '.'".
-            //
-            // Note that using "1$" in the format specifier has weird effects on the positional
-            // arguments for the rest of the specifier, so it is best to put these all at the end.
-            out.push_str(&format!("{:.<1$}  ", dict_keys[i], widths[i]));
-        }
+/// Query parameters for `/meter`. Only `stress` is required to search.
+///
+/// ```raw
+/// /meter?stress=010&rhyme=banana&onset=B&offset=200&limit=200
+/// ```
+#[derive(Debug, Default, FromForm, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct MeterOptions {
+    /// The stress pattern, e.g. "010". See `dictionary::StressQuery`.
+    stress: Option<String>,
 
-        // Again with the shift, and the previous EOL this time too.
-        out.push_str("\n     . ");
-        for i in 0..num_tokens {
-            out.push_str(&format!("{}  ", phoneme_strs[i]));
-        }
-        out.push('\n');
-    }
+    /// A word that the results must rhyme with.
+    rhyme: Option<String>,
+
+    /// Phonemes that the results must start with, e.g. "K R".
+    onset: Option<String>,
+
+    /// The number of words to skip in each group, for paging.
+    offset: Option<usize>,
+
+    /// The maximum number of words to return in each group.
+    limit: Option<usize>,
 }
 
-impl ToHtml for dictionary::Entry {
-    fn append_html_to(&self, out: &mut String) {
-        out.push_str(&format!(
-            "(<span class=\"phonemes\">{}</span>); \
-             <span class=\"entry_aux\">variant={}, \
-             syllables={}</span>",
-            &self.phonemes,
-            &self.variant,
-            self.num_syllables()
-        ));
+impl MeterOptions {
+    /// Returns `rhyme`, or None if it's missing or blank.
+    fn rhyme(&self) -> Option<&str> {
+        self.rhyme
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
     }
-}
 
-/// Handler for the root (/) page.
-#[rocket::get("/")]
-fn index() -> Template {
-    let context = HashMap::<&str, &str>::new();
-    return Template::render("index", context);
+    /// Returns `limit` or the default. It's at least 1, so that paging always advances.
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_METER_LIMIT).max(1)
+    }
+
+    /// Builds a `/meter` URL with these options, starting at `offset`.
+    fn to_url(&self, offset: usize) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("stress", &self.stress),
+            ("rhyme", &self.rhyme),
+            ("onset", &self.onset),
+        ] {
+            if let Some(v) = value {
+                query.append_pair(name, v);
+            }
+        }
+        query.append_pair("offset", &offset.to_string());
+        query.append_pair("limit", &self.limit().to_string());
+        format!("/meter?{}", query.finish())
+    }
 }
 
-/// Starts the Rocket HTTP server and awaits until the server shuts down.
-///
-/// Args:
-///
+/// The words found for one pronunciation of the rhyme on the meter page, or all of them if
+/// there's no rhyme.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct MeterGroupTemplateData {
+    /// The pronunciation of the rhyme, e.g. "wind(2)", if there is a rhyme.
+    dict_key: Option<String>,
+
+    /// Pre-serialized phonemes of the rhyme, e.g. "W AY1 N D", or "" without a rhyme.
+    phonemes: String,
+
+    /// The total number of words found.
+    num_found: usize,
+
+    /// The number of words returned.
+    num_returned: usize,
+
+    /// The 1-based position of the first returned word.
+    first: usize,
+
+    /// The words on this page.
+    words: Vec<SimilarWordTemplateData>,
+}
+
+/// A data container for populating the meter template.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct MeterTemplateData<'a> {
+    /// The options used for the query, to pre-fill the form.
+    options: &'a MeterOptions,
+
+    /// Whether the words were compared to a rhyme, so their scores mean something.
+    rhymed: bool,
+
+    /// Why the query couldn't be run, if it couldn't.
+    error: Option<String>,
+
+    /// One group per pronunciation of the rhyme, or just one without a rhyme.
+    groups: Vec<MeterGroupTemplateData>,
+
+    /// A link to the previous page of results, if any.
+    prev_url: Option<String>,
+
+    /// A link to the next page of results, if any.
+    next_url: Option<String>,
+}
+
+/// Handler for finding words by their stress pattern, for filling a metrical slot.
+///
+/// See `MeterOptions` for the query parameters. Without a stress pattern, this just shows the
+/// form.
+#[get("/meter?<options..>")]
+fn meter(state: &State<ServerState>, options: MeterOptions) -> Template {
+    let offset = options.offset.unwrap_or(0);
+    let limit = options.limit();
+    let mut data = MeterTemplateData {
+        options: &options,
+        rhymed: options.rhyme().is_some(),
+        error: None,
+        groups: vec![],
+        prev_url: None,
+        next_url: None,
+    };
+    let stress = match options.stress.as_deref().map(str::trim) {
+        Some(stress) if !stress.is_empty() => stress,
+        _ => return Template::render("meter", data),
+    };
+    let query = dictionary::StressQuery::new(stress).and_then(|mut query| {
+        query.onset = phoneme::parse_sequence(options.onset.as_deref().unwrap_or(""))?;
+        Ok(query)
+    });
+    let shelf = state.shelf.lock().unwrap();
+    let result = query.and_then(|query| {
+        dictionary::by_stress_rhyming_with(shelf.over_all(), &query, options.rhyme())
+    });
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            data.error = Some(e);
+            return Template::render("meter", data);
+        }
+    };
+
+    let mut has_more = false;
+    for group in result.groups {
+        let num_found = group.words.len();
+        has_more |= num_found > offset + limit;
+        let words: Vec<SimilarWordTemplateData> = group
+            .words
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|w| SimilarWordTemplateData::new(w, Notation::Arpabet))
+            .collect();
+        let dict_key = match (options.rhyme(), group.variant) {
+            (Some(word), Some(v)) if v != 1 => Some(format!("{}({})", word, v)),
+            (Some(word), _) => Some(word.to_string()),
+            (None, _) => None,
+        };
+        data.groups.push(MeterGroupTemplateData {
+            dict_key,
+            phonemes: group.phonemes.to_string(),
+            num_found,
+            num_returned: words.len(),
+            first: offset + 1,
+            words,
+        });
+    }
+    if offset > 0 {
+        data.prev_url = Some(options.to_url(offset.saturating_sub(limit)));
+    }
+    if has_more {
+        data.next_url = Some(options.to_url(offset + limit));
+    }
+    return Template::render("meter", data);
+}
+
+/// A `serde` template for `/datamuse` to provide a list of words from an input form.
+#[derive(FromForm)]
+struct DatamusePostInput<'a> {
+    /// A list of whitespace-separated words to look up.
+    words: &'a str,
+}
+
+/// Handles a POST request to /datamuse to look up all the words in the input.
+#[post("/datamuse", data = "<req>")]
+async fn datamuse(_state: &State<ServerState>, req: Form<DatamusePostInput<'_>>) -> String {
+    let datamuse = client::datamuse::Client::new();
+
+    let mut out = String::with_capacity(1000);
+    out.push_str("# Found these entries. You can add them with 'poet dict add'.\n");
+    for word in req.words.split_whitespace().take(100) {
+        match datamuse.get_phonemes(&word).await {
+            Ok(Some(entry)) => {
+                out.push_str(&format!("{} {}", &entry.word, &entry.phonemes));
+                // The tags are in the same format as a sidecar metadata file.
+                if let Some(metadata) = &entry.metadata {
+                    out.push_str(&format!(" # {}", metadata));
+                }
+                out.push('\n');
+            }
+            Ok(None) => {
+                out.push_str(&format!("# fetch of {} returned nothing\n", &word));
+            }
+            Err(e) => {
+                out.push_str(&format!(
+                    "# fetch of {} failed with error: {:?}\n",
+                    &word, &e
+                ));
+            }
+        }
+    }
+    return out;
+}
+
+/// A request guard for requests that may edit the user dictionary.
+///
+/// Requests must send the server's edit token as `Authorization: Bearer <token>`.
+struct EditAuthorized;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for EditAuthorized {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let provided = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let checked = match req.rocket().state::<ServerState>() {
+            Some(state) => state.check_edit_token(provided),
+            None => Err((Status::Forbidden, "editing is disabled")),
+        };
+        match checked {
+            Ok(()) => request::Outcome::Success(EditAuthorized),
+            Err(failure) => request::Outcome::Failure(failure),
+        }
+    }
+}
+
+/// The form for adding or updating a user dictionary entry.
+#[derive(FromForm)]
+struct UserDictEntryInput<'a> {
+    /// The entry as a line in cmudict format, e.g. "word W ER1 D".
+    entry: &'a str,
+}
+
+/// Converts the result of a user dictionary edit into a status and a plain-text message.
+fn user_dict_response(
+    result: Result<dictionary::Entry, dictionary::UserDictError>,
+    success: Status,
+    verb: &str,
+) -> (Status, String) {
+    match result {
+        Ok(entry) => (
+            success,
+            format!("{} {} {}\n", verb, entry.dict_key(), entry.phonemes),
+        ),
+        Err(e) => (user_dict_error_status(&e), format!("{}\n", e)),
+    }
+}
+
+/// Returns the status for a failed user dictionary edit, e.g. a server error if it couldn't be
+/// saved.
+fn user_dict_error_status(e: &dictionary::UserDictError) -> Status {
+    use dictionary::UserDictError;
+    match e {
+        UserDictError::NotLoaded => Status::ServiceUnavailable,
+        UserDictError::InvalidEntry(_) => Status::BadRequest,
+        UserDictError::AlreadyExists(_) => Status::Conflict,
+        UserDictError::NotFound(_) => Status::NotFound,
+        UserDictError::Io(_) => Status::InternalServerError,
+    }
+}
+
+/// Handles a POST to /api/userdict to add a new entry to the user dictionary.
+#[post("/api/userdict", data = "<req>")]
+fn userdict_add(
+    state: &State<ServerState>,
+    _auth: EditAuthorized,
+    req: Form<UserDictEntryInput<'_>>,
+) -> (Status, String) {
+    let mut shelf = state.shelf.lock().unwrap();
+    user_dict_response(shelf.add_user_entry(req.entry), Status::Created, "added")
+}
+
+/// Handles a PUT to /api/userdict to replace the phonemes of a user dictionary entry.
+#[put("/api/userdict", data = "<req>")]
+fn userdict_update(
+    state: &State<ServerState>,
+    _auth: EditAuthorized,
+    req: Form<UserDictEntryInput<'_>>,
+) -> (Status, String) {
+    let mut shelf = state.shelf.lock().unwrap();
+    user_dict_response(shelf.update_user_entry(req.entry), Status::Ok, "updated")
+}
+
+/// Handles a DELETE to /api/userdict/<key> to remove an entry, e.g. "word" or "word(2)".
+#[delete("/api/userdict/<key>")]
+fn userdict_remove(
+    state: &State<ServerState>,
+    _auth: EditAuthorized,
+    key: &str,
+) -> (Status, String) {
+    let mut shelf = state.shelf.lock().unwrap();
+    user_dict_response(shelf.remove_user_entry(key), Status::Ok, "removed")
+}
+
+/// Describes the parameters and types for /analyze POST requests.
+///
+/// This is used by Rocket to validate incoming requests and to pass the values to
+/// the `analyze` handler.
+#[derive(FromForm)]
+struct AnalyzeRequest<'a> {
+    /// The text snippet to analyze.
+    text: &'a str,
+    /// The name of the dialect to check rhymes and syllables in, e.g. "british".
+    dialect: Option<&'a str>,
+    /// How to write pronunciations: "arpabet" (the default) or "ipa".
+    notation: Option<&'a str>,
+}
+
+/// Handler for a POST form to analyze a block of prose / snippet.
+#[post("/analyze", data = "<req>")]
+fn analyze(state: &State<ServerState>, req: Form<AnalyzeRequest>) -> Template {
+    let dialect = state.dialect_or_default(req.dialect);
+    render_analysis(state, req.text, dialect, parse_notation(req.notation), None)
+}
+
+/// Returns the notation named in a request, or the default if it's missing or unknown.
+fn parse_notation(name: Option<&str>) -> Notation {
+    name.and_then(|n| n.parse().ok()).unwrap_or_default()
+}
+
+/// The form from an unknown word's panel on the /analyze page, to add it to the user dictionary.
+#[derive(FromForm)]
+struct AcceptWordRequest<'a> {
+    /// The text snippet to analyze again after adding the word.
+    text: &'a str,
+    /// The unknown word.
+    word: &'a str,
+    /// The accepted pronunciation, e.g. "W ER1 D".
+    phonemes: &'a str,
+    /// The server's edit token.
+    token: &'a str,
+    /// The dialect the text was analyzed in.
+    dialect: Option<&'a str>,
+    /// The notation the analysis was shown in.
+    notation: Option<&'a str>,
+}
+
+/// Handler for accepting a pronunciation for an unknown word on the /analyze page.
+///
+/// This adds the word to the user dictionary and then analyzes the text again.
+#[post("/analyze/accept", data = "<req>")]
+fn analyze_accept(state: &State<ServerState>, req: Form<AcceptWordRequest>) -> (Status, Template) {
+    let (status, message) = match state.check_edit_token(Some(req.token)) {
+        Err((status, reason)) => (status, format!("Could not add {}: {}.", req.word, reason)),
+        Ok(()) => {
+            let mut shelf = state.shelf.lock().unwrap();
+            let line = format!("{} {}", req.word, req.phonemes);
+            match shelf.add_user_entry(&line) {
+                Ok(entry) => (
+                    Status::Ok,
+                    format!("Added {} ({}).", entry.dict_key(), entry.phonemes),
+                ),
+                Err(e) => (
+                    user_dict_error_status(&e),
+                    format!("Could not add {}: {}.", req.word, e),
+                ),
+            }
+        }
+    };
+    let dialect = state.dialect_or_default(req.dialect);
+    let notation = parse_notation(req.notation);
+    (
+        status,
+        render_analysis(state, req.text, dialect, notation, Some(message)),
+    )
+}
+
+/// Analyzes `text` in `dialect` and renders the analyze page, with pronunciations in `notation`
+/// and an optional status `message`.
+fn render_analysis(
+    state: &ServerState,
+    text: &str,
+    dialect: Dialect,
+    notation: Notation,
+    message: Option<String>,
+) -> Template {
+    let shelf = state.shelf.lock().unwrap();
+    let dict = shelf.over_all();
+
+    // Parse the input and break it into one or more stanzas.
+    let stanzas = snippet::get_stanzas_from_text(text, dict);
+
+    let mut data = AnalyzeTemplateData {
+        user_input: text,
+        stanzas: Vec::with_capacity(stanzas.len()),
+        unknown_words: vec![],
+        editable: state.edit_token.is_some(),
+        message,
+        dialect: dialect.name(),
+        dialects: Dialect::ALL.iter().map(DialectTemplateData::new).collect(),
+        ipa: notation == Notation::Ipa,
+    };
+
+    for stanza in &stanzas {
+        for word in stanza.unknown_words() {
+            if !data.unknown_words.iter().any(|u| u.word == word) {
+                data.unknown_words
+                    .push(UnknownWordTemplateData::new(&word, dict));
+            }
+        }
+        data.stanzas.push(AnalysisTemplateData {
+            // The best interpretation has the phonemes of each word, the classification, and any
+            // errors/warnings from the analysis.
+            best: InterpretationTemplateData::new(&stanza.analyze(dialect), notation),
+            // The stanza has every word variant and their phonemes, so that users can see where
+            // the analysis may have been incorrect.
+            stanza: StanzaTemplateData::new(stanza, notation),
+        });
+    }
+    Template::render("analyze", data)
+}
+
+/// A data container for populating the analyze template.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct AnalyzeTemplateData<'a> {
+    /// The user input, copied to the output to pre-fill the form box.
+    user_input: &'a str,
+
+    /// The analysis of each stanza found in the input.
+    stanzas: Vec<AnalysisTemplateData>,
+
+    /// The unknown words in the input, each with some candidate pronunciations.
+    unknown_words: Vec<UnknownWordTemplateData>,
+
+    /// Whether the server allows adding words to the user dictionary.
+    editable: bool,
+
+    /// The result of the last edit, if any, e.g. "Added zed (Z EH1 D)."
+    message: Option<String>,
+
+    /// The name of the dialect the text was analyzed in, e.g. "british".
+    dialect: &'static str,
+
+    /// The dialects to choose from.
+    dialects: Vec<DialectTemplateData>,
+
+    /// Whether pronunciations are written in IPA.
+    ipa: bool,
+}
+
+/// A dialect to choose from on the analyze page.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct DialectTemplateData {
+    /// The name used in the form, e.g. "british".
+    name: &'static str,
+    /// The name shown to users, e.g. "British English".
+    label: String,
+}
+
+impl DialectTemplateData {
+    fn new(dialect: &Dialect) -> DialectTemplateData {
+        DialectTemplateData {
+            name: dialect.name(),
+            label: dialect.to_string(),
+        }
+    }
+}
+
+/// An unknown word and its candidate pronunciations, for the panel on the analyze page.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct UnknownWordTemplateData {
+    word: String,
+    candidates: Vec<CandidateTemplateData>,
+}
+
+impl UnknownWordTemplateData {
+    fn new(word: &str, dict: &dyn dictionary::Dictionary) -> UnknownWordTemplateData {
+        UnknownWordTemplateData {
+            word: word.to_string(),
+            candidates: guess::local_candidates(word, dict)
+                .iter()
+                .map(|c| CandidateTemplateData::new(word, c, dict))
+                .collect(),
+        }
+    }
+}
+
+/// A template-oriented version of `guess::Candidate`, with a preview of what it implies.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct CandidateTemplateData {
+    /// Where the candidate came from, e.g. "morphology".
+    source: guess::CandidateSource,
+    /// How the candidate was derived, e.g. "flower + -ing". May be empty.
+    note: String,
+    phonemes: String,
+    syllables: i32,
+    /// The best few rhymes for the candidate.
+    rhymes: Vec<String>,
+    /// Whether there are more rhymes than are listed.
+    more_rhymes: bool,
+}
+
+/// How many rhymes to preview for each candidate.
+const NUM_RHYMES_TO_PREVIEW: usize = 6;
+
+impl CandidateTemplateData {
+    fn new(
+        word: &str,
+        candidate: &guess::Candidate,
+        dict: &dyn dictionary::Dictionary,
+    ) -> CandidateTemplateData {
+        let similar = dict.similar_to_phonemes(&candidate.phonemes, Some(word));
+        let words = &similar.groups[0].words;
+        CandidateTemplateData {
+            source: candidate.source,
+            note: candidate.note.clone(),
+            phonemes: candidate.phonemes.to_string(),
+            syllables: candidate.phonemes.num_syllables(),
+            rhymes: words
+                .iter()
+                .take(NUM_RHYMES_TO_PREVIEW)
+                .map(|w| w.word.clone())
+                .collect(),
+            more_rhymes: words.len() > NUM_RHYMES_TO_PREVIEW,
+        }
+    }
+}
+
+/// A data container for the candidates template, which renders a list of candidates.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct CandidatesTemplateData {
+    candidates: Vec<CandidateTemplateData>,
+    /// A message to show instead of (or as well as) the candidates.
+    error: Option<String>,
+}
+
+/// Handler for previewing a pronunciation typed in for an unknown word on the analyze page.
+#[get("/api/preview?<word>&<phonemes>")]
+fn api_preview(state: &State<ServerState>, word: &str, phonemes: &str) -> Template {
+    let shelf = state.shelf.lock().unwrap();
+    let mut data = CandidatesTemplateData {
+        candidates: vec![],
+        error: None,
+    };
+    match dictionary::parse_user_entry(&format!("{} {}", word, phonemes)) {
+        Ok(entry) => {
+            let candidate = guess::Candidate {
+                source: guess::CandidateSource::Edited,
+                phonemes: entry.phonemes,
+                note: String::new(),
+            };
+            data.candidates.push(CandidateTemplateData::new(
+                word,
+                &candidate,
+                shelf.over_all(),
+            ));
+        }
+        Err(e) => data.error = Some(e.to_string()),
+    }
+    Template::render("candidates", data)
+}
+
+/// Handler for fetching candidate pronunciations for an unknown word from Datamuse.
+#[get("/api/candidates?<word>")]
+async fn api_candidates(state: &State<ServerState>, word: &str) -> Template {
+    let datamuse = client::datamuse::Client::new();
+    let fetched = datamuse.get_phonemes(word).await;
+
+    let shelf = state.shelf.lock().unwrap();
+    let mut data = CandidatesTemplateData {
+        candidates: vec![],
+        error: None,
+    };
+    match fetched {
+        Ok(Some(entry)) if !entry.phonemes.phonemes.is_empty() => {
+            let candidate = guess::Candidate {
+                source: guess::CandidateSource::Datamuse,
+                phonemes: entry.phonemes,
+                note: String::new(),
+            };
+            data.candidates.push(CandidateTemplateData::new(
+                word,
+                &candidate,
+                shelf.over_all(),
+            ));
+        }
+        Ok(_) => data.error = Some(format!("Datamuse has nothing for {}.", word)),
+        Err(e) => data.error = Some(format!("Fetching {} from Datamuse failed: {}", word, e)),
+    }
+    Template::render("candidates", data)
+}
+
+/// The analysis of one `snippet::Stanza`, rendered by the `analysis` macros.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct AnalysisTemplateData {
+    /// The best interpretation found by `Stanza::analyze()`.
+    best: InterpretationTemplateData,
+
+    /// The stanza itself, with all of the dictionary entries for each word.
+    stanza: StanzaTemplateData,
+}
+
+/// A template-oriented version of `snippet::BestInterpretation`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct InterpretationTemplateData {
+    /// The lines of the best interpretation.
+    lines: Vec<LineViewTemplateData>,
+
+    /// The name of the validator, e.g. "Haiku".
+    validator: String,
+
+    /// Any errors found, in sorted order.
+    errors: Vec<ErrorTemplateData>,
+
+    /// Unlikely readings of heteronyms that the interpretation relies on.
+    warnings: Vec<ErrorTemplateData>,
+
+    /// The dialect that rhymes and syllables were checked in, e.g. "British English", unless
+    /// it's the default.
+    dialect: Option<String>,
+}
+
+impl InterpretationTemplateData {
+    fn new(
+        interpretation: &snippet::BestInterpretation,
+        notation: Notation,
+    ) -> InterpretationTemplateData {
+        let view: &snippet::StanzaView = interpretation.best.as_ref().unwrap();
+        InterpretationTemplateData {
+            lines: view
+                .lines
+                .iter()
+                .map(|line| LineViewTemplateData::new(line, notation))
+                .collect(),
+            validator: interpretation.validator.clone(),
+            errors: interpretation
+                .errors
+                .iter()
+                .map(ErrorTemplateData::new)
+                .collect(),
+            warnings: interpretation
+                .warnings
+                .iter()
+                .map(ErrorTemplateData::new)
+                .collect(),
+            dialect: if interpretation.dialect == Dialect::default() {
+                None
+            } else {
+                Some(interpretation.dialect.to_string())
+            },
+        }
+    }
+}
+
+/// A template-oriented version of `snippet::ClassifyError`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ErrorTemplateData {
+    /// Either "stanza" or "line", used to pick the CSS class.
+    kind: &'static str,
+
+    /// The full error message, e.g. "Line 3: line 3 has 2 syllables but should have 5."
+    message: String,
+}
+
+impl ErrorTemplateData {
+    fn new(error: &snippet::ClassifyError) -> ErrorTemplateData {
+        use snippet::ClassifyError::*;
+        ErrorTemplateData {
+            kind: match error {
+                StanzaError(_) => "stanza",
+                LineError(_, _) => "line",
+            },
+            message: error.to_string(),
+        }
+    }
+}
+
+/// A template-oriented version of `snippet::LineView`, explaining which words were selected.
+///
+/// ```
+/// 01  1. Humans envy my
+///      . HU·mans          EN·vy        my
+///      . HH Y UW1 · M AH0 N Z  EH1 N · V IY0  M AY1
+/// ```
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct LineViewTemplateData {
+    /// The line number and index in the stanza, pre-formatted, e.g. "01  1.".
+    label: String,
+
+    /// The original text of the line.
+    raw_text: String,
+
+    /// The selected entry for each word on the line.
+    words: Vec<WordViewTemplateData>,
+}
+
+/// One word of a `LineViewTemplateData`, padded so that the columns line up.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct WordViewTemplateData {
+    /// The written syllables of the word (or the whole word, if missing).
+    syllables: Vec<WrittenSyllableTemplateData>,
+
+    /// The spaces needed after the syllables to fill the column.
+    padding: String,
+
+    /// The syllabified phonemes of the selected entry (or a centered "?", if missing), padded
+    /// with ' '.
+    phonemes: String,
+
+    /// Whether the word is missing from the dictionary.
+    missing: bool,
+}
+
+/// A template-oriented version of `spelling::WrittenSyllable`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct WrittenSyllableTemplateData {
+    text: String,
+    stressed: bool,
+
+    /// Whether a "·" follows, to show where the next syllable starts.
+    separated: bool,
+}
+
+impl LineViewTemplateData {
+    fn new(view: &snippet::LineView, notation: Notation) -> LineViewTemplateData {
+        let num_tokens = view.num_words();
+        let mut words = Vec::with_capacity(num_tokens);
+
+        for i in 0..num_tokens {
+            let (syllables, phonemes, missing) = match (view.get_entry(i), view.get_hyphenation(i))
+            {
+                (Some(e), Some(hyphenation)) => {
+                    let num_syllables = hyphenation.syllables.len();
+                    let syllables = hyphenation
+                        .syllables
+                        .into_iter()
+                        .enumerate()
+                        .map(|(k, s)| WrittenSyllableTemplateData {
+                            separated: k + 1 < num_syllables && s.needs_separator(),
+                            text: s.text,
+                            stressed: s.stressed,
+                        })
+                        .collect();
+                    (syllables, notation.format_syllables(&e.phonemes), false)
+                }
+                _ => {
+                    let token_text = view.get_text(i);
+                    // Centers the ?.
+                    let phonemes = format!("{: ^1$}", "?", token_text.chars().count());
+                    let syllables = vec![WrittenSyllableTemplateData {
+                        text: token_text.to_string(),
+                        stressed: false,
+                        separated: false,
+                    }];
+                    (syllables, phonemes, true)
+                }
+            };
+            let written_width = syllables
+                .iter()
+                .map(|s| s.text.chars().count() + s.separated as usize)
+                .sum::<usize>();
+            let phoneme_width = phonemes.chars().count();
+            let width = written_width.max(phoneme_width);
+            words.push(WordViewTemplateData {
+                syllables,
+                padding: " ".repeat(width - written_width),
+                phonemes: format!("{:<1$}", phonemes, width),
+                missing,
+            });
+        }
+
+        LineViewTemplateData {
+            label: format!("{:02} {:2}.", view.num(), view.index() + 1),
+            raw_text: view.raw_text().to_string(),
+            words,
+        }
+    }
+}
+
+/// A template-oriented version of `snippet::Stanza`.
+///
+/// This shows the words in the `Stanza` with the corresponding dictionary entries, to aid
+/// in checking the dictionaries have the correct words and interpretations available.
+///
+/// ```
+/// // https://www.mcsweeneys.net/articles/haiku-a-bitter-duck-might-write
+///
+/// Humans envy my
+///         humans: (HH Y UW1 M AH0 N Z); variant=1, syllables=2
+///               : (Y UW1 M AH0 N Z); variant=2, syllables=2
+///         envy: (EH1 N V IY0); variant=1, syllables=2
+///         my: (M AY1); variant=1, syllables=1
+/// ```
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct StanzaTemplateData {
+    /// The title of the stanza, if any.
+    title: Option<String>,
+
+    /// The lines of the stanza.
+    lines: Vec<LineTemplateData>,
+
+    /// Whether any of the words are missing from the dictionary.
+    has_unknown_words: bool,
+}
+
+/// A template-oriented version of `snippet::Line`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct LineTemplateData {
+    /// The original text of the line.
+    raw_text: String,
+
+    /// Each word on the line.
+    tokens: Vec<TokenTemplateData>,
+}
+
+/// A template-oriented version of `snippet::Token`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct TokenTemplateData {
+    /// The normalized text of the word.
+    text: String,
+
+    /// Blank space as wide as `text`, for aligning the second and later entries.
+    indent: String,
+
+    /// All of the dictionary entries for the word. Empty if it is missing.
+    entries: Vec<EntryTemplateData>,
+}
+
+/// A template-oriented version of `dictionary::Entry`.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct EntryTemplateData {
+    /// Pre-serialized phoneme sequence, e.g. "HH AH0 L OW1" or "həˈloʊ".
+    phonemes: String,
+
+    /// The variant number of the entry.
+    variant: i32,
+
+    /// The number of syllables in the entry.
+    syllables: i32,
+
+    /// The dictionary the entry came from, e.g. "./userdict.dict", if known.
+    source: Option<String>,
+
+    /// Whether another entry is more likely in context. See `snippet::Token::is_unlikely`.
+    unlikely: bool,
+}
+
+impl StanzaTemplateData {
+    fn new(stanza: &snippet::Stanza, notation: Notation) -> StanzaTemplateData {
+        let mut lines = Vec::with_capacity(stanza.lines.len());
+        for line in &stanza.lines {
+            let mut tokens = Vec::with_capacity(line.tokens.len());
+            for token in &line.tokens {
+                let mut entries = vec![];
+                if let Some(v) = token.entry {
+                    for (i, entry) in v.iter().enumerate() {
+                        entries.push(EntryTemplateData {
+                            phonemes: notation.format(&entry.phonemes),
+                            variant: entry.variant,
+                            syllables: entry.num_syllables(),
+                            source: entry.source.as_deref().map(String::from),
+                            unlikely: token.is_unlikely(i),
+                        });
+                    }
+                }
+                tokens.push(TokenTemplateData {
+                    text: token.text.clone(),
+                    indent: " ".repeat(token.text.len()),
+                    entries,
+                });
+            }
+            lines.push(LineTemplateData {
+                raw_text: line.raw_text.clone(),
+                tokens,
+            });
+        }
+        StanzaTemplateData {
+            title: stanza.title.clone(),
+            lines,
+            has_unknown_words: stanza.has_unknown_words(),
+        }
+    }
+}
+
+/// Handler for the root (/) page.
+#[rocket::get("/")]
+fn index() -> Template {
+    let context = HashMap::<&str, &str>::new();
+    return Template::render("index", context);
+}
+
+/// Assembles the Rocket instance with all of the routes and state, ready to launch.
+fn build_rocket(
+    shelf: dictionary::Shelf,
+    edit_token: Option<String>,
+    dialect: Dialect,
+) -> rocket::Rocket<rocket::Build> {
+    rocket::build()
+        .manage(ServerState {
+            shelf: Mutex::new(shelf),
+            edit_token,
+            dialect,
+        })
+        .attach(Template::fairing())
+        .mount(
+            "/",
+            routes![
+                index,
+                lookup,
+                analyze,
+                api_lookup,
+                search,
+                api_search,
+                api_soundalikes,
+                api_mosaic,
+                meter,
+                datamuse,
+                analyze_accept,
+                api_preview,
+                api_candidates,
+                userdict_add,
+                userdict_update,
+                userdict_remove
+            ],
+        )
+        .mount("/static", rocket::fs::FileServer::from("static/"))
+}
+
+/// Starts the Rocket HTTP server and awaits until the server shuts down.
+///
+/// Args:
+///
 /// * `shelf` - An already-initialized collection of dictionaries.
-pub async fn run(shelf: dictionary::Shelf) {
+/// * `edit_token` - The bearer token that allows editing the user dictionary through
+///   `/api/userdict`. If None, the user dictionary is read-only.
+/// * `dialect` - The dialect to analyze text in, unless a request picks another.
+pub async fn run(shelf: dictionary::Shelf, edit_token: Option<String>, dialect: Dialect) {
     println!("*****************************************************************");
     println!("*                                                               *");
     println!("*  Launching Web Server.                                        *");
@@ -455,18 +1722,987 @@ pub async fn run(shelf: dictionary::Shelf) {
     println!("*                                                               *");
     println!("*****************************************************************");
 
-    let result = rocket::build()
-        .manage(ServerState {
-            shelf: Mutex::new(shelf),
-        })
-        .attach(Template::fairing())
-        .mount("/", routes![index, lookup, analyze, api_lookup, datamuse])
-        .mount("/static", rocket::fs::FileServer::from("static/"))
-        .launch()
-        .await;
+    let result = build_rocket(shelf, edit_token, dialect).launch().await;
     if let Err(e) = result {
         println!("***** Failed to launch web server. *****");
         // Drop the error to get a Rocket-formatted panic.
         drop(e);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::LoadMode;
+    use crate::poet::frequency::Frequencies;
+    use crate::poet::lexicon::Format;
+    use crate::poet::metadata;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+
+    /// Creates a test client for a server with a small dictionary, including a hostile entry.
+    fn test_client() -> Client {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "red R EH1 D",
+            "read R EH1 D",
+            "<b>bed</b> B EH1 D",
+            "roses R OW1 Z AH0 Z",
+            "are AA1 R",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance")
+    }
+
+    /// Issues a POST to `/analyze` with `text` as the form input and returns the body.
+    fn post_analyze(client: &Client, text: &str) -> String {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("text", text)
+            .finish();
+        let response = client
+            .post("/analyze")
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_string().unwrap()
+    }
+
+    #[test]
+    fn analyze_escapes_hostile_text() {
+        let client = test_client();
+        let html = post_analyze(
+            &client,
+            "<i>Title</i>\n\n\
+             Roses are <script>alert(1)</script>\n\
+             red <img src=x onerror=alert(2)>\n",
+        );
+        assert!(!html.contains("<script>alert"));
+        assert!(!html.contains("<img src=x"));
+        assert!(!html.contains("<i>Title"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;&#x2F;script&gt;"));
+        assert!(html.contains("&lt;img src=x onerror=alert(2)&gt;"));
+        assert!(html.contains("&lt;i&gt;Title&lt;&#x2F;i&gt;"));
+    }
+
+    #[test]
+    fn analyze_warns_about_unlikely_readings() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "a AH0",
+            "bed B EH1 D",
+            "cat K AE1 T",
+            "read R IY1 D",
+            "read(2) R EH1 D",
+            "will W IH1 L",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let mut text = String::from("a a a a a a a a will read\n");
+        text.push_str("a a a a a a a a a cat\n");
+        text.push_str("a a a a a a a a a bed\n");
+        text.push_str(&"a a a a a a a a a cat\n".repeat(11));
+        let html = post_analyze(&client, &text);
+        assert!(html.contains("What a great Shakespearean Sonnet!"));
+        assert!(html.contains("Unlikely readings:"));
+        assert!(html.contains(
+            "Line 1: line 1 only fits with &quot;read&quot; as R EH1 D, though R IY1 D is more \
+             likely here."
+        ));
+        assert!(html.contains("variant=2, syllables=1; unlikely here"));
+    }
+
+    #[test]
+    fn analyze_checks_rhymes_in_the_chosen_dialect() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec!["a AH0", "cat K AE1 T", "law L AO1", "lore L AO1 R"]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::British))
+            .expect("valid rocket instance");
+
+        let mut text = String::from("a a a a a a a a a lore\n");
+        text.push_str("a a a a a a a a a cat\n");
+        text.push_str("a a a a a a a a a law\n");
+        text.push_str(&"a a a a a a a a a cat\n".repeat(11));
+        let analyze = |dialect: Option<&str>| {
+            let mut body = format!("text={}", text.replace('\n', "%0A").replace(' ', "+"));
+            if let Some(dialect) = dialect {
+                body.push_str(&format!("&dialect={}", dialect));
+            }
+            client
+                .post("/analyze")
+                .header(ContentType::Form)
+                .body(body)
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        // The server's dialect is the default.
+        let html = analyze(None);
+        assert!(html.contains("What a great Shakespearean Sonnet!"));
+        assert!(html.contains("Rhymes and syllables are checked as in British English."));
+        assert!(html.contains("<option value=\"british\" selected>British English</option>"));
+
+        let html = analyze(Some("general-american"));
+        assert!(html.contains("don&#x27;t rhyme?"));
+        assert!(!html.contains("checked as in"));
+        assert!(html.contains("<option value=\"general-american\" selected>"));
+
+        let html = analyze(Some("canadian"));
+        assert!(html.contains("don&#x27;t rhyme in Canadian English?"));
+
+        // Unknown dialects fall back to the default.
+        let html = analyze(Some("klingon"));
+        assert!(html.contains("What a great Shakespearean Sonnet!"));
+    }
+
+    #[test]
+    fn analyze_shows_ipa() {
+        let client = test_client();
+        let analyze = |body: &str| {
+            client
+                .post("/analyze")
+                .header(ContentType::Form)
+                .body(body)
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = analyze("text=roses+are+red%0Ared+roses+are&notation=ipa");
+        assert!(html.contains("ˈɹoʊzəz"));
+        assert!(html.contains("name=\"notation\" value=\"ipa\" checked"));
+
+        let html = analyze("text=roses+are+red%0Ared+roses+are");
+        assert!(html.contains("R OW1 Z AH0 Z"));
+        assert!(!html.contains("ˈɹoʊzəz"));
+    }
+
+    #[test]
+    fn analyze_matches_snapshot() {
+        // The snapshot was captured from the hand-built HTML that preceded the templates.
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "a AH0",
+            "a(2) EY1",
+            "are AA1 R",
+            "blue B L UW1",
+            "crumbs K R AH1 M Z",
+            "duck D AH1 K",
+            "for F AO1 R",
+            "for(2) F ER0",
+            "of AH1 V",
+            "quacking K W AE1 K IH0 NG",
+            "red R EH1 D",
+            "roses R OW1 Z AH0 Z",
+            "searching S ER1 CH IH0 NG",
+            "streets S T R IY1 T S",
+            "the DH AH0",
+            "the(2) DH AH1",
+            "the(3) DH IY0",
+            "violets V AY1 AH0 L AH0 T S",
+            "walked W AO1 K T",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = post_analyze(
+            &client,
+            "A duck walked the streets\n\
+             Searching for crumbs of <crackers>\n\
+             Quacking\n\
+             \n\
+             Valentine\n\
+             \n\
+             Roses are red\n\
+             Violets are blue\n",
+        );
+        let start = html.find("<div id=\"prose\">").unwrap() + "<div id=\"prose\">".len();
+        let end = start + html[start..].find("</div>").unwrap();
+        assert_eq!(
+            &html[start..end],
+            include_str!("testdata/analyze_prose.html")
+        );
+    }
+
+    #[test]
+    fn api_lookup_shows_every_variant() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "wind W IH1 N D",
+            "wind(2) W AY1 N D",
+            "grinned G R IH1 N D",
+            "kind K AY1 N D",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = client
+            .get("/api/lookup?term=wind")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("wind (<code>W IH1 N D</code>)"));
+        assert!(html.contains("wind(2) (<code>W AY1 N D</code>)"));
+        let first = html.find("wind (").unwrap();
+        let second = html.find("wind(2) (").unwrap();
+        assert!(first < html.find("grinned").unwrap());
+        assert!(html.find("grinned").unwrap() < second);
+        assert!(second < html.find("kind").unwrap());
+
+        let html = client
+            .get("/lookup?term=wind")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("W IH1 N D"));
+        assert!(html.contains("W AY1 N D"));
+        assert!(html.contains("grinned"));
+        assert!(html.contains("kind"));
+    }
+
+    /// Creates a test client with "apple" and some words that are similar to it.
+    fn apple_client() -> Client {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "apple AE1 P AH0 L",
+            "capital K AE1 P IH0 T AH0 L",
+            "chapel CH AE1 P AH0 L",
+            "grapple G R AE1 P AH0 L",
+            "label L EY1 B AH0 L",
+            "multiple M AH1 L T AH0 P AH0 L",
+            "people P IY1 P AH0 L",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance")
+    }
+
+    #[test]
+    fn api_lookup_filters_sorts_and_pages() {
+        let client = apple_client();
+        let get = |url: &str| {
+            client
+                .get(url.to_string())
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = get("/api/lookup?term=apple&min_syllables=3&sort=word");
+        assert!(html.contains("with 2 similar words like:<br><b>capital</b>, <b>multiple</b>, "));
+
+        let html = get("/api/lookup?term=apple&kind=perfect&min_score=100");
+        assert!(html.contains("with 2 similar words like:<br><b>chapel</b>, <b>grapple</b>, "));
+
+        let html = get("/api/lookup?term=apple&stress=100&sort=word");
+        assert!(html.contains("<b>capital</b>, <b>multiple</b>, <br>"));
+
+        let html = get("/api/lookup?term=apple&sort=word&offset=1&limit=2");
+        assert!(html.contains("with 6 similar words like:<br><b>chapel</b>, <b>grapple</b>, ..."));
+    }
+
+    #[test]
+    fn lookup_filters_sorts_and_pages() {
+        let client = apple_client();
+        let response = client
+            .get("/lookup?term=apple&sort=word&offset=2&limit=2")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let html = response.into_string().unwrap();
+        assert!(html.contains("Showing 3&ndash;4 of 6 potential results."));
+        // The sections after the rhymes, e.g. the minimal pairs, aren't paged.
+        let rhymes = &html[..html.find("<h4>").unwrap_or(html.len())];
+        assert!(!rhymes.contains("<td>chapel</td>"));
+        let grapple = rhymes.find("<td>grapple</td>").unwrap();
+        let label = rhymes.find("<td>label</td>").unwrap();
+        assert!(grapple < label);
+        assert!(!rhymes.contains("<td>multiple</td>"));
+        assert!(html.contains("sort=word&amp;offset=0&amp;limit=2\">&laquo; previous"));
+        assert!(html.contains("sort=word&amp;offset=4&amp;limit=2\">next &raquo;"));
+
+        // Empty values from the form should be ignored.
+        let response = client
+            .get("/lookup?term=apple&min_syllables=&max_syllables=&min_score=&kind=&stress=&sort=score&limit=")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let html = response.into_string().unwrap();
+        assert!(html.contains("Showing 1&ndash;6 of 6 potential results."));
+        assert!(html.contains("name=\"min_syllables\" type=number min=1 value=\"\""));
+
+        // A limit of 0 would never advance to the next page.
+        let html = client
+            .get("/lookup?term=apple&sort=word&limit=0")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("Showing 1&ndash;1 of 6 potential results."));
+        assert!(html.contains("sort=word&amp;offset=1&amp;limit=1\">next &raquo;"));
+    }
+
+    #[test]
+    fn lookup_filters_and_sorts_by_frequency() {
+        let html = apple_client()
+            .get("/lookup?term=apple")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(!html.contains("name=\"frequency\""));
+        assert!(!html.contains("Frequency</a>"));
+
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "apple AE1 P AH0 L",
+            "chapel CH AE1 P AH0 L",
+            "grapple G R AE1 P AH0 L",
+            "people P IY1 P AH0 L",
+            "multiple M AH1 L T AH0 P AH0 L",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let text = "apple 100\npeople 500\nmultiple 100\nchapel 5\nthe 999295\n";
+        let frequencies = Frequencies::from_reader("test", text.as_bytes(), LoadMode::Strict);
+        shelf.set_frequencies(frequencies.unwrap());
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = client
+            .get("/lookup?term=apple&frequency=common&sort=frequency")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<option value=\"common\" selected>common words only</option>"));
+        assert!(html.contains("Frequency</a> &darr;"));
+        let rhymes = &html[..html.find("<h4>").unwrap_or(html.len())];
+        let people = rhymes.find("<td>people</td>").unwrap();
+        let multiple = rhymes.find("<td>multiple</td>").unwrap();
+        assert!(people < multiple);
+        assert!(rhymes.contains("<td>common</td>"));
+        assert!(!rhymes.contains("<td>chapel</td>"));
+        assert!(!rhymes.contains("<td>grapple</td>"));
+
+        let html = client
+            .get("/lookup?term=apple&frequency=rare")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let rhymes = &html[..html.find("<h4>").unwrap_or(html.len())];
+        assert!(rhymes.contains("<td>grapple</td>"));
+        assert!(!rhymes.contains("<td>people</td>"));
+
+        // Frequencies from metadata count too, even if the term itself has none.
+        let path =
+            std::env::temp_dir().join(format!("poet-{}-server-freq.dict", std::process::id()));
+        let path = path.to_str().unwrap();
+        let sidecar = metadata::sidecar_path(path);
+        std::fs::write(
+            path,
+            "light L AY1 T
+kite K AY1 T
+",
+        )
+        .unwrap();
+        std::fs::write(
+            &sidecar,
+            "light n f:120
+kite n
+",
+        )
+        .unwrap();
+        let mut shelf = dictionary::Shelf::new();
+        shelf.init_dictionary(path, Format::Cmudict).unwrap();
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+        let html = client
+            .get("/lookup?term=kite")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("name=\"frequency\""));
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+
+    #[test]
+    fn lookup_filters_by_part_of_speech() {
+        let html = apple_client()
+            .get("/lookup?term=apple")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(!html.contains("name=\"pos\""));
+        assert!(!html.contains("Part of speech"));
+
+        let path =
+            std::env::temp_dir().join(format!("poet-{}-server-pos.dict", std::process::id()));
+        let path = path.to_str().unwrap();
+        let sidecar = metadata::sidecar_path(path);
+        std::fs::write(path, "light L AY1 T\nbright B R AY1 T\nkite K AY1 T\n").unwrap();
+        std::fs::write(&sidecar, "light n v adj\nbright adj\nkite n\n").unwrap();
+        let mut shelf = dictionary::Shelf::new();
+        shelf.init_dictionary(path, Format::Cmudict).unwrap();
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = client
+            .get("/lookup?term=light&pos=noun")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<option value=\"noun\" selected>nouns only</option>"));
+        assert!(html.contains("(noun, verb, adjective, 1 syllables"));
+        assert!(html.contains("<th>Part of speech</th>"));
+        assert!(html.contains("<td>kite</td>"));
+        assert!(html.contains("<td>noun</td>"));
+        assert!(!html.contains("<td>bright</td>"));
+        assert!(html.contains("pos=noun&amp;sort=word"));
+
+        let html = client
+            .get("/api/lookup?term=light&pos=adj")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("with 1 similar words like:<br><b>bright</b>, <br>"));
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+
+    #[test]
+    fn lookup_shows_near_rhymes() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "shape SH EY1 P",
+            "cape K EY1 P",
+            "break B R EY1 K",
+            "dog D AO1 G",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = client
+            .get("/lookup?term=shape")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let near = html.find("<h4>Near rhymes</h4>").unwrap();
+        assert!(html.find("<td>cape</td>").unwrap() < near);
+        assert!(html[near..].contains("<td>break</td>"));
+        assert!(html[near..].contains("<td>91</td>"));
+        assert!(!html.contains("<td>dog</td>"));
+    }
+
+    #[test]
+    fn lookup_shows_alliterations() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "phantom F AE1 N T AH0 M",
+            "fantastic F AE0 N T AE1 S T IH0 K",
+            "photo F OW1 T OW2",
+            "dog D AO1 G",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+
+        let html = client
+            .get("/lookup?term=phantom")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let alliterations = html.find("<h4>Alliterates with</h4>").unwrap();
+        let fantastic = html.find("<td>fantastic</td>").unwrap();
+        assert!(alliterations < fantastic);
+        assert!(fantastic < html.find("<td>photo</td>").unwrap());
+        assert!(!html.contains("<td>dog</td>"));
+
+        let html = client
+            .get("/lookup?term=phantom&alliterate=2")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<td>fantastic</td>"));
+        assert!(!html.contains("<td>photo</td>"));
+    }
+
+    #[test]
+    fn lookup_shows_homophones_and_minimal_pairs() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "pair P EH1 R",
+            "pear P EH1 R",
+            "pare P EH1 R",
+            "par P AA1 R",
+            "<i>pa</i> P AA1",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+        let get = |url: &str| {
+            client
+                .get(url.to_string())
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = get("/lookup?term=pair");
+        let homophones = html.find("<h4>Homophones</h4>").unwrap();
+        let minimal_pairs = html.find("<h4>Minimal pairs</h4>").unwrap();
+        assert!(html[homophones..minimal_pairs].contains("<td>pear</td>"));
+        assert!(!html[homophones..minimal_pairs].contains("<td>par</td>"));
+        assert!(html[minimal_pairs..].contains("<td>par</td>"));
+
+        let html = get("/api/soundalikes?term=pair&limit=1");
+        assert_eq!(
+            html,
+            "pair (<code>P EH1 R</code>) has 2 homophones: <b>pare</b>, ...; \
+             1 minimal pairs: <b>par</b><br>"
+        );
+        let html = get("/api/soundalikes?term=par");
+        assert!(
+            html.contains("0 homophones: ; 4 minimal pairs: <b>&lt;i&gt;pa&lt;&#x2F;i&gt;</b>, ")
+        );
+        let html = get("/api/soundalikes?term=%3Cb%3E");
+        assert_eq!(html, "<em>&lt;b&gt;</em> not found.");
+    }
+
+    #[test]
+    fn lookup_shows_mosaic_rhymes() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "poet P OW1 AH0 T",
+            "know N OW1",
+            "it IH1 T",
+            "cat K AE1 T",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+        let get = |url: &str| {
+            client
+                .get(url.to_string())
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = get("/lookup?term=poet");
+        let mosaics = html.find("<h4>Mosaic rhymes</h4>").unwrap();
+        assert!(html[mosaics..].contains("<td>know it</td>"));
+        assert!(html[mosaics..].contains("<td>perfect</td>"));
+
+        let html = get("/api/mosaic?term=poet&limit=1");
+        assert_eq!(
+            html,
+            "poet (<code>P OW1 AH0 T</code>) rhymes with 1 phrases like:<br>\
+             <b>know it</b> (<code>N OW1 AH0 T</code>)<br>"
+        );
+        let html = get("/api/mosaic?term=cat");
+        assert!(html.contains("rhymes with 0 phrases like:"));
+        let html = get("/api/mosaic?term=%3Cb%3E");
+        assert_eq!(html, "<em>&lt;b&gt;</em> not found.");
+    }
+
+    #[test]
+    fn search_finds_words_by_pattern() {
+        let client = apple_client();
+        let get = |url: &str| {
+            client
+                .get(url.to_string())
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = get("/search");
+        assert!(html.contains("<form action=\"/search\">"));
+        assert!(!html.contains("matching words"));
+
+        let html = get("/search?pattern=stress%3A100+*+AH0+L");
+        assert!(html.contains("Showing 1&ndash;2 of 2 matching words."));
+        assert!(html.contains("<a href=\"/lookup?term=capital\">capital</a>"));
+        assert!(html.contains("<a href=\"/lookup?term=multiple\">multiple</a>"));
+
+        let html = get("/search?pattern=*+AH0+L&offset=2&limit=2");
+        assert!(html.contains("Showing 3&ndash;4 of 7 matching words."));
+        assert!(html.contains("offset=0&amp;limit=2\">&laquo; previous"));
+        assert!(html.contains("offset=4&amp;limit=2\">next &raquo;"));
+
+        let html = get("/search?pattern=*+XX");
+        assert!(html.contains("unknown phoneme &quot;XX&quot;"));
+
+        let html = get("/api/search?pattern=stop+*+syllables%3A2&limit=1");
+        assert!(html.starts_with("2 words match <code>stop * syllables:2</code>:<br>"));
+        assert!(html.contains("<b>grapple</b> (<code>G R AE1 P AH0 L</code>), ..."));
+
+        let html = get("/api/search?pattern=%3Cb%3E");
+        assert!(html.contains("unknown phoneme &quot;&lt;b&gt;&quot;"));
+    }
+
+    #[test]
+    fn meter_finds_words_by_stress() {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec![
+            "banana B AH0 N AE1 N AH0",
+            "bandana B AE0 N D AE1 N AH0",
+            "cabana K AH0 B AE1 N AH0",
+            "tomato T AH0 M EY1 T OW2",
+            "button B AH1 T AH0 N",
+        ]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let client = Client::tracked(build_rocket(shelf, None, Dialect::default()))
+            .expect("valid rocket instance");
+        let get = |url: &str| {
+            client
+                .get(url.to_string())
+                .dispatch()
+                .into_string()
+                .unwrap()
+        };
+
+        let html = get("/meter");
+        assert!(html.contains("<form action=\"/meter\">"));
+        assert!(!html.contains("<table"));
+
+        let html = get("/meter?stress=010&rhyme=&onset=&limit=2");
+        assert!(html.contains("Showing 1&ndash;2 of 3 words."));
+        assert!(html.contains("<td>banana</td>"));
+        assert!(!html.contains("<th>Similarity</th>"));
+        assert!(html.contains("stress=010&amp;rhyme=&amp;onset=&amp;offset=2&amp;limit=2\">next"));
+
+        let html = get("/meter?stress=010&rhyme=banana&onset=k");
+        assert!(html.contains("Rhyming with banana <span class=\"phonemes\">B AH0 N AE1 N AH0"));
+        assert!(html.contains("Showing 1&ndash;1 of 1 words."));
+        assert!(html.contains("<td>cabana</td>"));
+        assert!(html.contains("<td>perfect</td>"));
+
+        let html = get("/meter?stress=da-DUM");
+        assert!(html.contains("invalid stress pattern &quot;da-DUM&quot;"));
+        let html = get("/meter?stress=010&rhyme=zzz");
+        assert!(html.contains("zzz isn&#x27;t in the dictionary"));
+    }
+
+    #[test]
+    fn api_lookup_escapes_unknown_term() {
+        let client = test_client();
+        let response = client
+            .get("/api/lookup?term=%3Cscript%3Ealert(1)%3C%2Fscript%3E")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        let html = response.into_string().unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;&#x2F;script&gt;"));
+    }
+
+    #[test]
+    fn api_lookup_escapes_similar_words() {
+        let client = test_client();
+        let response = client.get("/api/lookup?term=red").dispatch();
+        let html = response.into_string().unwrap();
+        assert!(!html.contains("<b>bed</b>"));
+        assert!(html.contains("<b>&lt;b&gt;bed&lt;&#x2F;b&gt;</b>"));
+    }
+
+    #[test]
+    fn lookup_shows_ipa() {
+        let client = test_client();
+        let html = client
+            .get("/lookup?term=roses&notation=ipa")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<th>IPA</th>"));
+        assert!(html.contains("ˈɹoʊ.zəz"));
+        assert!(html.contains("name=\"notation\" value=\"ipa\" checked"));
+
+        let html = client
+            .get("/lookup?term=roses")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("R OW1 · Z AH0 Z"));
+        assert!(!html.contains("ˈɹoʊ.zəz"));
+    }
+
+    #[test]
+    fn lookup_accepts_pronunciations() {
+        let client = test_client();
+        // "/bɛd/", in IPA.
+        let html = client
+            .get("/lookup?term=%2Fb%C9%9Bd%2F")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<td>red</td>"));
+        assert!(html.contains("<td>read</td>"));
+
+        let html = client
+            .get("/api/lookup?term=B+EH1+D&notation=ipa")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("B EH1 D (<code>bɛd</code>)"));
+        assert!(html.contains("<b>red</b>"));
+
+        let html = client
+            .get("/api/lookup?term=B+D")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("not found"));
+    }
+
+    /// Creates a test client with an empty user dictionary at `path` and edit token "secret".
+    fn userdict_client(path: &str) -> Client {
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec!["red R EH1 D", "bed B EH1 D"]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        shelf.init_userdict(path).unwrap();
+        Client::tracked(build_rocket(
+            shelf,
+            Some("secret".to_string()),
+            Dialect::default(),
+        ))
+        .expect("valid rocket instance")
+    }
+
+    #[test]
+    fn userdict_edits_require_the_token() {
+        let path =
+            std::env::temp_dir().join(format!("poet-{}-server-auth.dict", std::process::id()));
+        let client = userdict_client(path.to_str().unwrap());
+        let add = |auth: Option<&str>| {
+            let mut request = client
+                .post("/api/userdict")
+                .header(ContentType::Form)
+                .body("entry=zed+Z+EH1+D");
+            if let Some(auth) = auth {
+                request =
+                    request.header(rocket::http::Header::new("Authorization", auth.to_string()));
+            }
+            request.dispatch().status()
+        };
+        assert_eq!(add(None), Status::Unauthorized);
+        assert_eq!(add(Some("Bearer wrong")), Status::Unauthorized);
+        assert_eq!(add(Some("Bearer secre")), Status::Unauthorized);
+        assert_eq!(add(Some("Bearer secrets")), Status::Unauthorized);
+        assert_eq!(add(Some("Bearer ")), Status::Unauthorized);
+        assert_eq!(add(Some("secret")), Status::Unauthorized);
+        assert!(!path.exists());
+
+        let mut dict = dictionary::DictionaryImpl::new();
+        dict.insert_all(&vec!["red R EH1 D"]);
+        let mut shelf = dictionary::Shelf::new();
+        shelf.push_dictionary(Box::new(dict));
+        let read_only = Client::tracked(build_rocket(shelf, None, Dialect::default())).unwrap();
+        let response = read_only
+            .delete("/api/userdict/red")
+            .header(rocket::http::Header::new("Authorization", "Bearer secret"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn userdict_edits_take_effect_immediately() {
+        let path =
+            std::env::temp_dir().join(format!("poet-{}-server-edit.dict", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let client = userdict_client(path.to_str().unwrap());
+        let auth = || rocket::http::Header::new("Authorization", "Bearer secret");
+        let send = |request: rocket::local::blocking::LocalRequest| {
+            let response = request.header(auth()).dispatch();
+            (response.status(), response.into_string().unwrap())
+        };
+
+        let form = |entry: &str| {
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("entry", entry)
+                .finish()
+        };
+        let (status, body) = send(
+            client
+                .post("/api/userdict")
+                .header(ContentType::Form)
+                .body(form("zed Z EH1 D")),
+        );
+        assert_eq!(status, Status::Created);
+        assert_eq!(body, "added zed Z EH1 D\n");
+        let html = client
+            .get("/api/lookup?term=red")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("zed"));
+
+        let (status, _) = send(
+            client
+                .post("/api/userdict")
+                .header(ContentType::Form)
+                .body(form("zed Z EH1 D")),
+        );
+        assert_eq!(status, Status::Conflict);
+        let (status, _) = send(
+            client
+                .post("/api/userdict")
+                .header(ContentType::Form)
+                .body(form("red R IY1 D")),
+        );
+        assert_eq!(status, Status::Created);
+        let (status, _) = send(
+            client
+                .post("/api/userdict")
+                .header(ContentType::Form)
+                .body(form("zed")),
+        );
+        assert_eq!(status, Status::BadRequest);
+
+        let (status, body) = send(
+            client
+                .put("/api/userdict")
+                .header(ContentType::Form)
+                .body(form("zed Z IY1")),
+        );
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body, "updated zed Z IY1\n");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "red R IY1 D\nzed Z IY1\n"
+        );
+
+        let (status, body) = send(client.delete("/api/userdict/zed"));
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body, "removed zed Z IY1\n");
+        let (status, _) = send(client.delete("/api/userdict/zed"));
+        assert_eq!(status, Status::NotFound);
+        let html = client
+            .get("/api/lookup?term=zed")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("not found"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn analyze_shows_candidates_for_unknown_words() {
+        let client = test_client();
+        let html = post_analyze(&client, "roses are reds\nred are reds\nzed\n");
+        assert_eq!(
+            html.matches("<div class=\"unknown_word\" data-word=\"reds\">")
+                .count(),
+            1
+        );
+        assert!(html.contains("<code>R EH1 D Z</code>"));
+        assert!(html.contains("morphology: red + -s"));
+        assert!(html.contains("no rhymes found"));
+        assert!(html.contains("<div class=\"unknown_word\" data-word=\"zed\">"));
+        assert!(html.contains("<code>Z EH1 D</code>"));
+        assert!(html.contains("rhymes with &lt;b&gt;bed&lt;&#x2F;b&gt;, read, red"));
+        // Without an edit token, words can't be added.
+        assert!(html.contains("Start the server with an edit token"));
+        assert!(html.contains("value=\"Add to dictionary\" disabled"));
+    }
+
+    #[test]
+    fn analyze_accept_adds_the_word_and_reanalyzes() {
+        let path =
+            std::env::temp_dir().join(format!("poet-{}-server-accept.dict", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let client = userdict_client(path.to_str().unwrap());
+        let accept = |token: &str| {
+            let body = url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("text", "red zed\nbed red\n")
+                .append_pair("word", "zed")
+                .append_pair("phonemes", "Z EH1 D")
+                .append_pair("token", token)
+                .finish();
+            let response = client
+                .post("/analyze/accept")
+                .header(ContentType::Form)
+                .body(body)
+                .dispatch();
+            (response.status(), response.into_string().unwrap())
+        };
+
+        let (status, html) = accept("wrong");
+        assert_eq!(status, Status::Unauthorized);
+        assert!(html.contains("Could not add zed: missing or invalid token."));
+        assert!(html.contains("data-word=\"zed\""));
+
+        let (status, html) = accept("secret");
+        assert_eq!(status, Status::Ok);
+        assert!(html.contains("Added zed (Z EH1 D)."));
+        assert!(!html.contains("data-word=\"zed\""));
+        assert!(html.contains("zed: (<span class=\"phonemes\">Z EH1 D</span>)"));
+        let source = format!("; from {}</span>", escape_html(path.to_str().unwrap()));
+        assert!(html.contains(&source));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "zed Z EH1 D\n");
+
+        let (status, html) = accept("secret");
+        assert_eq!(status, Status::Conflict);
+        assert!(html.contains("Could not add zed: zed is already in the user dictionary."));
+        std::fs::remove_file(&path).unwrap();
+
+        // Failing to save the user dictionary is the server's fault, not the request's.
+        let dir = std::env::temp_dir().join(format!("poet-{}-missing-dir", std::process::id()));
+        let client = userdict_client(dir.join("userdict.dict").to_str().unwrap());
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("text", "red zed\n")
+            .append_pair("word", "zed")
+            .append_pair("phonemes", "Z EH1 D")
+            .append_pair("token", "secret")
+            .finish();
+        let response = client
+            .post("/analyze/accept")
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .contains("Could not add zed: "));
+    }
+
+    #[test]
+    fn api_preview_shows_syllables_and_rhymes() {
+        let client = test_client();
+        let html = client
+            .get("/api/preview?word=zed&phonemes=z%20eh1%20d")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("<code>Z EH1 D</code>"));
+        assert!(html.contains("edited"));
+        assert!(html.contains("1 syllable\n"));
+        assert!(html.contains("rhymes with &lt;b&gt;bed&lt;&#x2F;b&gt;, read, red"));
+
+        let html = client
+            .get("/api/preview?word=zed&phonemes=%3Cscript%3E")
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(html.contains("class=\"candidate error\""));
+        assert!(!html.contains("<script>"));
+    }
+}
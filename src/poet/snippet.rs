@@ -8,7 +8,11 @@
 //! and other formatting that may have to be removed.
 use std::cmp::Ordering;
 
+use crate::poet::dialect::Dialect;
 use crate::poet::dictionary::*;
+use crate::poet::heteronym;
+use crate::poet::ipa::Notation;
+use crate::poet::spelling::Hyphenation;
 
 /// A token is one word from the original text, normalized and annotated.
 #[derive(Debug)]
@@ -20,10 +24,37 @@ pub struct Token<'a> {
     ///
     /// Lifetime note: holds a reference into the dictionary used for lookup.
     pub entry: Option<&'a Vec<Entry>>,
+
+    /// How likely each of the entries is, given the words around it. Higher is more likely.
+    /// See `heteronym::likelihoods`.
+    pub likelihoods: Vec<i32>,
     // TODO: Include span information referring to the char positions in raw_text?
     // pub raw_text: &str[],
 }
 
+impl<'a> Token<'a> {
+    /// Returns how likely the `idx`-th entry is, compared to the others.
+    pub fn likelihood(&self, idx: usize) -> i32 {
+        self.likelihoods.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Returns the index of the most likely entry, preferring earlier ones in a tie.
+    pub fn most_likely(&self) -> usize {
+        let mut best = 0;
+        for (i, likelihood) in self.likelihoods.iter().enumerate() {
+            if *likelihood > self.likelihoods[best] {
+                best = i;
+            }
+        }
+        return best;
+    }
+
+    /// Returns whether another entry is more likely than the `idx`-th one.
+    pub fn is_unlikely(&self, idx: usize) -> bool {
+        self.likelihood(idx) < self.likelihood(self.most_likely())
+    }
+}
+
 /// Represents a single line of a stanza.
 #[derive(Debug)]
 pub struct Line<'a> {
@@ -63,12 +94,18 @@ impl<'a> Line<'a> {
             tokens: vec![],
         };
 
-        for word in raw.split_whitespace() {
-            let normalized_text = normalize_for_lookup(&word);
-            let entry_vec = dict.lookup(&normalized_text);
+        let words: Vec<String> = raw.split_whitespace().map(normalize_for_lookup).collect();
+        let word_refs: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+        for (i, normalized_text) in words.iter().enumerate() {
+            let entry_vec = dict.lookup(normalized_text);
+            let likelihoods = match entry_vec {
+                Some(entries) => heteronym::likelihoods(&word_refs, i, entries),
+                None => vec![],
+            };
             result.tokens.push(Token {
-                text: normalized_text,
+                text: normalized_text.clone(),
                 entry: entry_vec,
+                likelihoods,
             });
         }
         result
@@ -121,9 +158,9 @@ impl<'a> Stanza<'a> {
     /// Generates a summary of the stanza and its analysis in a text format.
     ///
     /// The summary includes the raw text and information about each word/token
-    /// in each line. The format is targeted for printing to a terminal or put in a
-    /// `<pre>` html block.
-    pub fn summarize_to_text(&self) -> String {
+    /// in each line, with pronunciations in `notation`. The format is targeted for printing to
+    /// a terminal or put in a `<pre>` html block.
+    pub fn summarize_to_text(&self, notation: Notation) -> String {
         let mut out = String::with_capacity(8192); // Arbitrary.
 
         if let Some(title) = &self.title {
@@ -136,6 +173,10 @@ impl<'a> Stanza<'a> {
             for token in &line.tokens {
                 if let Some(entries) = &token.entry {
                     for (i, entry) in entries.iter().enumerate() {
+                        let entry = match notation {
+                            Notation::Arpabet => format!("{}", entry),
+                            Notation::Ipa => format!("{:#}", entry),
+                        };
                         if i == 0 {
                             out.push_str(&format!("\t{}: {}\n", &token.text, &entry));
                         } else {
@@ -191,18 +232,24 @@ impl<'a> Stanza<'a> {
     /// Finds the interpretation of this stanza with best fit (fewest errors).
     ///
     /// This checks the stanza against all the validators, and then finds
-    /// the interpretation (`StanzaView`) that matches the best.
-    pub fn analyze(&self) -> BestInterpretation {
+    /// the interpretation (`StanzaView`) that matches the best. Among those that fit equally
+    /// well, it picks the one with the most likely readings of any heteronyms (see
+    /// `heteronym`), and warns if the fit relies on an unlikely reading.
+    ///
+    /// Rhymes and syllables are checked as they sound in `dialect`.
+    pub fn analyze(&self, dialect: Dialect) -> BestInterpretation {
         let mut out = BestInterpretation {
             best: None,
             validator: String::new(),
+            dialect,
             errors: vec![],
+            warnings: vec![],
             estimate: (0, None),
         };
 
         let all_validators: Vec<Box<dyn Validator>> = vec![
-            Box::new(SonnetValidator {}),
-            Box::new(HaikuValidator {}),
+            Box::new(SonnetValidator { dialect }),
+            Box::new(HaikuValidator { dialect }),
             Box::new(AlwaysValidValidator {}), // So there's always a fallback.
         ];
 
@@ -217,21 +264,27 @@ impl<'a> Stanza<'a> {
 
         let iter = self.interpretations();
         out.estimate = iter.size_hint();
+        let mut best_likelihood = 0;
         for i in iter {
-            match selected_validator.validate(&i) {
-                Ok(_) => {
-                    out.best = Some(i);
-                    out.errors = vec![];
-                }
-                Err(v) => {
-                    if out.best.is_none() || v.len() < out.errors.len() {
-                        out.best = Some(i);
-                        out.errors = v;
-                    }
-                }
+            let errors = selected_validator.validate(&i).err().unwrap_or_default();
+            let likelihood = i.likelihood();
+            let is_better = match &out.best {
+                None => true,
+                Some(_) => (errors.len(), -likelihood) < (out.errors.len(), -best_likelihood),
+            };
+            if is_better {
+                out.best = Some(i);
+                out.errors = errors;
+                best_likelihood = likelihood;
             }
         }
         out.errors.sort();
+        if let Some(best) = &mut out.best {
+            for line in &mut best.lines {
+                line.prefer_likely_readings();
+                out.warnings.append(&mut line.unlikely_reading_warnings());
+            }
+        }
         return out;
     }
 }
@@ -268,6 +321,11 @@ impl<'a> StanzaView<'a> {
     pub fn num_lines(&self) -> usize {
         self.lines.len()
     }
+
+    /// Returns the total likelihood of the selected entries. See `Token::likelihoods`.
+    pub fn likelihood(&self) -> i32 {
+        self.lines.iter().map(|l| l.likelihood()).sum()
+    }
 }
 
 impl<'a> std::fmt::Debug for StanzaView<'a> {
@@ -277,9 +335,14 @@ impl<'a> std::fmt::Debug for StanzaView<'a> {
 }
 
 impl<'a> std::fmt::Display for StanzaView<'a> {
+    /// Writes each line as `LineView` does, passing on `{:#}` for IPA.
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for line in &self.lines {
-            write!(f, "{}\n", &line)?;
+            if f.alternate() {
+                writeln!(f, "{:#}", &line)?;
+            } else {
+                writeln!(f, "{}", &line)?;
+            }
         }
         Ok(())
     }
@@ -303,51 +366,44 @@ impl<'a> std::fmt::Debug for LineView<'a> {
 }
 
 impl<'a> std::fmt::Display for LineView<'a> {
+    /// Writes the line, then each word split into the syllables of its selected entry (see
+    /// `spelling`), with the entry's phonemes below it in ARPABET, or with `{:#}`, in IPA.
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:02}. {}\n", self.line.num, &self.line.raw_text)?;
-        // Start with just blasting everything there, and then make it pretty / evenly spaced.
+        writeln!(f, "{:02}. {}", self.line.num, &self.line.raw_text)?;
+        let notation = if f.alternate() {
+            Notation::Ipa
+        } else {
+            Notation::Arpabet
+        };
         let num_tokens = self.indices.len();
-
-        let mut dict_keys: Vec<String> = Vec::with_capacity(num_tokens);
+        let mut words: Vec<String> = Vec::with_capacity(num_tokens);
         let mut phoneme_strs: Vec<String> = Vec::with_capacity(num_tokens);
-
-        // If a word is in the dictionary, then it must have phonemes.
-        // The phonemes will always be longer than the word, often significantly.
-        //
-        // Thus, the widths for known words are always computed from the phonemes.
-        // (And, until there is alignment with the raw strings, they are used in all cases.)
-        let mut widths: Vec<usize> = Vec::with_capacity(num_tokens);
         for i in 0..num_tokens {
-            match self.get_entry(i) {
-                Some(e) => {
-                    dict_keys.push(e.dict_key());
-                    phoneme_strs.push(format!("{}", e.phonemes));
+            match (self.get_entry(i), self.get_hyphenation(i)) {
+                (Some(e), Some(hyphenation)) => {
+                    words.push(hyphenation.to_string());
+                    phoneme_strs.push(notation.format_syllables(&e.phonemes));
                 }
-                None => {
-                    dict_keys.push(String::from("?"));
+                _ => {
+                    words.push(format!("{}?", self.get_text(i)));
                     phoneme_strs.push(String::from("?"));
                 }
             }
-            widths.push(phoneme_strs.last().unwrap().len());
         }
 
-        // Start the line by shifting over by the line number prefix (assumed "NN. ").
+        // Each word and its phonemes share a column, as wide as the wider of the two. Rust pads
+        // by chars, which is right for the "·"s and IPA.
         write!(f, "  . ")?;
-        for i in 0..num_tokens {
-            // "Make the minimum field width the value of the '1'st argument (widths[i]), by
-            // left-justifying the string ('<'), and filling the rest with '.'".
-            //
-            // Note that using "1$" in the format specifier has weird effects on the positional
-            // arguments for the rest of the specifier, so it is best to put these all at the end.
-            write!(f, "{:.<1$}  ", dict_keys[i], widths[i])?;
+        for (word, phonemes) in words.iter().zip(&phoneme_strs) {
+            let width = word.chars().count().max(phonemes.chars().count());
+            write!(f, "{:<1$}  ", word, width)?;
         }
-
-        // Again with the shift, and the previous EOL this time too.
         write!(f, "\n  . ")?;
-        for i in 0..num_tokens {
-            write!(f, "{}  ", phoneme_strs[i])?;
+        for (word, phonemes) in words.iter().zip(&phoneme_strs) {
+            let width = word.chars().count().max(phonemes.chars().count());
+            write!(f, "{:<1$}  ", phonemes, width)?;
         }
-        write!(f, "\n")
+        writeln!(f)
     }
 }
 
@@ -381,6 +437,13 @@ impl<'a> LineView<'a> {
         &self.line.tokens[idx].text
     }
 
+    /// Returns the text for the `idx`-th token split into the syllables of its selected
+    /// entry, e.g. "beau·ti·ful", or None if the word is unknown.
+    pub fn get_hyphenation(&self, idx: usize) -> Option<Hyphenation> {
+        let entry = self.get_entry(idx)?;
+        Some(Hyphenation::new(self.get_text(idx), &entry.phonemes))
+    }
+
     /// Returns the number of words on the line.
     pub fn num_words(&self) -> usize {
         self.indices.len()
@@ -389,12 +452,12 @@ impl<'a> LineView<'a> {
     /// Returns the number of syllables in the line.
     ///
     /// This will be an underestimate if `has_unknown_words()`.
-    pub fn num_syllables(&self) -> i32 {
+    pub fn num_syllables(&self, dialect: Dialect) -> i32 {
         let mut num_syllables = 0;
         assert_eq!(self.indices.len(), self.line.tokens.len());
         for (i, opt) in self.line.tokens.iter().enumerate() {
             if let Some(tokens) = opt.entry {
-                num_syllables += tokens[self.indices[i]].num_syllables();
+                num_syllables += dialect.num_syllables(&tokens[self.indices[i]].phonemes);
             }
         }
         return num_syllables;
@@ -425,6 +488,54 @@ impl<'a> LineView<'a> {
             > 0
     }
 
+    /// Returns the total likelihood of the selected entries. See `Token::likelihoods`.
+    pub fn likelihood(&self) -> i32 {
+        let tokens = self.line.tokens.iter();
+        tokens
+            .zip(&self.indices)
+            .map(|(token, idx)| token.likelihood(*idx))
+            .sum()
+    }
+
+    /// Switches to the most likely entry for each word where it makes no difference to the
+    /// form, i.e. for words that aren't at the end of the line, and where the most likely
+    /// entry has as many syllables. `InterpretationsIter` skips these, so they may not have
+    /// been tried.
+    fn prefer_likely_readings(&mut self) {
+        let last = self.indices.len().saturating_sub(1);
+        for (i, token) in self.line.tokens.iter().enumerate().take(last) {
+            if let Some(v) = token.entry {
+                let likely = token.most_likely();
+                if v[likely].num_syllables() == v[self.indices[i]].num_syllables() {
+                    self.indices[i] = likely;
+                }
+            }
+        }
+    }
+
+    /// Returns a warning for each word where a more likely entry was passed over, e.g. "read"
+    /// as "R EH1 D" after "will".
+    fn unlikely_reading_warnings(&self) -> Vec<ClassifyError> {
+        let mut out = vec![];
+        for (i, token) in self.line.tokens.iter().enumerate() {
+            if let Some(v) = token.entry {
+                if token.is_unlikely(self.indices[i]) {
+                    out.push(ClassifyError::LineError(
+                        self.index(),
+                        format!(
+                            "line {} only fits with \"{}\" as {}, though {} is more likely here.",
+                            self.num(),
+                            token.text,
+                            v[self.indices[i]].phonemes,
+                            v[token.most_likely()].phonemes
+                        ),
+                    ));
+                }
+            }
+        }
+        return out;
+    }
+
     /// Provides a human-friendly string representation of this line's tokens.
     ///
     /// Known tokens are printed as `dict_key` and unknown ones are printed as None.
@@ -701,7 +812,9 @@ pub trait Validator {
 }
 
 /// Determines if the input is a Haiku.
-struct HaikuValidator {}
+struct HaikuValidator {
+    dialect: Dialect,
+}
 
 impl Validator for HaikuValidator {
     fn name(&self) -> &str {
@@ -713,12 +826,14 @@ impl Validator for HaikuValidator {
     }
 
     fn validate(&self, view: &StanzaView) -> Result<(), Vec<ClassifyError>> {
-        is_haiku(view)
+        is_haiku(view, self.dialect)
     }
 }
 
 /// Determines if the input is a Shakespearean Sonnet.
-struct SonnetValidator {}
+struct SonnetValidator {
+    dialect: Dialect,
+}
 
 impl Validator for SonnetValidator {
     fn name(&self) -> &str {
@@ -730,7 +845,7 @@ impl Validator for SonnetValidator {
     }
 
     fn validate(&self, view: &StanzaView) -> Result<(), Vec<ClassifyError>> {
-        is_shakespearean_sonnet(view)
+        is_shakespearean_sonnet(view, self.dialect)
     }
 }
 
@@ -760,12 +875,13 @@ impl Validator for AlwaysValidValidator {
 /// Returns:
 /// - `Ok(())` if valid.
 /// - `Err(errors)` if not valid, with a vector of ClassifyErrors.
-fn is_haiku(stanza: &StanzaView) -> Result<(), Vec<ClassifyError>> {
+fn is_haiku(stanza: &StanzaView, dialect: Dialect) -> Result<(), Vec<ClassifyError>> {
     check_stanza_has_num_lines(stanza, 3)?;
 
     let mut errors = vec![];
     for (i, expected_syllables) in [5, 7, 5].iter().enumerate() {
-        if let Err(mut v) = check_line_has_num_syllables(&stanza.lines[i], *expected_syllables) {
+        let line = &stanza.lines[i];
+        if let Err(mut v) = check_line_has_num_syllables(line, *expected_syllables, dialect) {
             errors.append(&mut v);
         }
     }
@@ -785,18 +901,21 @@ fn is_haiku(stanza: &StanzaView) -> Result<(), Vec<ClassifyError>> {
 /// Returns:
 /// - `Ok(())` if valid.
 /// - `Err(errors)` if not valid, with a vector of ClassifyErrors.
-fn is_shakespearean_sonnet(stanza: &StanzaView) -> Result<(), Vec<ClassifyError>> {
+fn is_shakespearean_sonnet(
+    stanza: &StanzaView,
+    dialect: Dialect,
+) -> Result<(), Vec<ClassifyError>> {
     check_stanza_has_num_lines(stanza, 14)?;
 
     let mut errors = vec![];
     for line in &stanza.lines {
-        if let Err(mut v) = check_line_has_num_syllables(&line, 10) {
+        if let Err(mut v) = check_line_has_num_syllables(&line, 10, dialect) {
             errors.append(&mut v);
         }
     }
     let rhyming_lines = [(0, 2), (1, 3), (4, 6), (5, 7), (8, 10), (9, 11), (12, 13)];
     for (a, b) in rhyming_lines {
-        if let Err(mut v) = check_lines_rhyme(&stanza.lines[a], &stanza.lines[b]) {
+        if let Err(mut v) = check_lines_rhyme(&stanza.lines[a], &stanza.lines[b], dialect) {
             errors.append(&mut v);
         }
     }
@@ -810,24 +929,32 @@ fn is_shakespearean_sonnet(stanza: &StanzaView) -> Result<(), Vec<ClassifyError>
 
 /// Checks that the two given lines rhyme.
 ///
-/// Rhyming is currently that they share the same last syllable. This is conservative and treats
-/// unknown words as correct.
-fn check_lines_rhyme(a: &LineView, b: &LineView) -> Result<(), Vec<ClassifyError>> {
+/// Rhyming is currently that they share the same last syllable, as it sounds in `dialect`. This
+/// is conservative and treats unknown words as correct.
+fn check_lines_rhyme(
+    a: &LineView,
+    b: &LineView,
+    dialect: Dialect,
+) -> Result<(), Vec<ClassifyError>> {
     let a_last_entry = a.last_entry();
     let b_last_entry = b.last_entry();
     if a_last_entry.is_none() || b_last_entry.is_none() {
         return Ok(());
     }
 
-    if a_last_entry.unwrap().rhymes_with(&b_last_entry.unwrap()) {
+    if dialect.rhymes(
+        &a_last_entry.unwrap().phonemes,
+        &b_last_entry.unwrap().phonemes,
+    ) {
         Ok(())
     } else {
         let error_msg = format!(
-            "lines {} and {}: the words {} and {} don't rhyme?",
+            "lines {} and {}: the words {} and {} don't rhyme{}?",
             a.num(),
             b.num(),
             &a_last_entry.unwrap(),
-            &b_last_entry.unwrap()
+            &b_last_entry.unwrap(),
+            in_dialect(dialect)
         );
         Err(vec![
             ClassifyError::LineError(a.index(), error_msg.clone()),
@@ -852,7 +979,7 @@ fn check_stanza_has_num_lines(stanza: &StanzaView, n: usize) -> Result<(), Vec<C
     Ok(())
 }
 
-/// Checks that the given Line has the given number of syllables.
+/// Checks that the given Line has the given number of syllables, as it sounds in `dialect`.
 ///
 /// This is conservative in the face of unknown words. If some words are unknown
 /// and the number of syllables is short of the target, it will assume that the
@@ -861,10 +988,14 @@ fn check_stanza_has_num_lines(stanza: &StanzaView, n: usize) -> Result<(), Vec<C
 /// Returns:
 /// - `Ok(())` if valid.
 /// - `Err(info)` if not valid, with reason why.
-fn check_line_has_num_syllables(line: &LineView, expected: i32) -> Result<(), Vec<ClassifyError>> {
+fn check_line_has_num_syllables(
+    line: &LineView,
+    expected: i32,
+    dialect: Dialect,
+) -> Result<(), Vec<ClassifyError>> {
     let mut errors = vec![];
 
-    let num_syllables = line.num_syllables();
+    let num_syllables = line.num_syllables(dialect);
     if line.has_unknown_words() {
         if num_syllables >= expected {
             errors.push(ClassifyError::LineError(
@@ -883,9 +1014,10 @@ fn check_line_has_num_syllables(line: &LineView, expected: i32) -> Result<(), Ve
         errors.push(ClassifyError::LineError(
             line.index(),
             format!(
-                "line {} has {} syllables but should have {}.",
+                "line {} has {} syllables{} but should have {}.",
                 line.num(),
                 num_syllables,
+                in_dialect(dialect),
                 expected
             ),
         ));
@@ -897,6 +1029,15 @@ fn check_line_has_num_syllables(line: &LineView, expected: i32) -> Result<(), Ve
     }
 }
 
+/// Returns " in <dialect>" for error messages, or nothing for the default dialect, whose
+/// pronunciations are the dictionary's own.
+fn in_dialect(dialect: Dialect) -> String {
+    if dialect == Dialect::default() {
+        return String::new();
+    }
+    return format!(" in {}", dialect);
+}
+
 /// Finds and analyzes all the stanzas in the given string.
 ///
 /// Stanzas must have more than one line, and they are separated by one or more
@@ -980,8 +1121,13 @@ pub struct BestInterpretation<'a> {
     pub best: Option<StanzaView<'a>>,
     /// The name of the validator.
     pub validator: String,
+    /// The dialect that rhymes and syllables were checked in.
+    pub dialect: Dialect,
     /// Any errors found.
     pub errors: Vec<ClassifyError>,
+    /// Readings of heteronyms that the best interpretation relies on, but that are unlikely
+    /// in context.
+    pub warnings: Vec<ClassifyError>,
     /// The estimated number of interpretations.
     pub estimate: (usize, Option<usize>),
 }
@@ -992,8 +1138,15 @@ pub struct BestInterpretation<'a> {
 ///
 /// * `path` - The text file to analyze.
 /// * `dict` - The dictionary to use.
+/// * `dialect` - The dialect to check rhymes and syllables in.
+/// * `notation` - How to write pronunciations.
 ///
-pub fn analyze_one_file_to_terminal(path: &str, dict: &dyn Dictionary) {
+pub fn analyze_one_file_to_terminal(
+    path: &str,
+    dict: &dyn Dictionary,
+    dialect: Dialect,
+    notation: Notation,
+) {
     let raw_input = std::fs::read_to_string(path).unwrap();
     let stanzas = get_stanzas_from_text(&raw_input, dict);
     // Avoid silently dropping all of the input (e.g. if it's double-spaced), if possible.
@@ -1009,10 +1162,14 @@ pub fn analyze_one_file_to_terminal(path: &str, dict: &dyn Dictionary) {
     }
 
     for s in stanzas {
-        println!("====== STANZA ======\n{}", s.summarize_to_text());
+        println!("====== STANZA ======\n{}", s.summarize_to_text(notation));
 
-        let best = s.analyze();
-        println!("Best Interpretation:\n{}\n", &best.best.unwrap());
+        let best = s.analyze(dialect);
+        match notation {
+            Notation::Arpabet => println!("Best Interpretation:\n{}\n", &best.best.unwrap()),
+            Notation::Ipa => println!("Best Interpretation:\n{:#}\n", &best.best.unwrap()),
+        }
+        println!("Rhymes and syllables are checked as in {}.", &best.dialect);
         if best.errors.is_empty() {
             println!("What a great {}!", &best.validator);
         } else {
@@ -1022,6 +1179,12 @@ pub fn analyze_one_file_to_terminal(path: &str, dict: &dyn Dictionary) {
                 println!("{}", e);
             }
         }
+        if !best.warnings.is_empty() {
+            println!("\nUnlikely readings:\n");
+            for w in &best.warnings {
+                println!("{}", w);
+            }
+        }
     }
 }
 
@@ -1196,7 +1359,10 @@ mod tests {
               Quacking constantly\n";
             let stanza = to_stanza(text, &dict);
             assert!(!stanza.has_unknown_words()); // Test invariant.
-            assert_eq!(is_haiku(&unique_interp(&stanza)), Ok(()));
+            assert_eq!(
+                is_haiku(&unique_interp(&stanza), Dialect::default()),
+                Ok(())
+            );
         }
 
         #[test]
@@ -1213,16 +1379,16 @@ mod tests {
             let all_too_long = "a a a a a a\na a a a a a a a\na a a a a a";
             let stanza = to_stanza(&line1_too_short, &dict);
             assert!(!stanza.has_unknown_words()); // Test invariant.
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
 
             let stanza = to_stanza(&line2_too_short, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
 
             let stanza = to_stanza(&line3_too_short, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
 
             let stanza = to_stanza(&all_too_long, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
         }
 
         #[test]
@@ -1235,10 +1401,10 @@ mod tests {
             let four_lines = "a a a a a\na a a a a a a\na a a a a\na a a a a";
 
             let stanza = to_stanza(&two_lines, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
 
             let stanza = to_stanza(&four_lines, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
         }
 
         #[test]
@@ -1251,7 +1417,7 @@ mod tests {
                         wertgreen\n\
                         a a a a a";
             let stanza = to_stanza(&text, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_ok());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_ok());
         }
 
         #[test]
@@ -1264,7 +1430,7 @@ mod tests {
                         a a a a a a a\n\
                         a a a a a";
             let stanza = to_stanza(&text, &dict);
-            assert!(is_haiku(&unique_interp(&stanza)).is_err());
+            assert!(is_haiku(&unique_interp(&stanza), Dialect::default()).is_err());
         }
     }
 
@@ -1324,13 +1490,13 @@ mod tests {
             let poem_dict_entries = vec![
                 "a AH0 D",
                 "b AH0 P",        // 1 syllable.
-                "b(2) AY1 AH0 X", // 2 syllables.
+                "b(2) AY1 AH0 K", // 2 syllables.
                 "c IH1 V",
                 "c(2) AY1 IH1 Y",
                 "c(3) AE1 IH1 Z",
                 "d G IH1 V",
                 "e EH1 L",
-                "e(2) AY1 EH1 X",
+                "e(2) AY1 EH1 K",
                 // f missing.
             ];
             let mut poem_dict = DictionaryImpl::new();
@@ -1357,13 +1523,13 @@ mod tests {
             let poem_dict_entries = vec![
                 "a AH0 D",
                 "b AH0 P",
-                "b(2) AH0 X",
+                "b(2) AH0 K",
                 "c IH1 V",
                 "c(2) IH1 Y",
                 "c(3) IH1 Z",
                 "d G IH1 V",
                 "e EH1 L",
-                "e(2) EH1 X",
+                "e(2) EH1 K",
                 // f missing.
             ];
             let mut poem_dict = DictionaryImpl::new();
@@ -1381,6 +1547,75 @@ mod tests {
         }
     }
 
+    mod heteronyms {
+        use super::*;
+
+        fn heteronym_dict() -> DictionaryImpl {
+            let mut dict = DictionaryImpl::new();
+            dict.insert_all(&vec![
+                "a AH0",
+                "bed B EH1 D",
+                "blows B L OW1 Z",
+                "cat K AE1 T",
+                "have HH AE1 V",
+                "hat HH AE1 T",
+                "the DH AH0",
+                "read R IY1 D",
+                "read(2) R EH1 D",
+                "will W IH1 L",
+                "wind W AY1 N D",
+                "wind(2) W IH1 N D",
+            ]);
+            dict
+        }
+
+        #[test]
+        fn test_prefers_likely_readings() {
+            let dict = heteronym_dict();
+            let text = "a a a will read\n\
+                        a a a a the wind blows\n\
+                        a a a have read\n";
+            let stanza = to_stanza(text, &dict);
+            assert_eq!(stanza.lines[0].tokens[4].likelihoods, vec![2, 0]);
+            assert!(stanza.lines[0].tokens[4].is_unlikely(1));
+            let best = stanza.analyze(Dialect::default());
+            assert_eq!(best.validator, "Haiku");
+            assert!(best.errors.is_empty());
+            assert!(best.warnings.is_empty());
+            let view = best.best.unwrap();
+            assert_eq!(view.lines[0].token_string(), "a a a will read");
+            assert_eq!(view.lines[1].token_string(), "a a a a the wind(2) blows");
+            assert_eq!(view.lines[2].token_string(), "a a a have read(2)");
+        }
+
+        #[test]
+        fn test_warns_about_unlikely_readings() {
+            let dict = heteronym_dict();
+            let mut text = String::from("a a a a a a a a will read\n");
+            for last in ["cat", "bed", "hat"] {
+                text.push_str(&format!("a a a a a a a a a {}\n", last));
+            }
+            text.push_str(&"a a a a a a a a a cat\n".repeat(10));
+            let stanza = to_stanza(&text, &dict);
+            let best = stanza.analyze(Dialect::default());
+            assert_eq!(best.validator, "Shakespearean Sonnet");
+            assert!(best.errors.is_empty());
+            assert_eq!(
+                best.best.unwrap().lines[0].token_string(),
+                "a a a a a a a a will read(2)"
+            );
+            assert_eq!(
+                best.warnings,
+                vec![ClassifyError::LineError(
+                    0,
+                    "line 1 only fits with \"read\" as R EH1 D, though R IY1 D is more likely \
+                     here."
+                        .to_string()
+                )]
+            );
+        }
+    }
+
     mod is_shakespearean_sonnet {
         use super::*;
 
@@ -1392,13 +1627,17 @@ mod tests {
 
             let line = "a a a a a a a a a a\n";
             let too_short = to_stanza(&line.repeat(13), &dict);
-            assert!(is_shakespearean_sonnet(&unique_interp(&too_short)).is_err());
+            assert!(
+                is_shakespearean_sonnet(&unique_interp(&too_short), Dialect::default()).is_err()
+            );
 
             let correct = to_stanza(&line.repeat(14), &dict);
-            assert!(is_shakespearean_sonnet(&unique_interp(&correct)).is_ok());
+            assert!(is_shakespearean_sonnet(&unique_interp(&correct), Dialect::default()).is_ok());
 
             let too_long = to_stanza(&line.repeat(15), &dict);
-            assert!(is_shakespearean_sonnet(&unique_interp(&too_long)).is_err());
+            assert!(
+                is_shakespearean_sonnet(&unique_interp(&too_long), Dialect::default()).is_err()
+            );
         }
 
         #[test]
@@ -1428,9 +1667,13 @@ mod tests {
             );
 
             let too_short = to_stanza(&prose_short, &dict);
-            assert!(is_shakespearean_sonnet(&unique_interp(&too_short)).is_err());
+            assert!(
+                is_shakespearean_sonnet(&unique_interp(&too_short), Dialect::default()).is_err()
+            );
             let too_long = to_stanza(&prose_long, &dict);
-            assert!(is_shakespearean_sonnet(&unique_interp(&too_long)).is_err());
+            assert!(
+                is_shakespearean_sonnet(&unique_interp(&too_long), Dialect::default()).is_err()
+            );
         }
 
         #[test]
@@ -1479,7 +1722,9 @@ mod tests {
             {
                 // Original, correct version shoudld be ok.
                 let stanza = to_stanza(&poem, &poem_dict);
-                assert!(is_shakespearean_sonnet(&unique_interp(&stanza)).is_ok());
+                assert!(
+                    is_shakespearean_sonnet(&unique_interp(&stanza), Dialect::default()).is_ok()
+                );
             }
 
             // For the last word in each line, replace it with another word of the same length
@@ -1503,8 +1748,40 @@ mod tests {
             for (old, new) in replacements {
                 let text = poem.replace(old, new);
                 let stanza = to_stanza(&text, &poem_dict);
-                assert!(is_shakespearean_sonnet(&unique_interp(&stanza)).is_err());
+                assert!(
+                    is_shakespearean_sonnet(&unique_interp(&stanza), Dialect::default()).is_err()
+                );
             }
         }
+
+        #[test]
+        fn test_checks_in_dialect() {
+            let mut dict = DictionaryImpl::new();
+            dict.insert_all(&vec![
+                "a AH0",
+                "cat K AE1 T",
+                "law L AO1",
+                "lore L AO1 R",
+                "military M IH1 L AH0 T EH2 R IY0",
+            ]);
+            // "lore" and "law" only rhyme without the R, and "military" only has three
+            // syllables in British English.
+            let mut text = String::from("a a a a a a a a a lore\n");
+            text.push_str("a a a a a a a a a cat\n");
+            text.push_str("a a a a a a a a a law\n");
+            text.push_str("a a a a a a military cat\n");
+            text.push_str(&"a a a a a a a a a cat\n".repeat(10));
+            let stanza = to_stanza(&text, &dict);
+            let view = unique_interp(&stanza);
+            assert!(is_shakespearean_sonnet(&view, Dialect::British).is_ok());
+            let errors = is_shakespearean_sonnet(&view, Dialect::GeneralAmerican).unwrap_err();
+            assert_eq!(errors.len(), 3);
+            assert!(is_shakespearean_sonnet(&view, Dialect::Australian).is_ok());
+            let errors = is_shakespearean_sonnet(&view, Dialect::Canadian).unwrap_err();
+            assert_eq!(
+                errors[0].to_string(),
+                "Line 4: line 4 has 11 syllables in Canadian English but should have 10."
+            );
+        }
     } // mod is_shakespearean_sonnet
 }
@@ -0,0 +1,455 @@
+//! Lines up a word's spelling with its pronunciation, to split the written word into syllables.
+//!
+//! A syllable count is easier to check against the word when it's written out by syllable,
+//! like "beau·ti·ful", so this finds which letters spell which phonemes, and then cuts the word
+//! where `syllable` cuts its phonemes.
+//!
+//! The letters are aligned with the phonemes by the cheapest sequence of steps, where each step
+//! spells one phoneme (or two, like the "x" in "extra") with a few letters, skips a silent
+//! letter, or skips a phoneme that isn't written. Spellings from `SPELLINGS` are free, and any
+//! other vowel letter for a vowel or consonant letter for a consonant costs a little more than
+//! a silent "e" or a doubled letter. A few of the usual rules for hyphenation are applied on
+//! top: doubled consonants are split ("hap·py"), "ck" stays with the syllable before it
+//! ("chick·en"), suffixes like "-ing" are split off whole ("search·ing"), and punctuation is
+//! never split from the letters before it ("x-ray"). A syllable whose letters can't be told
+//! apart from the one before it, like the second one of "fire", stays joined to it.
+use crate::poet::dictionary::Phonemes;
+use crate::poet::phoneme::{Phoneme, Stress, Symbol};
+use crate::poet::syllable::Syllable;
+use std::fmt;
+use std::ops::Range;
+
+/// Common spellings of each phoneme.
+const SPELLINGS: &[(Symbol, &[&str])] = &[
+    (Symbol::AA, &["a", "o", "ah", "au", "aw", "al", "ea"]),
+    (Symbol::AE, &["a", "ai", "au"]),
+    (
+        Symbol::AH,
+        &[
+            "a", "e", "i", "o", "u", "y", "ou", "oo", "io", "ai", "ia", "eo",
+        ],
+    ),
+    (Symbol::AO, &["o", "a", "au", "aw", "ou", "oa", "al", "oo"]),
+    (Symbol::AW, &["ou", "ow", "au"]),
+    (
+        Symbol::AY,
+        &["i", "y", "ie", "igh", "ai", "ei", "uy", "ye", "eye", "ey"],
+    ),
+    (Symbol::EH, &["e", "ea", "a", "ai", "ie", "ue", "ei"]),
+    (
+        Symbol::ER,
+        &[
+            "er", "ir", "ur", "or", "ear", "ar", "our", "re", "yr", "err", "urr", "r",
+        ],
+    ),
+    (
+        Symbol::EY,
+        &["a", "ai", "ay", "ei", "ey", "ea", "eigh", "e", "aigh"],
+    ),
+    (Symbol::IH, &["i", "y", "e", "ee", "ui", "a", "ie"]),
+    (
+        Symbol::IY,
+        &["e", "ee", "ea", "y", "i", "ie", "ei", "ey", "eo", "oe"],
+    ),
+    (
+        Symbol::OW,
+        &["o", "oa", "ow", "oe", "ou", "ew", "eau", "oh", "ough"],
+    ),
+    (Symbol::OY, &["oi", "oy"]),
+    (Symbol::UH, &["oo", "u", "ou", "o"]),
+    (
+        Symbol::UW,
+        &["oo", "u", "ue", "ew", "o", "ou", "ui", "eu", "ough"],
+    ),
+    (Symbol::B, &["b", "bb"]),
+    (Symbol::CH, &["ch", "tch", "t", "c"]),
+    (Symbol::D, &["d", "dd", "ed"]),
+    (Symbol::DH, &["th"]),
+    (Symbol::F, &["f", "ff", "ph", "gh"]),
+    (Symbol::G, &["g", "gg", "gu", "gh"]),
+    (Symbol::HH, &["h", "wh"]),
+    (Symbol::JH, &["j", "g", "dg", "dge", "ge", "gg", "d"]),
+    (Symbol::K, &["k", "c", "ck", "ch", "q", "cc", "qu", "kk"]),
+    (Symbol::L, &["l", "ll"]),
+    (Symbol::M, &["m", "mm", "mb", "mn"]),
+    (Symbol::N, &["n", "nn", "kn", "gn", "pn"]),
+    (Symbol::NG, &["ng", "n"]),
+    (Symbol::P, &["p", "pp"]),
+    (Symbol::R, &["r", "rr", "wr", "rh"]),
+    (Symbol::S, &["s", "ss", "c", "sc", "ce", "se", "z"]),
+    (
+        Symbol::SH,
+        &["sh", "ti", "ci", "ssi", "s", "ch", "si", "ss"],
+    ),
+    (Symbol::T, &["t", "tt", "ed", "th", "pt"]),
+    (Symbol::TH, &["th"]),
+    (Symbol::V, &["v", "f", "ve", "vv"]),
+    (Symbol::W, &["w", "u", "wh", "o"]),
+    (Symbol::Y, &["y", "i", "j"]),
+    (Symbol::Z, &["z", "zz", "s", "x", "ze", "se", "ss"]),
+    (Symbol::ZH, &["s", "si", "g", "z", "ge"]),
+];
+
+/// Common spellings of pairs of phonemes, like the "x" in "box".
+const PAIR_SPELLINGS: &[([Symbol; 2], &[&str])] = &[
+    ([Symbol::K, Symbol::S], &["x", "xe"]),
+    ([Symbol::G, Symbol::Z], &["x"]),
+    ([Symbol::K, Symbol::SH], &["x"]),
+    ([Symbol::K, Symbol::W], &["qu"]),
+    (
+        [Symbol::Y, Symbol::UW],
+        &["u", "eu", "ew", "eau", "ue", "iew"],
+    ),
+    ([Symbol::Y, Symbol::UH], &["u", "eu"]),
+    ([Symbol::Y, Symbol::AH], &["u"]),
+    ([Symbol::W, Symbol::AH], &["o"]),
+];
+
+/// Suffixes that are split off whole, rather than taking the consonant before them, e.g.
+/// "search·ing" rather than "sear·ching". Each is used only if it spells the whole rime of the
+/// last syllable.
+const SUFFIXES: &[(&str, &[Symbol])] = &[
+    ("ing", &[Symbol::IH, Symbol::NG]),
+    ("ings", &[Symbol::IH, Symbol::NG, Symbol::Z]),
+];
+
+/// The most letters that spell one phoneme or pair of phonemes in the tables.
+const MAX_SPELLING_LEN: usize = 4;
+
+/// The cost of a letter for a phoneme that isn't one of its usual spellings, but is at least a
+/// vowel letter for a vowel or a consonant letter for a consonant.
+const UNUSUAL_SPELLING_COST: u32 = 2;
+
+/// The cost of a letter for a phoneme of the other kind.
+const MISMATCHED_SPELLING_COST: u32 = 4;
+
+/// The cost of a phoneme without any letters.
+const UNWRITTEN_COST: u32 = 3;
+
+/// Returns whether `c` is usually written for a vowel. "y" is both.
+fn is_vowel_letter(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+}
+
+/// Returns the cost of spelling `phoneme` with `letters`, or None if it's not allowed.
+fn spelling_cost(letters: &str, phoneme: Phoneme) -> Option<u32> {
+    let symbol = phoneme.symbol();
+    let usual = SPELLINGS
+        .iter()
+        .any(|(s, spellings)| *s == symbol && spellings.contains(&letters));
+    if usual {
+        return Some(0);
+    }
+    let mut chars = letters.chars();
+    let letter = match (chars.next(), chars.next()) {
+        (Some(letter), None) => letter,
+        _ => return None,
+    };
+    if !letter.is_alphabetic() {
+        return None;
+    }
+    if letter == 'y' || is_vowel_letter(letter) == symbol.is_vowel() {
+        return Some(UNUSUAL_SPELLING_COST);
+    }
+    return Some(MISMATCHED_SPELLING_COST);
+}
+
+/// Returns the cost of leaving the `i`-th letter silent.
+fn silent_cost(letters: &[char], i: usize) -> u32 {
+    let c = letters[i];
+    if !c.is_alphabetic() {
+        return 0;
+    }
+    if i > 0 && letters[i - 1] == c {
+        return 1;
+    }
+    match c {
+        'e' => 1,
+        'h' | 'w' | 'k' | 'b' | 'g' | 'u' | 't' | 'l' | 'p' | 'n' | 'a' | 'o' => 2,
+        _ => 3,
+    }
+}
+
+/// Finds the letters of `text` that spell each of `phonemes`, as ranges of char indices.
+///
+/// Phonemes without letters of their own get an empty range where they'd be. That includes
+/// the second of a pair spelled together, like the S of the "x" in "box", which gets the empty
+/// range after the "x".
+pub fn align(text: &str, phonemes: &[Phoneme]) -> Vec<Range<usize>> {
+    let letters: Vec<char> = text.to_lowercase().chars().collect();
+    let (n, m) = (letters.len(), phonemes.len());
+
+    // The cheapest cost of spelling the first j phonemes with the first i letters, and the
+    // numbers of letters and phonemes in the step that got there.
+    let mut cost = vec![vec![u32::MAX; m + 1]; n + 1];
+    let mut step = vec![vec![(0usize, 0usize); m + 1]; n + 1];
+    cost[0][0] = 0;
+    for i in 0..=n {
+        for j in 0..=m {
+            let here = cost[i][j];
+            if here == u32::MAX {
+                continue;
+            }
+            let mut relax = |di: usize, dj: usize, extra: u32| {
+                if here + extra < cost[i + di][j + dj] {
+                    cost[i + di][j + dj] = here + extra;
+                    step[i + di][j + dj] = (di, dj);
+                }
+            };
+            if i < n {
+                relax(1, 0, silent_cost(&letters, i));
+            }
+            if j < m {
+                relax(0, 1, UNWRITTEN_COST);
+            }
+            for k in 1..=MAX_SPELLING_LEN.min(n - i) {
+                let chunk: String = letters[i..i + k].iter().collect();
+                if j < m {
+                    if let Some(extra) = spelling_cost(&chunk, phonemes[j]) {
+                        relax(k, 1, extra);
+                    }
+                }
+                if j + 1 < m {
+                    let pair = [phonemes[j].symbol(), phonemes[j + 1].symbol()];
+                    let spelled = PAIR_SPELLINGS
+                        .iter()
+                        .any(|(p, spellings)| *p == pair && spellings.contains(&chunk.as_str()));
+                    if spelled {
+                        relax(k, 2, 0);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut ranges = vec![0..0; m];
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let (di, dj) = step[i][j];
+        match dj {
+            1 => ranges[j - 1] = i - di..i,
+            2 => {
+                ranges[j - 2] = i - di..i;
+                ranges[j - 1] = i..i;
+            }
+            _ => {}
+        }
+        i -= di;
+        j -= dj;
+    }
+    return ranges;
+}
+
+/// One written syllable of a word, e.g. "ti" in "beautiful".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrittenSyllable {
+    pub text: String,
+    /// Whether the syllable has the primary stress. This is only set if there's more than one.
+    pub stressed: bool,
+}
+
+impl WrittenSyllable {
+    /// Returns whether a "·" is needed after this syllable to show where the next one starts.
+    /// It isn't after punctuation, like the "-" in "x-ray", which already shows it.
+    pub fn needs_separator(&self) -> bool {
+        self.text.ends_with(char::is_alphabetic)
+    }
+}
+
+/// A word split into written syllables, following its pronunciation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hyphenation {
+    pub syllables: Vec<WrittenSyllable>,
+}
+
+impl Hyphenation {
+    /// Splits `text` into the syllables of `phonemes`. Syllables that can't be told apart in
+    /// the letters are joined to the one before, so e.g. an abbreviation is left whole.
+    pub fn new(text: &str, phonemes: &Phonemes) -> Hyphenation {
+        let letters: Vec<char> = text.chars().collect();
+        let lowercase: Vec<char> = text.to_lowercase().chars().collect();
+        let spoken = phonemes.syllables();
+        if spoken.len() < 2 || lowercase.len() != letters.len() {
+            return Hyphenation {
+                syllables: vec![WrittenSyllable {
+                    text: text.to_string(),
+                    stressed: false,
+                }],
+            };
+        }
+
+        // The first letter and the first spoken syllable of each written syllable.
+        let ranges = align(text, &phonemes.phonemes);
+        let mut starts = vec![(0, 0)];
+        let mut first_phoneme = 0;
+        for (k, syllable) in spoken.iter().enumerate() {
+            if k > 0 {
+                let previous = starts.last().unwrap().0;
+                let is_last = k + 1 == spoken.len();
+                let range = ranges[first_phoneme].clone();
+                if let Some(start) = written_start(&lowercase, range, syllable, is_last, previous) {
+                    starts.push((start, k));
+                }
+            }
+            first_phoneme += syllable.onset.len() + 1 + syllable.coda.len();
+        }
+
+        let stressed = match starts.len() {
+            1 => None,
+            _ => spoken.iter().position(|s| s.stress() == Stress::Primary),
+        };
+        let syllables = starts
+            .iter()
+            .enumerate()
+            .map(|(i, (start, first_spoken))| {
+                let (end, next_spoken) = starts
+                    .get(i + 1)
+                    .copied()
+                    .unwrap_or((letters.len(), spoken.len()));
+                WrittenSyllable {
+                    text: letters[*start..end].iter().collect(),
+                    stressed: stressed.is_some_and(|k| (*first_spoken..next_spoken).contains(&k)),
+                }
+            })
+            .collect();
+        return Hyphenation { syllables };
+    }
+}
+
+/// Returns the index of the letter that starts the written form of `syllable`, where `range` has
+/// the letters of its first phoneme and the syllable before starts at `previous`. Returns None
+/// if the syllable should stay joined to the one before.
+fn written_start(
+    lowercase: &[char],
+    range: Range<usize>,
+    syllable: &Syllable,
+    is_last: bool,
+    previous: usize,
+) -> Option<usize> {
+    let spelling: String = lowercase[range.clone()].iter().collect();
+    let mut start = range.start;
+    if spelling == "ck" {
+        start += 2;
+    } else if range.len() >= 2
+        && lowercase[start] == lowercase[start + 1]
+        && !is_vowel_letter(lowercase[start])
+    {
+        start += 1;
+    } else if is_last && syllable.onset.len() <= 1 && spelling != "th" {
+        // A suffix takes at most one consonant from the syllable before, and "-thing" is
+        // usually a word of its own, as in "some·thing".
+        let rime: Vec<Symbol> = syllable.rime().iter().map(|ph| ph.symbol()).collect();
+        let word: String = lowercase.iter().collect();
+        if let Some((suffix, _)) = SUFFIXES
+            .iter()
+            .find(|(suffix, sounds)| *sounds == rime.as_slice() && word.ends_with(suffix))
+        {
+            start = lowercase.len() - suffix.chars().count();
+        }
+    }
+
+    // Punctuation stays with the letters before it.
+    start = start.max(previous + 1);
+    while start < lowercase.len() && !lowercase[start].is_alphabetic() {
+        start += 1;
+    }
+    if start >= lowercase.len() {
+        return None;
+    }
+    // The syllable before keeps a vowel letter, unless punctuation marks the break, as in "x-ray".
+    let before = &lowercase[previous..start];
+    if !before.iter().any(|c| is_vowel_letter(*c)) && before[before.len() - 1].is_alphabetic() {
+        return None;
+    }
+    // A syllable that starts with its vowel can't start with a consonant letter, like the "r"
+    // of "fire".
+    if syllable.onset.is_empty() && !is_vowel_letter(lowercase[start]) {
+        return None;
+    }
+    return Some(start);
+}
+
+impl fmt::Display for Hyphenation {
+    /// Writes the syllables separated by "·" (see `WrittenSyllable::needs_separator`), with the
+    /// stressed one in capitals, e.g. "BEAU·ti·ful".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, syllable) in self.syllables.iter().enumerate() {
+            if i > 0 && self.syllables[i - 1].needs_separator() {
+                write!(f, "·")?;
+            }
+            if syllable.stressed {
+                write!(f, "{}", syllable.text.to_uppercase())?;
+            } else {
+                write!(f, "{}", syllable.text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Entry;
+
+    fn hyphenate(line: &str) -> String {
+        let entry = Entry::new(line);
+        Hyphenation::new(&entry.word, &entry.phonemes).to_string()
+    }
+
+    #[test]
+    fn aligns_letters_with_phonemes() {
+        let entry = Entry::new("box B AA1 K S");
+        assert_eq!(
+            align("box", &entry.phonemes.phonemes),
+            vec![0..1, 1..2, 2..3, 3..3]
+        );
+        let entry = Entry::new("knight N AY1 T");
+        assert_eq!(
+            align("knight", &entry.phonemes.phonemes),
+            vec![0..2, 2..5, 5..6]
+        );
+    }
+
+    #[test]
+    fn splits_words_into_written_syllables() {
+        assert_eq!(hyphenate("beautiful B Y UW1 T AH0 F AH0 L"), "BEAU·ti·ful");
+        assert_eq!(hyphenate("hello HH AH0 L OW1"), "hel·LO");
+        assert_eq!(hyphenate("happy HH AE1 P IY0"), "HAP·py");
+        assert_eq!(hyphenate("chicken CH IH1 K AH0 N"), "CHICK·en");
+        assert_eq!(hyphenate("extra EH1 K S T R AH0"), "EX·tra");
+        assert_eq!(hyphenate("lovely L AH1 V L IY0"), "LOVE·ly");
+        assert_eq!(hyphenate("roses R OW1 Z AH0 Z"), "RO·ses");
+        assert_eq!(hyphenate("searching S ER1 CH IH0 NG"), "SEARCH·ing");
+        assert_eq!(hyphenate("running R AH1 N IH0 NG"), "RUN·ning");
+        assert_eq!(hyphenate("something S AH1 M TH IH0 NG"), "SOME·thing");
+        assert_eq!(hyphenate("violets V AY1 AH0 L AH0 T S"), "VI·o·lets");
+        assert_eq!(
+            hyphenate("understand AH2 N D ER0 S T AE1 N D"),
+            "un·der·STAND"
+        );
+        assert_eq!(hyphenate("fire F AY1 ER0"), "fire");
+        assert_eq!(hyphenate("hour AW1 ER0"), "hour");
+        assert_eq!(hyphenate("red R EH1 D"), "red");
+        assert_eq!(hyphenate("w D AH1 B AH0 L Y UW0"), "w");
+        assert_eq!(hyphenate("tv T IY1 V IY1"), "tv");
+    }
+
+    #[test]
+    fn keeps_punctuation_with_the_letters_before_it() {
+        let entry = Entry::new("x-ray EH1 K S R EY2");
+        let hyphenation = Hyphenation::new(&entry.word, &entry.phonemes);
+        let texts: Vec<&str> = hyphenation
+            .syllables
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["x-", "ray"]);
+        assert!(!hyphenation.syllables[0].needs_separator());
+        assert_eq!(hyphenation.to_string(), "X-ray");
+
+        assert_eq!(hyphenate("a.m. EY1 EH1 M"), "a.m.");
+        assert_eq!(hyphenate("o'clock AH0 K L AA1 K"), "o'CLOCK");
+        assert_eq!(hyphenate("well-being W EH1 L B IY1 IH0 NG"), "WELL-be·ing");
+    }
+}
@@ -0,0 +1,221 @@
+//! Splits pronunciations into syllables, each with an onset, a nucleus and a coda.
+//!
+//! cmudict marks each syllable's vowel (its nucleus), but not where one syllable ends and the
+//! next begins. That's decided here by the maximal onset principle: the consonants between two
+//! vowels start the later syllable, as many of them as can start an English syllable, and the
+//! rest end the earlier one. So "extra" is "EH1 K · S T R AH0", since "S T R" can start a
+//! syllable but "K S T R" can't, and "misspell" is "M IH0 S · S P EH1 L".
+//!
+//! The consonants at the start of a word all go to its first syllable, and the ones at the end
+//! to its last, even where they couldn't start or end an English word, e.g. in names.
+//!
+//! Related references:
+//!
+//! * <https://en.wikipedia.org/wiki/Syllable>
+//! * <https://en.wikipedia.org/wiki/English_phonology#Phonotactics>
+use crate::poet::ipa;
+use crate::poet::phoneme::{Phoneme, Stress, Symbol};
+use std::fmt;
+
+/// One syllable of a pronunciation, e.g. "S T R AH0" at the end of "extra".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Syllable {
+    /// The consonants before the vowel, e.g. "S T R". May be empty.
+    pub onset: Vec<Phoneme>,
+    /// The vowel, e.g. "AH0".
+    pub nucleus: Phoneme,
+    /// The consonants after the vowel. May be empty.
+    pub coda: Vec<Phoneme>,
+}
+
+impl Syllable {
+    /// Returns the stress of the syllable's vowel.
+    pub fn stress(&self) -> Stress {
+        self.nucleus.stress().unwrap_or(Stress::Unstressed)
+    }
+
+    /// Returns the vowel and the consonants after it, which is the part that rhymes.
+    pub fn rime(&self) -> Vec<Phoneme> {
+        let mut out = Vec::with_capacity(1 + self.coda.len());
+        out.push(self.nucleus);
+        out.extend_from_slice(&self.coda);
+        return out;
+    }
+
+    /// Returns all of the phonemes of the syllable, in order.
+    pub fn phonemes(&self) -> Vec<Phoneme> {
+        let mut out = self.onset.clone();
+        out.extend(self.rime());
+        return out;
+    }
+}
+
+impl fmt::Display for Syllable {
+    /// Writes the syllable in ARPABET, e.g. "L OW1", or with `{:#}`, in IPA without any stress
+    /// mark.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phonemes = self.phonemes();
+        if f.alternate() {
+            return write!(f, "{}", ipa::to_ipa(&phonemes));
+        }
+        for (i, ph) in phonemes.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", ph)?;
+        }
+        Ok(())
+    }
+}
+
+/// Consonant clusters that can start an English syllable, besides single consonants (other
+/// than NG). See `is_onset`.
+const ONSETS: &[&[Symbol]] = &[
+    &[Symbol::P, Symbol::R],
+    &[Symbol::P, Symbol::L],
+    &[Symbol::P, Symbol::Y],
+    &[Symbol::B, Symbol::R],
+    &[Symbol::B, Symbol::L],
+    &[Symbol::B, Symbol::Y],
+    &[Symbol::T, Symbol::R],
+    &[Symbol::T, Symbol::W],
+    &[Symbol::D, Symbol::R],
+    &[Symbol::D, Symbol::W],
+    &[Symbol::K, Symbol::R],
+    &[Symbol::K, Symbol::L],
+    &[Symbol::K, Symbol::W],
+    &[Symbol::K, Symbol::Y],
+    &[Symbol::G, Symbol::R],
+    &[Symbol::G, Symbol::L],
+    &[Symbol::G, Symbol::W],
+    &[Symbol::F, Symbol::R],
+    &[Symbol::F, Symbol::L],
+    &[Symbol::F, Symbol::Y],
+    &[Symbol::V, Symbol::Y],
+    &[Symbol::TH, Symbol::R],
+    &[Symbol::TH, Symbol::W],
+    &[Symbol::SH, Symbol::R],
+    &[Symbol::HH, Symbol::Y],
+    &[Symbol::HH, Symbol::W],
+    &[Symbol::M, Symbol::Y],
+    &[Symbol::S, Symbol::P],
+    &[Symbol::S, Symbol::T],
+    &[Symbol::S, Symbol::K],
+    &[Symbol::S, Symbol::M],
+    &[Symbol::S, Symbol::N],
+    &[Symbol::S, Symbol::L],
+    &[Symbol::S, Symbol::W],
+    &[Symbol::S, Symbol::F],
+    &[Symbol::S, Symbol::P, Symbol::R],
+    &[Symbol::S, Symbol::P, Symbol::L],
+    &[Symbol::S, Symbol::P, Symbol::Y],
+    &[Symbol::S, Symbol::T, Symbol::R],
+    &[Symbol::S, Symbol::K, Symbol::R],
+    &[Symbol::S, Symbol::K, Symbol::L],
+    &[Symbol::S, Symbol::K, Symbol::W],
+    &[Symbol::S, Symbol::K, Symbol::Y],
+];
+
+/// Returns whether `consonants` can start a syllable.
+fn is_onset(consonants: &[Symbol]) -> bool {
+    match consonants {
+        [] => true,
+        [single] => *single != Symbol::NG,
+        _ => ONSETS.contains(&consonants),
+    }
+}
+
+/// Returns how many of `consonants`, which come between two vowels, start the second
+/// syllable: the most that can start a syllable.
+fn onset_len(consonants: &[Symbol]) -> usize {
+    return (0..=consonants.len())
+        .rev()
+        .find(|len| is_onset(&consonants[consonants.len() - len..]))
+        .unwrap_or(0);
+}
+
+/// Splits `phonemes` into syllables, one for each vowel. A sequence without any vowels, like
+/// "HH M" for "hmm", has no syllables.
+pub fn syllabify(phonemes: &[Phoneme]) -> Vec<Syllable> {
+    let vowels: Vec<usize> = (0..phonemes.len())
+        .filter(|i| phonemes[*i].is_vowel())
+        .collect();
+    let mut out: Vec<Syllable> = Vec::with_capacity(vowels.len());
+    for (n, vowel) in vowels.iter().enumerate() {
+        let onset_start = match n {
+            0 => 0,
+            _ => {
+                let between = &phonemes[vowels[n - 1] + 1..*vowel];
+                let symbols: Vec<Symbol> = between.iter().map(|ph| ph.symbol()).collect();
+                let onset_start = vowel - onset_len(&symbols);
+                if let Some(previous) = out.last_mut() {
+                    previous
+                        .coda
+                        .extend_from_slice(&phonemes[vowels[n - 1] + 1..onset_start]);
+                }
+                onset_start
+            }
+        };
+        out.push(Syllable {
+            onset: phonemes[onset_start..*vowel].to_vec(),
+            nucleus: phonemes[*vowel],
+            coda: vec![],
+        });
+    }
+    if let (Some(last), Some(vowel)) = (out.last_mut(), vowels.last()) {
+        last.coda.extend_from_slice(&phonemes[vowel + 1..]);
+    }
+    return out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poet::dictionary::Entry;
+
+    fn syllables(line: &str) -> Vec<String> {
+        syllabify(&Entry::new(line).phonemes.phonemes)
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_maximal_onsets() {
+        assert_eq!(syllables("hello HH AH0 L OW1"), vec!["HH AH0", "L OW1"]);
+        assert_eq!(
+            syllables("extra EH1 K S T R AH0"),
+            vec!["EH1 K", "S T R AH0"]
+        );
+        assert_eq!(
+            syllables("misspell M IH0 S S P EH1 L"),
+            vec!["M IH0 S", "S P EH1 L"]
+        );
+        assert_eq!(syllables("singer S IH1 NG ER0"), vec!["S IH1 NG", "ER0"]);
+        assert_eq!(
+            syllables("poetry P OW1 AH0 T R IY0"),
+            vec!["P OW1", "AH0", "T R IY0"]
+        );
+        assert_eq!(
+            syllables("action AE1 K SH AH0 N"),
+            vec!["AE1 K", "SH AH0 N"]
+        );
+        assert_eq!(
+            syllables("strengths S T R EH1 NG K TH S"),
+            vec!["S T R EH1 NG K TH S"]
+        );
+        assert!(syllables("hmm HH M").is_empty());
+    }
+
+    #[test]
+    fn has_parts() {
+        let phonemes = Entry::new("extra EH1 K S T R AH0").phonemes;
+        let syllables = syllabify(&phonemes.phonemes);
+        assert_eq!(syllables[0].stress(), Stress::Primary);
+        assert_eq!(syllables[1].onset, vec!["S", "T", "R"]);
+        assert_eq!(syllables[1].nucleus, "AH0");
+        assert!(syllables[1].coda.is_empty());
+        assert_eq!(syllables[0].rime(), vec!["EH1", "K"]);
+        assert_eq!(format!("{:#}", syllables[1]), "stɹə");
+    }
+}
@@ -0,0 +1,51 @@
+// Handles the panels for resolving unknown words on the /analyze page.
+//
+// Each panel lists candidate pronunciations. "Use" copies a candidate into the
+// phonemes box, "Preview" and "Ask Datamuse" fetch more candidates from the
+// server, and the form adds the word to the user dictionary.
+
+// The edit token is kept for the browser session, so it only has to be typed once.
+const TOKEN_KEY = 'poet_edit_token';
+
+document.addEventListener("DOMContentLoaded", function(event) {
+  let tokenBox = document.getElementById('edit_token');
+  if (tokenBox != null) {
+    tokenBox.value = sessionStorage.getItem(TOKEN_KEY) || '';
+    tokenBox.addEventListener('change', () => {
+      sessionStorage.setItem(TOKEN_KEY, tokenBox.value);
+    });
+  }
+
+  for (let panel of document.querySelectorAll('.unknown_word')) {
+    let word = panel.dataset.word;
+    let list = panel.querySelector('.candidates');
+    let phonemes = panel.querySelector('.pronunciation');
+    let form = panel.querySelector('.accept');
+
+    list.addEventListener('click', (e) => {
+      if (e.target.classList.contains('use')) {
+        phonemes.value = e.target.dataset.phonemes;
+      }
+    });
+    panel.querySelector('.preview').addEventListener('click', () => {
+      fetchCandidates(list, '/api/preview?word=' + encodeURIComponent(word) +
+        '&phonemes=' + encodeURIComponent(phonemes.value));
+    });
+    panel.querySelector('.datamuse').addEventListener('click', () => {
+      fetchCandidates(list, '/api/candidates?word=' + encodeURIComponent(word));
+    });
+    form.addEventListener('submit', () => {
+      // Send the latest text and the token along with the accepted pronunciation.
+      form.querySelector('.token').value = tokenBox != null ? tokenBox.value : '';
+      form.querySelector('[name=text]').value = document.querySelector('#formbox textarea').value;
+    });
+  }
+});
+
+// Fetches a list of candidates from `url` and adds them to the end of `list`.
+function fetchCandidates(list, url) {
+  fetch(url)
+    .then((response) => response.text())
+    .then((html) => list.insertAdjacentHTML('beforeend', html))
+    .catch((error) => console.error(error));
+}
@@ -42,6 +42,6 @@ function doneTyping() {
     xhr = null;
   };
   
-  xhr.open('GET', 'http://127.0.0.1:8000/api/lookup?term=' + text);
+  xhr.open('GET', '/api/lookup?term=' + encodeURIComponent(text));
   xhr.send();
 }